@@ -1,4 +1,4 @@
-import { AfterViewInit, Component, OnInit, TemplateRef, ViewChild } from '@angular/core';
+import { AfterViewInit, Component, OnDestroy, OnInit, TemplateRef, ViewChild } from '@angular/core';
 import {
   DomEvent,
   DomUtil,
@@ -36,7 +36,7 @@ const destinationIcon = 'data:image/svg+xml;base64,PD94bWwgdmVyc2lvbj0iMS4wIiA/P
   templateUrl: './map.component.html',
   styleUrls: ['./map.component.css']
 })
-export class MapComponent implements OnInit {
+export class MapComponent implements OnInit, OnDestroy {
   modalRefHelp: BsModalRef;
   modalRefData: BsModalRef;
 
@@ -60,6 +60,10 @@ export class MapComponent implements OnInit {
   markerStop: Marker;
 
   private map;
+  private events: EventSource;
+  // terminal job events which arrived while the id of the requested job was not known yet
+  private terminalJobs = new Map<number, any>();
+  private awaitingJobId = false;
 
   options: MapOptions = {
     layers: [tileLayer('https://{s}.tile.openstreetmap.org/{z}/{x}/{y}.png', {
@@ -90,7 +94,8 @@ export class MapComponent implements OnInit {
 
   buildGraph() {
     this.apiService.buildGraph({ num_nodes: 1000000 }).subscribe(
-      _ => this.showAlert('Building graph.. this might take a while', 'info'));
+      _ => this.showAlert('Building graph.. this might take a while', 'info'),
+      (error) => this.showApiError(error, 'Could not start building the graph'));
   }
 
   defineYourWaypointOnClick(e: any) {
@@ -143,23 +148,31 @@ export class MapComponent implements OnInit {
   requestBenchmark() {
     this.apiService.startBenchmark({ nmb_queries: this.benchmarkRuns }).subscribe(
       () => this.showAlert('Success, benchmark running', 'info'),
-      (error) => this.showAlert(error.toString(), 'danger')
+      (error) => this.showApiError(error, 'Could not start benchmark')
     )
   }
 
   requestRoute() {
+    this.awaitingJobId = true;
     this.apiService.route({
       lat_start: this.startLat, lon_start: this.startLon, lat_end: this.endLat, lon_end: this.endLon
     }).subscribe(
       data => {
         this.showAlert('calculating result, jobId: ' + data, 'info');
+        // the result is fetched as soon as the server reports that the job is finished
         this.jobId = data;
-        // try to fetch the result after one second
-        setTimeout(() => {
-          this.requestResult();
-        }, 1000);
+        this.awaitingJobId = false;
+        const job = this.terminalJobs.get(data);
+        this.terminalJobs.clear();
+        if (job !== undefined) {
+          this.onJobEvent(job);
+        }
       },
-      () => this.showAlert('Could not calculate result did you build the graph?', 'danger'));
+      (error) => {
+        this.awaitingJobId = false;
+        this.terminalJobs.clear();
+        this.showApiError(error, 'Could not calculate result did you build the graph?');
+      });
   }
 
   requestResult() {
@@ -182,11 +195,21 @@ export class MapComponent implements OnInit {
         this.updateMarkers(res.nodes[0].lat, res.nodes[0].lon, res.nodes[res.nodes.length - 1].lat, res.nodes[res.nodes.length - 1].lon);
         this.map.addLayer(this.currentRoute);
         this.showAlert('Success! Route length in m: ' + res.distance, 'info');
-      }, () => this.showAlert('Could not fetch result did you build the graph?' +
-        ' And did you check the id? Calculating a route might take a while', 'danger')
+      }, (error) => this.showApiError(error, 'Could not fetch result did you build the graph?' +
+        ' And did you check the id? Calculating a route might take a while')
     );
   }
 
+  // the server sends GPX, KML and RTZ routes as attachment, so the browser downloads them
+  downloadRoute(format: string) {
+    window.open(this.apiService.rootUrl + '/jobResult/' + this.jobId + '?format=' + format);
+  }
+
+  // shows the message of the JSON error body of the server, or the fallback if the server did not answer
+  showApiError(error: any, fallback: string) {
+    this.showAlert(error.error?.message ?? fallback, 'danger');
+  }
+
   showAlert(msg: string, type: string) {
     this.alerts.push({
       type,
@@ -218,5 +241,39 @@ export class MapComponent implements OnInit {
   }
 
   ngOnInit(): void {
+    this.events = new EventSource(this.apiService.rootUrl + '/events');
+    this.events.addEventListener('job', (event: MessageEvent) => this.onJobEvent(JSON.parse(event.data).job));
+    this.events.addEventListener('progress', (event: MessageEvent) => this.onProgressEvent(JSON.parse(event.data)));
+  }
+
+  ngOnDestroy(): void {
+    this.events.close();
+  }
+
+  onJobEvent(job: any) {
+    if (job.id !== this.jobId) {
+      if (this.awaitingJobId && ['finished', 'failed', 'cancelled'].includes(job.state)) {
+        this.terminalJobs.set(job.id, job);
+      }
+      return;
+    }
+    if (job.state === 'finished') {
+      this.requestResult();
+    } else if (job.state === 'failed') {
+      this.showAlert('Could not calculate route: ' + job.errorMessage, 'danger');
+    } else if (job.state === 'cancelled') {
+      this.showAlert('Route calculation ' + job.id + ' was cancelled', 'warning');
+    }
+  }
+
+  onProgressEvent(progress: any) {
+    if (!progress.finished) {
+      return;
+    }
+    if (progress.task === 'graphBuild') {
+      this.showAlert('Finished building the graph', 'success');
+    } else if (progress.task === 'benchmark') {
+      this.showAlert('Finished benchmark', 'success');
+    }
   }
 }
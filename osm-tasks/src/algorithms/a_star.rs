@@ -2,23 +2,24 @@ use std::collections::BinaryHeap;
 use crate::model::adjacency_array::AdjacencyArray;
 use crate::model::grid_graph::GridGraph;
 use crate::model::priority_heap_item::PriorityHeapItem;
+use crate::model::weight::{Weight, INFINITE_WEIGHT};
 
 pub(crate) struct AStar<'a> {
     adj_ref: AdjacencyArray,
     graph_ref: &'a GridGraph,
     heap: BinaryHeap<PriorityHeapItem>,
-    distances: Vec<u32>,
+    distances: Vec<Weight>,
     previous_nodes: Vec<u32>,
     source_node: u32,
     amount_nodes_popped: u32,
 }
 
-impl<'a> AStar<'a> {
+impl AStar<'_> {
     pub fn new(grid_graph: &GridGraph, source_node: u32) -> AStar {
         //println!("New dijkstra instance with source node {}", source_node);
         let number_of_nodes = grid_graph.nodes.len();
         let mut heap = BinaryHeap::with_capacity(number_of_nodes);
-        let distances = vec![u32::MAX; number_of_nodes];
+        let distances = vec![INFINITE_WEIGHT; number_of_nodes];
         let previous_nodes = vec![u32::MAX; number_of_nodes];
         heap.push(PriorityHeapItem {
             node_id: source_node,
@@ -26,12 +27,12 @@ impl<'a> AStar<'a> {
             priority: 0,
             previous_node: source_node,
         });
-        return AStar { adj_ref: grid_graph.adjacency_array(), graph_ref: grid_graph, heap, distances, previous_nodes, source_node, amount_nodes_popped: 0 };
+        AStar { adj_ref: grid_graph.adjacency_array(), graph_ref: grid_graph, heap, distances, previous_nodes, source_node, amount_nodes_popped: 0 }
     }
 
-    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, u32, u32)> {
+    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, Weight, u32)> {
         self.a_star(&destination_node);
-        if self.distances[destination_node as usize] != u32::MAX {
+        if self.distances[destination_node as usize] != INFINITE_WEIGHT {
             Some((self.traverse_route(&destination_node), self.distances[destination_node as usize], self.amount_nodes_popped))
         } else {
             None
@@ -49,21 +50,22 @@ impl<'a> AStar<'a> {
                 }
                 self.previous_nodes[heap_element.node_id as usize] = heap_element.previous_node;
                 self.distances[heap_element.node_id as usize] = heap_element.distance;
-                let neighbors_and_distances = self.adj_ref.get_neighbors_of_node_and_distances(heap_element.node_id);
+                let (neighbors, distances) = self.adj_ref.get_neighbors_of_node_and_distances(heap_element.node_id);
                 //println!("distance {}", dist_to_dest);
-                for i in (0..neighbors_and_distances.len()).step_by(2) {
-                    let next_node = neighbors_and_distances[i];
-                    let next_node_distance = neighbors_and_distances[i + 1] as u64;
+                for i in 0..neighbors.len() {
+                    let next_node = neighbors[i];
+                    let next_node_distance = distances[i];
 
                     // heuristic
                     let heuristic = self.graph_ref.get_distance(next_node, *destination_node);
 
-                    if self.distances[next_node as usize] == u32::MAX {
+                    if self.distances[next_node as usize] == INFINITE_WEIGHT {
                         //println!("add edge form {} to {} with dist {}", heap_element.node_id, next_node, next_node_distance);
+                        let distance = heap_element.distance.saturating_add(next_node_distance);
                         self.heap.push(PriorityHeapItem {
                             node_id: next_node,
-                            distance: (next_node_distance as u32) + heap_element.distance,
-                            priority: next_node_distance + (heap_element.distance as u64) + heuristic,
+                            distance,
+                            priority: distance.saturating_add(heuristic),
                             previous_node: heap_element.node_id,
                         });
                     }
@@ -88,6 +90,6 @@ impl<'a> AStar<'a> {
         }
         nodes.push(self.source_node);
         nodes.reverse();
-        return nodes;
+        nodes
     }
 }
@@ -2,33 +2,34 @@ use std::collections::{BinaryHeap};
 use crate::model::adjacency_array::AdjacencyArray;
 use crate::model::grid_graph::GridGraph;
 use crate::model::heap_item::HeapItem;
+use crate::model::weight::{Weight, INFINITE_WEIGHT};
 
 pub(crate) struct BdDijkstra<'a> {
     graph_ref: &'a GridGraph,
     forward_heap: BinaryHeap<HeapItem>,
     backward_heap: BinaryHeap<HeapItem>,
-    forward_distances: Vec<u32>,
-    backward_distances: Vec<u32>,
+    forward_distances: Vec<Weight>,
+    backward_distances: Vec<Weight>,
     forward_previous_nodes: Vec<u32>,
     backward_previous_nodes: Vec<u32>,
     source_node: u32,
     amount_nodes_popped_forward: usize,
     amount_nodes_popped_backward: usize,
-    mu: u32,
+    mu: Weight,
     meeting_node: u32,
 }
 
-impl<'a> BdDijkstra<'a> {
+impl BdDijkstra<'_> {
     pub fn new(graph: &GridGraph, source_node: u32) -> BdDijkstra {
-        let number_of_nodes = graph.nodes.len() as usize;
+        let number_of_nodes = graph.nodes.len();
         let forward_heap = BinaryHeap::with_capacity(number_of_nodes);
         let backward_heap = BinaryHeap::with_capacity(number_of_nodes);
-        let forward_distances = vec![u32::MAX; number_of_nodes];
-        let backward_distances = vec![u32::MAX; number_of_nodes];
+        let forward_distances = vec![INFINITE_WEIGHT; number_of_nodes];
+        let backward_distances = vec![INFINITE_WEIGHT; number_of_nodes];
         let forward_previous_nodes = vec![u32::MAX; number_of_nodes];
         let backward_previous_nodes = vec![u32::MAX; number_of_nodes];
 
-        return BdDijkstra {
+        BdDijkstra {
             graph_ref: graph,
             forward_heap,
             backward_heap,
@@ -39,12 +40,12 @@ impl<'a> BdDijkstra<'a> {
             source_node,
             amount_nodes_popped_forward: 0,
             amount_nodes_popped_backward: 0,
-            mu: u32::MAX,
+            mu: INFINITE_WEIGHT,
             meeting_node: u32::MAX
-        };
+        }
     }
 
-    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, u32, u32)> {
+    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, Weight, u32)> {
         let meeting_node = self.bd_dijkstra(self.source_node, destination_node);
 
         let mut route = vec![];
@@ -63,14 +64,14 @@ impl<'a> BdDijkstra<'a> {
         route.push(destination_node);
 
         Some((route,
-              self.forward_distances[meeting_node as usize] + self.backward_distances[meeting_node as usize],
+              self.forward_distances[meeting_node as usize].saturating_add(self.backward_distances[meeting_node as usize]),
               (self.amount_nodes_popped_forward + self.amount_nodes_popped_backward) as u32))
     }
 
     fn bd_dijkstra(&mut self, source_node:u32, destination_node: u32) -> u32 {
         let adj_array = self.graph_ref.adjacency_array();
         self.meeting_node = u32::MAX;
-        self.mu = u32::MAX;
+        self.mu = INFINITE_WEIGHT;
 
         self.backward_heap.push(HeapItem {
             node_id: destination_node,
@@ -86,7 +87,7 @@ impl<'a> BdDijkstra<'a> {
         self.backward_distances[destination_node as usize] = 0;
 
         loop {
-            let curr_mu = self.forward_heap.peek().unwrap().distance + self.backward_heap.peek().unwrap().distance;
+            let curr_mu = self.forward_heap.peek().unwrap().distance.saturating_add(self.backward_heap.peek().unwrap().distance);
 
             if curr_mu >= self.mu {
                 return self.meeting_node;
@@ -104,16 +105,16 @@ impl<'a> BdDijkstra<'a> {
         let current = self.forward_heap.pop();
         if let Some(curr) = current {
             self.amount_nodes_popped_forward += 1;
-            let neighbors_and_distances = adj_array.get_neighbors_of_node_and_distances(curr.node_id);
+            let (neighbors, distances) = adj_array.get_neighbors_of_node_and_distances(curr.node_id);
 
             // iterate over children
-            for i in (0..neighbors_and_distances.len()).step_by(2) {
-                let neighbor = neighbors_and_distances[i];
-                let neighbor_distance = neighbors_and_distances[i + 1];
+            for i in 0..neighbors.len() {
+                let neighbor = neighbors[i];
+                let neighbor_distance = distances[i];
 
-                let score = curr.distance + neighbor_distance;
+                let score = curr.distance.saturating_add(neighbor_distance);
 
-                if self.forward_distances[neighbor as usize] == u32::MAX || self.forward_distances[neighbor as usize] > score {
+                if self.forward_distances[neighbor as usize] == INFINITE_WEIGHT || self.forward_distances[neighbor as usize] > score {
                     // we did not encounter this node before
                     self.forward_previous_nodes[neighbor as usize] = curr.node_id;
                     self.forward_distances[neighbor as usize] = score;
@@ -132,16 +133,16 @@ impl<'a> BdDijkstra<'a> {
         let current = self.backward_heap.pop();
         if let Some(curr) = current {
             self.amount_nodes_popped_backward += 1;
-            let neighbors_and_distances = adj_array.get_neighbors_of_node_and_distances(curr.node_id);
+            let (neighbors, distances) = adj_array.get_neighbors_of_node_and_distances(curr.node_id);
 
             // iterate over children
-            for i in (0..neighbors_and_distances.len()).step_by(2) {
-                let neighbor = neighbors_and_distances[i];
-                let neighbor_distance = neighbors_and_distances[i + 1];
+            for i in 0..neighbors.len() {
+                let neighbor = neighbors[i];
+                let neighbor_distance = distances[i];
 
-                let score = curr.distance + neighbor_distance;
+                let score = curr.distance.saturating_add(neighbor_distance);
 
-                if self.backward_distances[neighbor as usize] == u32::MAX || self.backward_distances[neighbor as usize] > score {
+                if self.backward_distances[neighbor as usize] == INFINITE_WEIGHT || self.backward_distances[neighbor as usize] > score {
                     // we did not encounter this node before
                     self.backward_previous_nodes[neighbor as usize] = curr.node_id;
                     self.backward_distances[neighbor as usize] = score;
@@ -156,10 +157,10 @@ impl<'a> BdDijkstra<'a> {
         }
     }
 
-    fn update_best_path_forward(&mut self, neighbor: usize, score: u32) -> bool {
-        if self.backward_previous_nodes[neighbor as usize] != u32::MAX {
+    fn update_best_path_forward(&mut self, neighbor: usize, score: Weight) -> bool {
+        if self.backward_previous_nodes[neighbor] != u32::MAX {
             // backward search already found this node
-            let new_mu = self.backward_distances[neighbor as usize] + score;
+            let new_mu = self.backward_distances[neighbor].saturating_add(score);
             if self.mu > new_mu {
                 self.mu = new_mu;
                 self.meeting_node = neighbor as u32;
@@ -169,10 +170,10 @@ impl<'a> BdDijkstra<'a> {
         false
     }
 
-    fn update_best_path_backward(&mut self, neighbor: usize, score: u32) -> bool {
-        if self.forward_previous_nodes[neighbor as usize] != u32::MAX {
+    fn update_best_path_backward(&mut self, neighbor: usize, score: Weight) -> bool {
+        if self.forward_previous_nodes[neighbor] != u32::MAX {
             // backward search already found this node
-            let new_mu = self.forward_distances[neighbor as usize] + score;
+            let new_mu = self.forward_distances[neighbor].saturating_add(score);
             if self.mu > new_mu {
                 self.mu = new_mu;
                 self.meeting_node = neighbor as u32;
@@ -1,12 +1,15 @@
 use std::collections::{HashMap};
 use crate::model::adjacency_array::AdjacencyArray;
 use crate::model::grid_graph::{GridGraph, Edge};
-use crate::model::heap_item::HeapItem;
 use crate::algorithms::witness_search::WitnessSearch;
 use rand::Rng;
 use rand::distributions::Uniform;
 use crate::model::cn_model::{Shortcut, CNMetadata};
 use std::time::Instant;
+use crate::persistence::event_bus::EventBus;
+use crate::persistence::build_tracker::BuildTracker;
+use crate::model::server_event::Task;
+use crate::model::build_status::BuildPhase;
 
 pub(crate) struct CNGraphCreator<'a> {
     graph_ref: &'a GridGraph,
@@ -17,25 +20,27 @@ pub(crate) struct CNGraphCreator<'a> {
     get_shortcut: HashMap<String, Shortcut>,
 }
 
-impl<'a> CNGraphCreator<'a> {
+impl CNGraphCreator<'_> {
     pub fn new(graph: &GridGraph) -> CNGraphCreator {
-        let number_of_nodes = graph.nodes.len() as usize;
-
-        return CNGraphCreator {
+        CNGraphCreator {
             graph_ref: graph,
             modified_graph: GridGraph {
                 number_edges: 0,
                 number_nodes: 0,
                 edges: vec![],
                 nodes: vec![],
+                component_ids: vec![],
+                removed_nodes: vec![],
+                grid_type: graph.grid_type,
             },
             contracted_nodes: HashMap::new(),
             get_shortcut: HashMap::new(),
-        };
+        }
     }
 
     pub fn build_cn_graph(&mut self) -> CNMetadata {
         println!("starting to create cn metadata");
+        BuildTracker::global().phase(BuildPhase::ChPreprocessing);
         let number_edges_before = self.graph_ref.edges.concat().len();
         let mut collected_nodes = 0.0;
         self.modified_graph = (*self.graph_ref).clone();
@@ -52,12 +57,12 @@ impl<'a> CNGraphCreator<'a> {
             // create independent set by picking nodes randomly
             let mut disallowed_nodes: HashMap<u32, bool> = HashMap::new();
             let mut has_enough_nodes = false;
-            let mut independent_set = Vec::with_capacity(i_set_size as usize);
+            let mut independent_set = Vec::with_capacity(i_set_size);
             let range = Uniform::from(0..(self.modified_graph.nodes.len() as u32));
             let sample_size = i_set_size * 2;
 
             while !has_enough_nodes {
-                let choices: Vec<u32> = rand::thread_rng().sample_iter(&range).take(sample_size as usize).collect();
+                let choices: Vec<u32> = rand::thread_rng().sample_iter(&range).take(sample_size).collect();
                 //println!("generated random numbers {} ms", start_time.elapsed().as_millis());
                 let mut cnt: i32 = -1;
                 'node: for node in choices {
@@ -69,9 +74,9 @@ impl<'a> CNGraphCreator<'a> {
                         }
                         continue 'node;
                     }
-                    let neighbors_distances = modified_adj_array.get_neighbors_of_node_and_distances(node);
-                    for neighbor in (0..neighbors_distances.len()).step_by(2) {
-                        if disallowed_nodes.contains_key(&(neighbor as u32)) {
+                    let (neighbors, _) = modified_adj_array.get_neighbors_of_node_and_distances(node);
+                    for neighbor in neighbors.iter() {
+                        if disallowed_nodes.contains_key(neighbor) {
                             continue 'node;
                         }
                     }
@@ -81,7 +86,7 @@ impl<'a> CNGraphCreator<'a> {
                     //println!("set size {}, i_set_size {}", independent_set.len(), i_set_size);
 
                     // stopping condition, we reached i_set_size nodes
-                    if independent_set.len() == i_set_size as usize {
+                    if independent_set.len() == i_set_size {
                         has_enough_nodes = true;
                         break 'node;
                     }
@@ -92,23 +97,24 @@ impl<'a> CNGraphCreator<'a> {
             let mut rank_map: Vec<Vec<u32>> = vec![vec![]; 15];
 
             // create map of ranks and remove nodes from graph
-            for i in 0..independent_set.len() {
+            for node in independent_set.iter() {
                 collected_nodes += 1.0;
 
                 // contraction order heuristic: out-degree
-                let curr_rank = calc_number_edges(independent_set[i], &self.graph_ref) as usize;
-                rank_map[curr_rank].push(independent_set[i]);
+                let curr_rank = calc_number_edges(*node, self.graph_ref) as usize;
+                rank_map[curr_rank].push(*node);
             }
             println!("collected nodes {} in {} ms", collected_nodes, start_time.elapsed().as_millis());
+            BuildTracker::global().progress((collected_nodes / ((9.0 / 10.0) * self.graph_ref.number_nodes as f64) * 100.0) as u32);
 
             let mut index_max_nmb_nodes_in_rank= 0;
             let mut max_nmb_nodes = 0;
-            for rank in 0..15 {
-                if rank_map[rank].len() > max_nmb_nodes {
-                    max_nmb_nodes = rank_map[rank].len();
+            for (rank, nodes) in rank_map.iter().enumerate() {
+                if nodes.len() > max_nmb_nodes {
+                    max_nmb_nodes = nodes.len();
                     index_max_nmb_nodes_in_rank = rank;
                 }
-                println!("rank {}, len {}", rank, rank_map[rank].len());
+                println!("rank {}, len {}", rank, nodes.len());
             }
             // we don't create shortcuts for the core
             rank_map[index_max_nmb_nodes_in_rank] = vec![];
@@ -127,9 +133,9 @@ impl<'a> CNGraphCreator<'a> {
             println!("found shortcuts between nodes, nmb already collected: {}, time: {} ms", self.get_shortcut.keys().len(), start_time.elapsed().as_millis());
 
             // remove nodes for sweeps after this one
-            for i in 0..independent_set.len() {
-                self.modified_graph.remove_node(independent_set[i]);
-                removed_nodes.insert(independent_set[i], true);
+            for node in independent_set.iter() {
+                self.modified_graph.remove_node(*node);
+                removed_nodes.insert(*node, true);
             }
             println!("removed nodes from graph in {}", start_time.elapsed().as_millis());
         }
@@ -145,10 +151,11 @@ impl<'a> CNGraphCreator<'a> {
         println!("added {} shortcuts", self.get_shortcut.keys().len());
 
         println!("finished building cn metadata - started copying graph after {} ms", start_time.elapsed().as_millis());
-        return CNMetadata {
+        EventBus::global().publish_finished(Task::CnMetadata);
+        CNMetadata {
             graph: final_graph,
             get_shortcut: self.get_shortcut.clone()
-        };
+        }
     }
 
     fn find_shortcuts(&mut self, node: u32, dest: &[u32], adj_array: &AdjacencyArray, removed_nodes: &HashMap<u32, bool>) {
@@ -160,12 +167,10 @@ impl<'a> CNGraphCreator<'a> {
         dijkstra.change_source_node(node);
         // TODO check if uvw = length of route found (v contracted, u,w neighbors) [STALL ON DEMAND]
         // TODO this ensures no suboptimal shortcuts are added (and that u is always included)
-        let dist_uvw = 0;
         if let Some(result) = dijkstra.find_route(dest) {
             let routes = result.0;
             let distances = result.1;
-            let mut counter = 0;
-            for route in routes {
+            for (counter, route) in routes.into_iter().enumerate() {
                 let edge = Edge {
                     source: route[0],
                     target: route[route.len() - 1],
@@ -183,13 +188,11 @@ impl<'a> CNGraphCreator<'a> {
                     });
                     self.modified_graph.add_new_edge(edge);
                 }
-
-                counter += 1;
             }
         }
     }
 }
 
 fn calc_number_edges(v: u32, graph: &GridGraph) -> u32 {
-    return graph.edges[v as usize].len() as u32;
+    graph.edges[v as usize].len() as u32
 }
@@ -3,36 +3,36 @@ use crate::model::adjacency_array::AdjacencyArray;
 use crate::model::grid_graph::GridGraph;
 use crate::model::cn_model::CNMetadata;
 use crate::model::priority_heap_item::PriorityHeapItem;
-use crate::model::heap_item::HeapItem;
+use crate::model::weight::{Weight, INFINITE_WEIGHT};
 
 pub(crate) struct CNBdDijkstra<'a> {
     meta: &'a CNMetadata,
     graph_ref: &'a GridGraph,
     forward_heap: BinaryHeap<PriorityHeapItem>,
     backward_heap: BinaryHeap<PriorityHeapItem>,
-    forward_distances: Vec<u32>,
-    backward_distances: Vec<u32>,
+    forward_distances: Vec<Weight>,
+    backward_distances: Vec<Weight>,
     forward_previous_nodes: Vec<u32>,
     backward_previous_nodes: Vec<u32>,
     source_node: u32,
     destination_node: u32,
     amount_nodes_popped_forward: usize,
     amount_nodes_popped_backward: usize,
-    mu: u32,
+    mu: Weight,
     meeting_node: u32,
 }
 
-impl<'a> CNBdDijkstra<'a> {
+impl CNBdDijkstra<'_> {
     pub fn new(meta: &CNMetadata, source_node: u32) -> CNBdDijkstra {
-        let number_of_nodes = meta.graph.nodes.len() as usize;
+        let number_of_nodes = meta.graph.nodes.len();
         let forward_heap = BinaryHeap::with_capacity(number_of_nodes);
         let backward_heap = BinaryHeap::with_capacity(number_of_nodes);
-        let forward_distances = vec![u32::MAX; number_of_nodes];
-        let backward_distances = vec![u32::MAX; number_of_nodes];
+        let forward_distances = vec![INFINITE_WEIGHT; number_of_nodes];
+        let backward_distances = vec![INFINITE_WEIGHT; number_of_nodes];
         let forward_previous_nodes = vec![u32::MAX; number_of_nodes];
         let backward_previous_nodes = vec![u32::MAX; number_of_nodes];
 
-        return CNBdDijkstra {
+        CNBdDijkstra {
             meta,
             graph_ref: &meta.graph,
             forward_heap,
@@ -45,12 +45,12 @@ impl<'a> CNBdDijkstra<'a> {
             source_node,
             amount_nodes_popped_forward: 0,
             amount_nodes_popped_backward: 0,
-            mu: u32::MAX,
+            mu: INFINITE_WEIGHT,
             meeting_node: u32::MAX,
-        };
+        }
     }
 
-    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, u32, u32)> {
+    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, Weight, u32)> {
         let meeting_node = self.bd_dijkstra(self.source_node, destination_node);
         self.destination_node = destination_node;
 
@@ -73,11 +73,11 @@ impl<'a> CNBdDijkstra<'a> {
         let complete_route = self.unwrap_shortcuts(&route);
 
         Some((complete_route,
-              self.forward_distances[meeting_node as usize] + self.backward_distances[meeting_node as usize],
+              self.forward_distances[meeting_node as usize].saturating_add(self.backward_distances[meeting_node as usize]),
               (self.amount_nodes_popped_forward + self.amount_nodes_popped_backward) as u32))
     }
 
-    fn unwrap_shortcuts(&self, route: &Vec<u32>) -> Vec<u32> {
+    fn unwrap_shortcuts(&self, route: &[u32]) -> Vec<u32> {
         let mut result: Vec<u32> = vec![];
 
         for i in 0..route.len() {
@@ -88,7 +88,8 @@ impl<'a> CNBdDijkstra<'a> {
                 if let Some((_key, shortcut)) = self.meta.get_shortcut.get_key_value(&key) {
                     // found shortcut, unwrap
                     result.push(source);
-                    result.append(&mut self.unwrap_shortcuts(&shortcut.replaced_edges[1..shortcut.replaced_edges.len()-1].to_vec()));
+                    result.append(&mut self.unwrap_shortcuts(&shortcut.replaced_edges[1..shortcut.replaced_edges.len()-1]
+));
                 } else {
                     result.push(source);
                 }
@@ -96,13 +97,13 @@ impl<'a> CNBdDijkstra<'a> {
                 result.push(source);
             }
         }
-        return result;
+        result
     }
 
     fn bd_dijkstra(&mut self, source_node: u32, destination_node: u32) -> u32 {
         let adj_array = self.graph_ref.adjacency_array();
         self.meeting_node = u32::MAX;
-        self.mu = u32::MAX;
+        self.mu = INFINITE_WEIGHT;
 
         self.backward_heap.push(PriorityHeapItem {
             node_id: destination_node,
@@ -120,7 +121,7 @@ impl<'a> CNBdDijkstra<'a> {
         self.backward_distances[destination_node as usize] = 0;
 
         loop {
-            let curr_mu = self.forward_heap.peek().unwrap().distance + self.backward_heap.peek().unwrap().distance;
+            let curr_mu = self.forward_heap.peek().unwrap().distance.saturating_add(self.backward_heap.peek().unwrap().distance);
 
             if curr_mu > self.mu {
                 return self.meeting_node;
@@ -138,25 +139,25 @@ impl<'a> CNBdDijkstra<'a> {
         let current = self.forward_heap.pop();
         if let Some(curr) = current {
             self.amount_nodes_popped_forward += 1;
-            let neighbors_and_distances = adj_array.get_neighbors_of_node_and_distances(curr.node_id);
+            let (neighbors, distances) = adj_array.get_neighbors_of_node_and_distances(curr.node_id);
 
-            let rank = neighbors_and_distances.len() / 2;
+            let rank = neighbors.len();
 
             // iterate over children
-            for i in (0..neighbors_and_distances.len()).step_by(2) {
-                let neighbor = neighbors_and_distances[i];
-                let neighbor_distance = neighbors_and_distances[i + 1];
+            for i in 0..neighbors.len() {
+                let neighbor = neighbors[i];
+                let neighbor_distance = distances[i];
 
-                let score = curr.distance + neighbor_distance;
-                let mut priority = score as u64;
+                let score = curr.distance.saturating_add(neighbor_distance);
+                let priority = score;
 
 
                 let key = curr.node_id.to_string() + "_" + &*neighbor.to_string();
-                if rank < 6 && self.meta.get_shortcut.contains_key(&*key) == false {
+                if rank < 6 && !self.meta.get_shortcut.contains_key(&*key) {
                     //continue;
                 }
 
-                if self.forward_distances[neighbor as usize] == u32::MAX || self.forward_distances[neighbor as usize] > score {
+                if self.forward_distances[neighbor as usize] == INFINITE_WEIGHT || self.forward_distances[neighbor as usize] > score {
                     // we did not encounter this node before
                     self.forward_previous_nodes[neighbor as usize] = curr.node_id;
                     self.forward_distances[neighbor as usize] = score;
@@ -176,25 +177,25 @@ impl<'a> CNBdDijkstra<'a> {
         let current = self.backward_heap.pop();
         if let Some(curr) = current {
             self.amount_nodes_popped_backward += 1;
-            let neighbors_and_distances = adj_array.get_neighbors_of_node_and_distances(curr.node_id);
+            let (neighbors, distances) = adj_array.get_neighbors_of_node_and_distances(curr.node_id);
 
-            let rank = neighbors_and_distances.len() / 2;
+            let rank = neighbors.len();
 
             // iterate over children
-            for i in (0..neighbors_and_distances.len()).step_by(2) {
-                let neighbor = neighbors_and_distances[i];
-                let neighbor_distance = neighbors_and_distances[i + 1];
+            for i in 0..neighbors.len() {
+                let neighbor = neighbors[i];
+                let neighbor_distance = distances[i];
 
-                let score = curr.distance + neighbor_distance;
-                let mut priority = score as u64;
+                let score = curr.distance.saturating_add(neighbor_distance);
+                let priority = score;
 
 
                 let key = curr.node_id.to_string() + "_" + &*neighbor.to_string();
-                if rank < 6 && self.meta.get_shortcut.contains_key(&*key) == false {
+                if rank < 6 && !self.meta.get_shortcut.contains_key(&*key) {
                     //continue;
                 }
 
-                if self.backward_distances[neighbor as usize] == u32::MAX || self.backward_distances[neighbor as usize] > score {
+                if self.backward_distances[neighbor as usize] == INFINITE_WEIGHT || self.backward_distances[neighbor as usize] > score {
                     // we did not encounter this node before
                     self.backward_previous_nodes[neighbor as usize] = curr.node_id;
                     self.backward_distances[neighbor as usize] = score;
@@ -210,10 +211,10 @@ impl<'a> CNBdDijkstra<'a> {
         }
     }
 
-    fn update_best_path_forward(&mut self, neighbor: usize, score: u32) -> bool {
-        if self.backward_previous_nodes[neighbor as usize] != u32::MAX {
+    fn update_best_path_forward(&mut self, neighbor: usize, score: Weight) -> bool {
+        if self.backward_previous_nodes[neighbor] != u32::MAX {
             // backward search already found this node
-            let new_mu = self.backward_distances[neighbor as usize] + score;
+            let new_mu = self.backward_distances[neighbor].saturating_add(score);
             if self.mu > new_mu {
                 self.mu = new_mu;
                 self.meeting_node = neighbor as u32;
@@ -223,10 +224,10 @@ impl<'a> CNBdDijkstra<'a> {
         false
     }
 
-    fn update_best_path_backward(&mut self, neighbor: usize, score: u32) -> bool {
-        if self.forward_previous_nodes[neighbor as usize] != u32::MAX {
+    fn update_best_path_backward(&mut self, neighbor: usize, score: Weight) -> bool {
+        if self.forward_previous_nodes[neighbor] != u32::MAX {
             // backward search already found this node
-            let new_mu = self.forward_distances[neighbor as usize] + score;
+            let new_mu = self.forward_distances[neighbor].saturating_add(score);
             if self.mu > new_mu {
                 self.mu = new_mu;
                 self.meeting_node = neighbor as u32;
@@ -0,0 +1,123 @@
+use crate::model::adjacency_array::AdjacencyArray;
+
+/// Calculates the strongly connected components of the graph with an iterative version of Tarjan's
+/// algorithm. Returns the component id of each node, ids are assigned in order of discovery.
+pub(crate) fn strongly_connected_components(graph: &AdjacencyArray) -> Vec<u32> {
+    let number_of_nodes = graph.get_nodes_count() as usize;
+    let mut index = vec![u32::MAX; number_of_nodes];
+    let mut low_link = vec![u32::MAX; number_of_nodes];
+    let mut on_stack = vec![false; number_of_nodes];
+    let mut component_ids = vec![u32::MAX; number_of_nodes];
+    let mut stack: Vec<u32> = Vec::new();
+    // (node, position of the next neighbor to visit in the neighbors slice)
+    let mut call_stack: Vec<(u32, usize)> = Vec::new();
+    let mut next_index = 0;
+    let mut next_component_id = 0;
+
+    for root in 0..number_of_nodes as u32 {
+        if index[root as usize] != u32::MAX {
+            continue;
+        }
+        call_stack.push((root, 0));
+        while let Some((node, neighbor_position)) = call_stack.pop() {
+            if neighbor_position == 0 {
+                // first visit of this node
+                index[node as usize] = next_index;
+                low_link[node as usize] = next_index;
+                next_index += 1;
+                stack.push(node);
+                on_stack[node as usize] = true;
+            }
+            let (neighbors, _) = graph.get_neighbors_of_node_and_distances(node);
+            let mut descended = false;
+            let mut i = neighbor_position;
+            while i < neighbors.len() {
+                let neighbor = neighbors[i];
+                i += 1;
+                if index[neighbor as usize] == u32::MAX {
+                    // continue with the next neighbor after the neighbor has been processed
+                    call_stack.push((node, i));
+                    call_stack.push((neighbor, 0));
+                    descended = true;
+                    break;
+                } else if on_stack[neighbor as usize] {
+                    low_link[node as usize] = low_link[node as usize].min(index[neighbor as usize]);
+                }
+            }
+            if descended {
+                continue;
+            }
+            if low_link[node as usize] == index[node as usize] {
+                // node is the root of a component
+                loop {
+                    let member = stack.pop().expect("node of component has to be on the stack");
+                    on_stack[member as usize] = false;
+                    component_ids[member as usize] = next_component_id;
+                    if member == node {
+                        break;
+                    }
+                }
+                next_component_id += 1;
+            }
+            if let Some((parent, _)) = call_stack.last() {
+                low_link[*parent as usize] = low_link[*parent as usize].min(low_link[node as usize]);
+            }
+        }
+    }
+    component_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(number_of_nodes: usize, edges: &[(u32, u32)]) -> AdjacencyArray {
+        let mut offsets = vec![0];
+        let mut targets = Vec::new();
+        for node in 0..number_of_nodes as u32 {
+            targets.extend(edges.iter().filter(|(source, _)| *source == node).map(|(_, target)| *target));
+            offsets.push(targets.len() as u32);
+        }
+        let weights = vec![1; targets.len()];
+        AdjacencyArray::new(offsets, targets, weights)
+    }
+
+    #[test]
+    fn finds_cycles_and_one_way_connections() {
+        // cycle 0 -> 1 -> 2 -> 0, one way edge 2 -> 3, cycle 3 <-> 4, isolated node 5
+        let component_ids = strongly_connected_components(&graph(6, &[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 3)]));
+        assert_eq!(component_ids[0], component_ids[1]);
+        assert_eq!(component_ids[1], component_ids[2]);
+        assert_eq!(component_ids[3], component_ids[4]);
+        assert_ne!(component_ids[0], component_ids[3]);
+        assert_ne!(component_ids[5], component_ids[0]);
+        assert_ne!(component_ids[5], component_ids[3]);
+    }
+
+    #[test]
+    fn nested_cycles_form_one_component() {
+        // 0 -> 1 -> 2 -> 1 and 2 -> 3 -> 0
+        let component_ids = strongly_connected_components(&graph(4, &[(0, 1), (1, 2), (2, 1), (2, 3), (3, 0)]));
+        assert!(component_ids.iter().all(|id| *id == component_ids[0]));
+    }
+
+    #[test]
+    fn long_path_does_not_overflow_the_stack() {
+        let number_of_nodes = 200_000;
+        let edges: Vec<(u32, u32)> = (0..number_of_nodes as u32 - 1).flat_map(|i| vec![(i, i + 1), (i + 1, i)]).collect();
+        let mut offsets = vec![0];
+        let mut targets = Vec::new();
+        for (source, target) in edges.iter() {
+            while offsets.len() <= *source as usize {
+                offsets.push(targets.len() as u32);
+            }
+            targets.push(*target);
+        }
+        while offsets.len() <= number_of_nodes {
+            offsets.push(targets.len() as u32);
+        }
+        let weights = vec![1; targets.len()];
+        let component_ids = strongly_connected_components(&AdjacencyArray::new(offsets, targets, weights));
+        assert!(component_ids.iter().all(|id| *id == 0));
+    }
+}
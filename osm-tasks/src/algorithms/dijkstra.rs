@@ -1,6 +1,12 @@
 use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use crate::model::adjacency_array::AdjacencyArray;
 use crate::model::heap_item::HeapItem;
+use crate::model::weight::{Weight, INFINITE_WEIGHT};
+
+// number of popped nodes after which the cancel flag is checked
+const CANCEL_CHECK_INTERVAL: u32 = 1024;
 
 #[allow(dead_code)]
 pub(crate) struct DummyGraph {
@@ -12,8 +18,8 @@ pub(crate) struct DummyGraph {
 impl DummyGraph {
     pub(crate) fn init() -> DummyGraph {
         let offsets_old = vec![0, 2, 5, 8, 11, 16, 19, 22, 24];
-        let edges_target = vec![1, 4, 0, 2, 4, 1, 3, 4, 2, 4, 6, 0, 1, 2, 3, 5, 4, 6, 7, 3, 5, 7, 5, 6];
-        let edges_distance = vec![2, 1, 2, 2, 1, 2, 1, 2, 1, 3, 2, 1, 1, 2, 3, 1, 1, 3, 1, 2, 3, 1, 1, 1];
+        let edges_target = [1, 4, 0, 2, 4, 1, 3, 4, 2, 4, 6, 0, 1, 2, 3, 5, 4, 6, 7, 3, 5, 7, 5, 6];
+        let edges_distance = [2, 1, 2, 2, 1, 2, 1, 2, 1, 3, 2, 1, 1, 2, 3, 1, 1, 3, 1, 2, 3, 1, 1, 1];
         let offsets: Vec<u32> = offsets_old.into_iter().map(|i| { i * 2 }).collect();
         let mut edges = Vec::with_capacity(edges_target.len() * 2);
         for i in 0..edges_target.len() {
@@ -38,27 +44,27 @@ impl DummyGraph {
 }
 
 pub(crate) struct Dijkstra {
-    graph_ref: AdjacencyArray,
+    graph_ref: Arc<AdjacencyArray>,
     heap: BinaryHeap<HeapItem>,
-    distances: Vec<u32>,
+    distances: Vec<Weight>,
     previous_nodes: Vec<u32>,
     source_node: u32,
     amount_nodes_popped: u32,
 }
 
 impl Dijkstra {
-    pub fn new(graph: AdjacencyArray, source_node: u32) -> Dijkstra {
+    pub fn new(graph: Arc<AdjacencyArray>, source_node: u32) -> Dijkstra {
         //println!("New dijkstra instance with source node {}", source_node);
         let number_of_nodes = graph.get_nodes_count() as usize;
         let mut heap = BinaryHeap::with_capacity(number_of_nodes);
-        let distances = vec![u32::MAX; number_of_nodes];
+        let distances = vec![INFINITE_WEIGHT; number_of_nodes];
         let previous_nodes = vec![u32::MAX; number_of_nodes];
         heap.push(HeapItem {
             node_id: source_node,
             distance: 0,
             previous_node: source_node,
         });
-        return Dijkstra { graph_ref: graph, heap, distances, previous_nodes, source_node, amount_nodes_popped: 0 };
+        Dijkstra { graph_ref: graph, heap, distances, previous_nodes, source_node, amount_nodes_popped: 0 }
     }
 
     pub fn change_source_node(&mut self, source_node: u32) {
@@ -73,25 +79,35 @@ impl Dijkstra {
             distance: 0,
             previous_node: source_node,
         });
-        self.distances.fill(u32::MAX);
+        self.distances.fill(INFINITE_WEIGHT);
         self.previous_nodes.fill(u32::MAX);
     }
 
-    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, u32, u32)> {
+    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, Weight, u32)> {
+        self.find_route_cancellable(destination_node, &AtomicBool::new(false))
+    }
+
+    /// Like find_route, but stops the search as soon as the cancelled flag is set. The search can be
+    /// continued by the next query with the same source node.
+    pub fn find_route_cancellable(&mut self, destination_node: u32, cancelled: &AtomicBool) -> Option<(Vec<u32>, Weight, u32)> {
         /* disable caching
-        if self.distances[destination_node as usize] != u32::MAX {
+        if self.distances[destination_node as usize] != INFINITE_WEIGHT {
             return Some((self.traverse_route(&destination_node), self.distances[destination_node as usize]));
         } */
-        self.dijkstra(&destination_node);
-        if self.distances[destination_node as usize] != u32::MAX {
+        self.dijkstra(&destination_node, cancelled);
+        if self.distances[destination_node as usize] != INFINITE_WEIGHT {
             Some((self.traverse_route(&destination_node), self.distances[destination_node as usize], self.amount_nodes_popped))
         } else {
             None
         }
     }
 
-    fn dijkstra(&mut self, destination_node: &u32) {
+    fn dijkstra(&mut self, destination_node: &u32, cancelled: &AtomicBool) {
         loop {
+            if self.amount_nodes_popped % CANCEL_CHECK_INTERVAL == 0 && cancelled.load(Ordering::Relaxed) {
+                println!("Cancelled search from {} to {}", self.source_node, destination_node);
+                return;
+            }
             if let Some(heap_element) = self.heap.pop() {
                 self.amount_nodes_popped += 1;
                 //println!("Popped element from heap {}", heap_element);
@@ -101,15 +117,15 @@ impl Dijkstra {
                 }
                 self.previous_nodes[heap_element.node_id as usize] = heap_element.previous_node;
                 self.distances[heap_element.node_id as usize] = heap_element.distance;
-                let neighbors_and_distances = self.graph_ref.get_neighbors_of_node_and_distances(heap_element.node_id);
-                for i in (0..neighbors_and_distances.len()).step_by(2) {
-                    let next_node = neighbors_and_distances[i];
-                    let next_node_distance = neighbors_and_distances[i + 1];
-                    if self.distances[next_node as usize] == u32::MAX {
+                let (neighbors, distances) = self.graph_ref.get_neighbors_of_node_and_distances(heap_element.node_id);
+                for i in 0..neighbors.len() {
+                    let next_node = neighbors[i];
+                    let next_node_distance = distances[i];
+                    if self.distances[next_node as usize] == INFINITE_WEIGHT {
                         //println!("add edge form {} to {} with dist {}", heap_element.node_id, next_node, next_node_distance);
                         self.heap.push(HeapItem {
                             node_id: next_node,
-                            distance: next_node_distance + heap_element.distance,
+                            distance: heap_element.distance.saturating_add(next_node_distance),
                             previous_node: heap_element.node_id,
                         });
                     }
@@ -134,6 +150,6 @@ impl Dijkstra {
         }
         nodes.push(self.source_node);
         nodes.reverse();
-        return nodes;
+        nodes
     }
 }
@@ -0,0 +1,94 @@
+use std::collections::{BinaryHeap, HashMap};
+use crate::model::adjacency_array::AdjacencyArray;
+use crate::model::heap_item::HeapItem;
+use crate::model::weight::{Weight, INFINITE_WEIGHT};
+
+pub(crate) struct FacilitySearch<'a> {
+    graph_ref: &'a AdjacencyArray,
+    heap: BinaryHeap<HeapItem>,
+    distances: Vec<Weight>,
+    source_node: u32,
+}
+
+// one to many dijkstra which stops as soon as k facilities have been settled
+impl<'a> FacilitySearch<'a> {
+    pub fn new(graph: &'a AdjacencyArray, source_node: u32) -> FacilitySearch<'a> {
+        let number_of_nodes = graph.get_nodes_count() as usize;
+        let mut heap = BinaryHeap::with_capacity(number_of_nodes);
+        let distances = vec![INFINITE_WEIGHT; number_of_nodes];
+        heap.push(HeapItem {
+            node_id: source_node,
+            distance: 0,
+            previous_node: source_node,
+        });
+        FacilitySearch { graph_ref: graph, heap, distances, source_node }
+    }
+
+    /// returns up to k (facility index, graph node, distance) tuples ordered by their distance by sea
+    pub fn find_nearest(&mut self, node_to_facilities: &HashMap<u32, Vec<usize>>, k: usize) -> Vec<(usize, u32, Weight)> {
+        // there can't be more results than facilities
+        let k = k.min(node_to_facilities.values().map(Vec::len).sum());
+        let mut results: Vec<(usize, u32, Weight)> = Vec::with_capacity(k);
+        if k == 0 {
+            return results;
+        }
+        loop {
+            if let Some(heap_element) = self.heap.pop() {
+                if heap_element.distance >= self.distances[heap_element.node_id as usize] {
+                    continue;
+                }
+                self.distances[heap_element.node_id as usize] = heap_element.distance;
+                if let Some(facilities) = node_to_facilities.get(&heap_element.node_id) {
+                    // found facilities, several facilities can share the same node
+                    for facility in facilities {
+                        results.push((*facility, heap_element.node_id, heap_element.distance));
+                        if results.len() == k {
+                            return results;
+                        }
+                    }
+                }
+                let (neighbors, distances) = self.graph_ref.get_neighbors_of_node_and_distances(heap_element.node_id);
+                for i in 0..neighbors.len() {
+                    let next_node = neighbors[i];
+                    let next_node_distance = distances[i];
+                    if self.distances[next_node as usize] == INFINITE_WEIGHT {
+                        self.heap.push(HeapItem {
+                            node_id: next_node,
+                            distance: heap_element.distance.saturating_add(next_node_distance),
+                            previous_node: heap_element.node_id,
+                        });
+                    }
+                }
+            } else {
+                println!("Heap is empty but only found {} of {} facilities. src {}", results.len(), k, self.source_node);
+                return results;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // path 0 - 1 - 2 - 3 with edges in both directions
+    fn path() -> AdjacencyArray {
+        AdjacencyArray::new(vec![0, 1, 3, 5, 6], vec![1, 0, 2, 1, 3, 2], vec![10, 10, 20, 20, 30, 30])
+    }
+
+    #[test]
+    fn finds_facilities_ordered_by_distance() {
+        let graph = path();
+        let node_to_facilities: HashMap<u32, Vec<usize>> = vec![(3, vec![0]), (1, vec![1, 2])].into_iter().collect();
+        let results = FacilitySearch::new(&graph, 0).find_nearest(&node_to_facilities, 2);
+        assert_eq!(results, vec![(1, 1, 10), (2, 1, 10)]);
+    }
+
+    #[test]
+    fn k_is_limited_to_the_number_of_facilities() {
+        let graph = path();
+        let node_to_facilities: HashMap<u32, Vec<usize>> = vec![(3, vec![0])].into_iter().collect();
+        let results = FacilitySearch::new(&graph, 0).find_nearest(&node_to_facilities, usize::MAX);
+        assert_eq!(results, vec![(0, 3, 60)]);
+    }
+}
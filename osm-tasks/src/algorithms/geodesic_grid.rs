@@ -0,0 +1,282 @@
+use std::collections::{HashMap, HashSet};
+use rayon::prelude::*;
+use crate::algorithms::grid_generator::GridGenerator;
+use crate::algorithms::land_test::LandTest;
+use crate::model::grid_graph::{GridGraph, GridType, Node, Edge, distance};
+use crate::model::weight::meters_to_weight;
+use crate::model::build_status::BuildPhase;
+use crate::persistence::build_tracker::BuildTracker;
+
+/// triangle mesh of a subdivided icosahedron on the unit sphere
+struct GeodesicMesh {
+    vertices: Vec<[f64; 3]>,
+    triangles: Vec<[u32; 3]>,
+}
+
+impl GeodesicMesh {
+    /// Subdivides each face of the icosahedron into frequency^2 triangles (class I geodesic grid). The
+    /// mesh has 10 * frequency^2 + 2 vertices, all but the 12 corners of the icosahedron have six neighbors.
+    fn subdivided_icosahedron(frequency: usize) -> GeodesicMesh {
+        let (corners, faces) = icosahedron();
+        let mut vertices: Vec<[f64; 3]> = Vec::new();
+        // vertices on edges and corners are shared between faces, they are identified by their
+        // barycentric weights of the corners sorted by the corner index
+        let mut vertex_ids: HashMap<Vec<(usize, usize)>, u32> = HashMap::new();
+        let mut triangles: Vec<[u32; 3]> = Vec::with_capacity(20 * frequency * frequency);
+        for face in faces.iter() {
+            // ids of the vertices of this face by their position (i, j) with i + j <= frequency
+            let mut face_ids = vec![vec![0u32; frequency + 1]; frequency + 1];
+            for (i, ids) in face_ids.iter_mut().enumerate() {
+                for (j, face_id) in ids[..=frequency - i].iter_mut().enumerate() {
+                    let mut weights: Vec<(usize, usize)> = vec![(face[0], frequency - i - j), (face[1], i), (face[2], j)]
+                        .into_iter().filter(|(_, weight)| *weight > 0).collect();
+                    weights.sort();
+                    let id = *vertex_ids.entry(weights.clone()).or_insert_with(|| {
+                        let mut position = [0.0; 3];
+                        for (corner, weight) in weights.iter() {
+                            for (axis, value) in position.iter_mut().enumerate() {
+                                *value += corners[*corner][axis] * *weight as f64;
+                            }
+                        }
+                        vertices.push(normalize(position));
+                        vertices.len() as u32 - 1
+                    });
+                    *face_id = id;
+                }
+            }
+            for i in 0..frequency {
+                for j in 0..frequency - i {
+                    triangles.push([face_ids[i][j], face_ids[i + 1][j], face_ids[i][j + 1]]);
+                    if i + j + 1 < frequency {
+                        triangles.push([face_ids[i + 1][j], face_ids[i + 1][j + 1], face_ids[i][j + 1]]);
+                    }
+                }
+            }
+        }
+        GeodesicMesh { vertices, triangles }
+    }
+
+    /// undirected edges of the triangles, each edge is returned once with the smaller id first
+    fn edges(&self) -> Vec<(u32, u32)> {
+        let mut edges: HashSet<(u32, u32)> = HashSet::with_capacity(self.triangles.len() * 3 / 2);
+        for triangle in self.triangles.iter() {
+            for k in 0..3 {
+                let (a, b) = (triangle[k], triangle[(k + 1) % 3]);
+                edges.insert((a.min(b), a.max(b)));
+            }
+        }
+        edges.into_iter().collect()
+    }
+}
+
+/// Geodesic grid: the nodes are the vertices of a subdivided icosahedron, each node is connected to
+/// its six (five at the corners of the icosahedron) neighbors.
+pub struct IcosahedralGridGenerator {
+    pub(crate) number_nodes: usize,
+}
+
+impl GridGenerator for IcosahedralGridGenerator {
+    fn generate(&self, land_test: &dyn LandTest) -> GridGraph {
+        // 10 * f^2 + 2 vertices
+        let frequency = (((self.number_nodes.max(12) - 2) as f64 / 10.0).sqrt().round() as usize).max(1);
+        println!("Generating icosahedral grid with frequency {}", frequency);
+        let mesh = GeodesicMesh::subdivided_icosahedron(frequency);
+        let edges = mesh.edges();
+        build_graph(mesh.vertices, edges, land_test, GridType::Icosahedral)
+    }
+}
+
+/// Hexagonal grid similar to the aperture 3 hexagonal grids of discrete global grid systems. The
+/// centers of the triangles of a subdivided icosahedron are added as nodes (sqrt(3) subdivision), so
+/// each node is the center of a hexagonal cell (pentagonal at the corners of the icosahedron) and is
+/// connected to the centers of the six neighboring cells.
+pub struct HexagonalGridGenerator {
+    pub(crate) number_nodes: usize,
+}
+
+impl GridGenerator for HexagonalGridGenerator {
+    fn generate(&self, land_test: &dyn LandTest) -> GridGraph {
+        // 10 * f^2 + 2 vertices and 20 * f^2 triangle centers
+        let frequency = (((self.number_nodes.max(32) - 2) as f64 / 30.0).sqrt().round() as usize).max(1);
+        println!("Generating hexagonal grid with frequency {}", frequency);
+        let mesh = GeodesicMesh::subdivided_icosahedron(frequency);
+        let number_vertices = mesh.vertices.len() as u32;
+        let mut positions = mesh.vertices.clone();
+        let mut edges: Vec<(u32, u32)> = Vec::with_capacity(mesh.triangles.len() * 9 / 2);
+        // the two triangles of each mesh edge
+        let mut triangles_of_edge: HashMap<(u32, u32), Vec<u32>> = HashMap::with_capacity(mesh.triangles.len() * 3 / 2);
+        for (t, triangle) in mesh.triangles.iter().enumerate() {
+            let center_id = number_vertices + t as u32;
+            let mut center = [0.0; 3];
+            for k in 0..3 {
+                let vertex = mesh.vertices[triangle[k] as usize];
+                for axis in 0..3 {
+                    center[axis] += vertex[axis];
+                }
+                // connect the center to the corners of its triangle
+                edges.push((center_id, triangle[k]));
+                let (a, b) = (triangle[k], triangle[(k + 1) % 3]);
+                triangles_of_edge.entry((a.min(b), a.max(b))).or_default().push(center_id);
+            }
+            positions.push(normalize(center));
+        }
+        // the edges of the mesh are flipped: the centers of neighboring triangles are connected instead of the vertices
+        for centers in triangles_of_edge.values() {
+            if centers.len() == 2 {
+                edges.push((centers[0], centers[1]));
+            }
+        }
+        build_graph(positions, edges, land_test, GridType::Hexagonal)
+    }
+}
+
+// classifies the positions and creates the graph with all edges between water nodes
+fn build_graph(positions: Vec<[f64; 3]>, edges: Vec<(u32, u32)>, land_test: &dyn LandTest, grid_type: GridType) -> GridGraph {
+    BuildTracker::global().phase(BuildPhase::ClassifyingNodes);
+    let classified: Vec<Option<Node>> = positions.par_iter().map(|position| {
+        let node = to_node(position);
+        if land_test.check_intersection((node.lon, node.lat)) {
+            None
+        } else {
+            Some(node)
+        }
+    }).collect();
+    let mut new_ids: Vec<Option<u32>> = vec![None; positions.len()];
+    let mut nodes: Vec<Node> = Vec::new();
+    for (i, node_option) in classified.into_iter().enumerate() {
+        if let Some(node) = node_option {
+            new_ids[i] = Some(nodes.len() as u32);
+            nodes.push(node);
+        }
+    }
+    BuildTracker::global().phase(BuildPhase::CreatingEdges);
+    let mut graph_edges: Vec<Vec<Edge>> = vec![Vec::with_capacity(6); nodes.len()];
+    for (a, b) in edges {
+        if let (Some(source), Some(target)) = (new_ids[a as usize], new_ids[b as usize]) {
+            let (node1, node2) = (&nodes[source as usize], &nodes[target as usize]);
+            let edge_distance = meters_to_weight(distance(node1.lon, node1.lat, node2.lon, node2.lat));
+            graph_edges[source as usize].push(Edge { source, target, distance: edge_distance });
+            graph_edges[target as usize].push(Edge { source: target, target: source, distance: edge_distance });
+        }
+    }
+    let number_edges: usize = graph_edges.iter().map(|e| e.len()).sum();
+    println!("number grid points {}", positions.len());
+    println!("number placed nodes {}", nodes.len());
+    println!("number edges {}", number_edges);
+    GridGraph {
+        number_edges: number_edges as i64,
+        number_nodes: nodes.len() as i64,
+        edges: graph_edges,
+        nodes,
+        component_ids: vec![],
+        removed_nodes: vec![],
+        grid_type,
+    }
+}
+
+// corners and faces of an icosahedron with a corner at each pole
+fn icosahedron() -> (Vec<[f64; 3]>, Vec<[usize; 3]>) {
+    let mut corners = vec![[0.0, 0.0, 1.0]];
+    let ring_lat = (0.5f64).atan();
+    for k in 0..5 {
+        let lon = (k as f64 * 72.0).to_radians();
+        corners.push([ring_lat.cos() * lon.cos(), ring_lat.cos() * lon.sin(), ring_lat.sin()]);
+    }
+    for k in 0..5 {
+        let lon = (k as f64 * 72.0 + 36.0).to_radians();
+        corners.push([ring_lat.cos() * lon.cos(), ring_lat.cos() * lon.sin(), -ring_lat.sin()]);
+    }
+    corners.push([0.0, 0.0, -1.0]);
+    let mut faces = Vec::with_capacity(20);
+    for k in 0..5 {
+        let (upper, next_upper) = (1 + k, 1 + (k + 1) % 5);
+        let (lower, next_lower) = (6 + k, 6 + (k + 1) % 5);
+        faces.push([0, upper, next_upper]);
+        faces.push([upper, lower, next_upper]);
+        faces.push([next_upper, lower, next_lower]);
+        faces.push([11, next_lower, lower]);
+    }
+    (corners, faces)
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    [v[0] / length, v[1] / length, v[2] / length]
+}
+
+fn to_node(position: &[f64; 3]) -> Node {
+    Node {
+        lat: position[2].clamp(-1.0, 1.0).asin().to_degrees(),
+        lon: position[1].atan2(position[0]).to_degrees(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::import::pbf_reader::{save_graph_to_disk, load_graph_from_disk};
+
+    struct NoLand;
+
+    impl LandTest for NoLand {
+        fn check_intersection(&self, _: (f64, f64)) -> bool {
+            false
+        }
+    }
+
+    // checks that each node has five or six neighbors, the 12 corners of the icosahedron have five
+    fn check_degrees(graph: &GridGraph) {
+        let degrees: Vec<usize> = graph.edges.iter().map(|edges| edges.len()).collect();
+        assert!(degrees.iter().all(|degree| *degree == 5 || *degree == 6));
+        assert_eq!(degrees.iter().filter(|degree| **degree == 5).count(), 12);
+    }
+
+    // checks that each edge exists in both directions with the same distance and without duplicates
+    fn check_symmetric_edges(graph: &GridGraph) {
+        let mut edges: HashMap<(u32, u32), u64> = HashMap::new();
+        for edge in graph.edges.iter().flatten() {
+            assert_ne!(edge.source, edge.target);
+            assert!(edges.insert((edge.source, edge.target), edge.distance).is_none());
+        }
+        for ((source, target), distance) in edges.iter() {
+            assert_eq!(edges.get(&(*target, *source)), Some(distance));
+        }
+        assert_eq!(graph.number_edges as usize, edges.len());
+    }
+
+    #[test]
+    fn icosahedral_grid_is_regular() {
+        Config::init_for_tests();
+        let graph = IcosahedralGridGenerator { number_nodes: 1000 }.generate(&NoLand);
+        // frequency 10
+        assert_eq!(graph.nodes.len(), 1002);
+        assert_eq!(graph.grid_type, GridType::Icosahedral);
+        check_degrees(&graph);
+        check_symmetric_edges(&graph);
+    }
+
+    #[test]
+    fn hexagonal_grid_is_regular() {
+        Config::init_for_tests();
+        let graph = HexagonalGridGenerator { number_nodes: 3000 }.generate(&NoLand);
+        // frequency 10, 1002 vertices and 2000 triangle centers
+        assert_eq!(graph.nodes.len(), 3002);
+        assert_eq!(graph.grid_type, GridType::Hexagonal);
+        check_degrees(&graph);
+        check_symmetric_edges(&graph);
+    }
+
+    #[test]
+    fn grid_type_is_saved_with_the_graph() {
+        Config::init_for_tests();
+        let path = std::env::temp_dir().join(format!("osm-tasks-test-{}.graph", std::process::id()));
+        let graph = HexagonalGridGenerator { number_nodes: 100 }.generate(&NoLand);
+        save_graph_to_disk(&path, &graph).unwrap();
+        let loaded = load_graph_from_disk(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.grid_type, GridType::Hexagonal);
+        assert_eq!(loaded.nodes.len(), graph.nodes.len());
+        assert_eq!(loaded.number_edges, graph.number_edges);
+    }
+}
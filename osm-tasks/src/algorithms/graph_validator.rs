@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, HashSet};
+use std::f64::consts::PI;
+use std::fmt;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::algorithms::connected_components::strongly_connected_components;
+use crate::algorithms::land_test::LandTest;
+use crate::model::grid_graph::{GridGraph, GridType, Node, EARTH_RADIUS, distance};
+use crate::model::weight::{Weight, weight_to_meters};
+
+// maximum number of offending edges and nodes listed in the report, all issues are counted anyway
+const MAX_LISTED_ELEMENTS: usize = 10000;
+// edges shorter than this factor times the expected spacing are counted as short edges
+const SHORT_EDGE_FACTOR: f64 = 0.5;
+// edges longer than this factor times the expected spacing are counted as long edges
+const LONG_EDGE_FACTOR: f64 = 2.0;
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    // edge without an edge in the opposite direction
+    AsymmetricEdge,
+    // edge which exists more than once
+    DuplicateEdge,
+    SelfLoop,
+    ZeroLengthEdge,
+    // target is not a node of the graph or the source does not match the node the edge is stored at
+    InvalidEdge,
+    // edge much shorter or longer than the expected spacing
+    IrregularLength,
+    NodeOnLand,
+    // node which is not part of the largest connected component
+    DisconnectedNode,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OffendingEdge {
+    pub(crate) kind: IssueKind,
+    pub(crate) source: u32,
+    pub(crate) target: u32,
+    // length stored in the graph in meters
+    pub(crate) distance: f64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OffendingNode {
+    pub(crate) kind: IssueKind,
+    pub(crate) id: u32,
+    pub(crate) node: Node,
+}
+
+/// edge lengths in meters
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeLengthStatistics {
+    pub(crate) expected_spacing: f64,
+    pub(crate) min: f64,
+    pub(crate) max: f64,
+    pub(crate) mean: f64,
+    pub(crate) median: f64,
+    pub(crate) standard_deviation: f64,
+    // edges shorter than half of the expected spacing
+    pub(crate) short_edges: usize,
+    // edges longer than twice the expected spacing
+    pub(crate) long_edges: usize,
+    // largest difference between the stored length and the great circle distance of the nodes
+    pub(crate) max_stored_length_error: f64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ComponentStatistics {
+    pub(crate) number_components: usize,
+    pub(crate) largest_component_size: usize,
+    // number of nodes which are not part of the largest component
+    pub(crate) disconnected_nodes: usize,
+    // sizes of all components except for the largest one, largest first
+    pub(crate) disconnected_component_sizes: Vec<usize>,
+}
+
+/// Result of the validation of a graph. Each issue is counted, the offending elements are listed up to
+/// a maximum number.
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    pub(crate) grid_type: GridType,
+    pub(crate) number_nodes: usize,
+    pub(crate) number_edges: usize,
+    // number_nodes and number_edges of the graph do not match the stored nodes and edges
+    pub(crate) inconsistent_counts: bool,
+    pub(crate) asymmetric_edges: usize,
+    pub(crate) duplicate_edges: usize,
+    pub(crate) self_loops: usize,
+    pub(crate) zero_length_edges: usize,
+    pub(crate) invalid_edges: usize,
+    // None if no land test was available
+    pub(crate) nodes_on_land: Option<usize>,
+    pub(crate) edge_lengths: EdgeLengthStatistics,
+    // number of nodes by their number of outgoing edges
+    pub(crate) degree_histogram: BTreeMap<usize, usize>,
+    // None if the components could not be calculated because of invalid edges
+    pub(crate) components: Option<ComponentStatistics>,
+    pub(crate) offending_edges: Vec<OffendingEdge>,
+    pub(crate) offending_nodes: Vec<OffendingNode>,
+}
+
+impl ValidationReport {
+    /// true if the graph has no structural issues (asymmetric, duplicate, invalid edges, self loops, zero length edges or nodes on land)
+    pub fn is_valid(&self) -> bool {
+        !self.inconsistent_counts && self.asymmetric_edges == 0 && self.duplicate_edges == 0 && self.self_loops == 0
+            && self.zero_length_edges == 0 && self.invalid_edges == 0 && self.nodes_on_land.unwrap_or(0) == 0
+    }
+
+    /// GeoJSON feature collection with a LineString for each offending edge and a Point for each offending node
+    pub fn offending_elements_geojson(&self, graph: &GridGraph) -> Value {
+        let mut features: Vec<Value> = Vec::with_capacity(self.offending_edges.len() + self.offending_nodes.len());
+        for edge in self.offending_edges.iter() {
+            let source = graph.nodes[edge.source as usize];
+            // invalid targets are drawn as points at the source
+            let target = graph.nodes.get(edge.target as usize).copied().unwrap_or(source);
+            features.push(json!({
+                "type": "Feature",
+                "properties": { "issue": edge.kind, "source": edge.source, "target": edge.target, "distance": edge.distance },
+                "geometry": { "type": "LineString", "coordinates": [[source.lon, source.lat], [target.lon, target.lat]] }
+            }));
+        }
+        for node in self.offending_nodes.iter() {
+            features.push(json!({
+                "type": "Feature",
+                "properties": { "issue": node.kind, "id": node.id },
+                "geometry": { "type": "Point", "coordinates": [node.node.lon, node.node.lat] }
+            }));
+        }
+        json!({ "type": "FeatureCollection", "features": features })
+    }
+
+    fn add_edge(&mut self, kind: IssueKind, source: u32, target: u32, weight: Weight) {
+        if self.offending_edges.len() < MAX_LISTED_ELEMENTS {
+            self.offending_edges.push(OffendingEdge { kind, source, target, distance: weight_to_meters(weight) });
+        }
+    }
+
+    fn add_node(&mut self, kind: IssueKind, id: u32, node: Node) {
+        if self.offending_nodes.len() < MAX_LISTED_ELEMENTS {
+            self.offending_nodes.push(OffendingNode { kind, id, node });
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} graph with {} nodes and {} edges: {} asymmetric edges, {} duplicate edges, {} self loops, {} zero length edges, {} invalid edges",
+               self.grid_type, self.number_nodes, self.number_edges, self.asymmetric_edges, self.duplicate_edges, self.self_loops, self.zero_length_edges, self.invalid_edges)?;
+        if let Some(nodes_on_land) = self.nodes_on_land {
+            write!(f, ", {} nodes on land", nodes_on_land)?;
+        }
+        write!(f, "; edge lengths min {:.0} m, mean {:.0} m, max {:.0} m (expected {:.0} m), {} short and {} long edges",
+               self.edge_lengths.min, self.edge_lengths.mean, self.edge_lengths.max, self.edge_lengths.expected_spacing, self.edge_lengths.short_edges, self.edge_lengths.long_edges)?;
+        if let Some(components) = self.components.as_ref() {
+            write!(f, "; {} components, {} nodes outside of the largest component", components.number_components, components.disconnected_nodes)?;
+        }
+        if self.inconsistent_counts {
+            write!(f, "; node or edge count does not match the stored nodes and edges")?;
+        }
+        Ok(())
+    }
+}
+
+/// Expected distance between neighboring nodes in meters for a graph generated from number_nodes grid
+/// points or with a spacing of spacing_km for regional graphs.
+pub fn expected_spacing(grid_type: GridType, number_nodes: usize, spacing_km: f64) -> f64 {
+    let area_per_point = 4.0 * PI * EARTH_RADIUS * EARTH_RADIUS / number_nodes.max(1) as f64;
+    match grid_type {
+        GridType::Regional => spacing_km * 1000.0,
+        GridType::Sphere => area_per_point.sqrt(),
+        // distance between the centers of neighboring hexagons (or points of a triangular lattice) of the same area
+        GridType::Icosahedral | GridType::Hexagonal => (2.0 * area_per_point / 3f64.sqrt()).sqrt(),
+    }
+}
+
+/// Checks the invariants of the graph: each edge has an edge in the opposite direction, no duplicate
+/// edges, self loops or edges of length zero and no nodes on land. Additionally collects statistics of
+/// the edge lengths, node degrees and connected components.
+pub fn validate_graph(graph: &GridGraph, land_test: Option<&dyn LandTest>, expected_spacing: f64) -> ValidationReport {
+    let number_nodes = graph.nodes.len();
+    let number_edges: usize = graph.edges.iter().map(|edges| edges.len()).sum();
+    let mut report = ValidationReport {
+        grid_type: graph.grid_type,
+        number_nodes,
+        number_edges,
+        inconsistent_counts: graph.number_nodes as usize != number_nodes || graph.number_edges as usize != number_edges || graph.edges.len() != number_nodes,
+        asymmetric_edges: 0,
+        duplicate_edges: 0,
+        self_loops: 0,
+        zero_length_edges: 0,
+        invalid_edges: 0,
+        nodes_on_land: None,
+        edge_lengths: EdgeLengthStatistics { expected_spacing, ..Default::default() },
+        degree_histogram: BTreeMap::new(),
+        components: None,
+        offending_edges: Vec::new(),
+        offending_nodes: Vec::new(),
+    };
+
+    let mut lengths: Vec<f64> = Vec::with_capacity(number_edges);
+    for (source, edges) in graph.edges.iter().enumerate().take(number_nodes) {
+        *report.degree_histogram.entry(edges.len()).or_insert(0) += 1;
+        let mut targets: HashSet<u32> = HashSet::with_capacity(edges.len());
+        for edge in edges.iter() {
+            if edge.source as usize != source || edge.target as usize >= number_nodes {
+                report.invalid_edges += 1;
+                report.add_edge(IssueKind::InvalidEdge, source as u32, edge.target, edge.distance);
+                continue;
+            }
+            if edge.target as usize == source {
+                report.self_loops += 1;
+                report.add_edge(IssueKind::SelfLoop, edge.source, edge.target, edge.distance);
+            }
+            if !targets.insert(edge.target) {
+                report.duplicate_edges += 1;
+                report.add_edge(IssueKind::DuplicateEdge, edge.source, edge.target, edge.distance);
+            }
+            if !graph.edges.get(edge.target as usize).is_some_and(|reverse_edges| reverse_edges.iter().any(|reverse| reverse.target == edge.source)) {
+                report.asymmetric_edges += 1;
+                report.add_edge(IssueKind::AsymmetricEdge, edge.source, edge.target, edge.distance);
+            }
+            let (node1, node2) = (&graph.nodes[source], &graph.nodes[edge.target as usize]);
+            let length = distance(node1.lon, node1.lat, node2.lon, node2.lat);
+            if edge.distance == 0 || length == 0.0 {
+                report.zero_length_edges += 1;
+                report.add_edge(IssueKind::ZeroLengthEdge, edge.source, edge.target, edge.distance);
+            } else if length < expected_spacing * SHORT_EDGE_FACTOR || length > expected_spacing * LONG_EDGE_FACTOR {
+                report.add_edge(IssueKind::IrregularLength, edge.source, edge.target, edge.distance);
+            }
+            report.edge_lengths.max_stored_length_error = report.edge_lengths.max_stored_length_error.max((weight_to_meters(edge.distance) - length).abs());
+            lengths.push(length);
+        }
+    }
+    collect_length_statistics(&mut report.edge_lengths, lengths);
+
+    if let Some(land_test) = land_test {
+        let nodes_on_land: Vec<u32> = (0..number_nodes as u32).into_par_iter().filter(|id| {
+            let node = graph.nodes[*id as usize];
+            land_test.check_intersection((node.lon, node.lat))
+        }).collect();
+        report.nodes_on_land = Some(nodes_on_land.len());
+        for id in nodes_on_land {
+            report.add_node(IssueKind::NodeOnLand, id, graph.nodes[id as usize]);
+        }
+    }
+
+    // the adjacency array requires valid edges
+    if report.invalid_edges == 0 && !report.inconsistent_counts && number_nodes > 0 {
+        let component_ids = strongly_connected_components(&graph.adjacency_array());
+        let number_components = component_ids.iter().max().map(|max| *max as usize + 1).unwrap_or(0);
+        let mut component_sizes = vec![0; number_components];
+        component_ids.iter().for_each(|id| component_sizes[*id as usize] += 1);
+        let largest_component = (0..number_components).max_by_key(|id| component_sizes[*id]).unwrap_or(0);
+        let mut disconnected_component_sizes: Vec<usize> = component_sizes.iter().enumerate()
+            .filter(|(id, _)| *id != largest_component).map(|(_, size)| *size).collect();
+        disconnected_component_sizes.sort_unstable_by(|a, b| b.cmp(a));
+        for (id, component_id) in component_ids.iter().enumerate() {
+            if *component_id as usize != largest_component {
+                report.add_node(IssueKind::DisconnectedNode, id as u32, graph.nodes[id]);
+            }
+        }
+        report.components = Some(ComponentStatistics {
+            number_components,
+            largest_component_size: component_sizes[largest_component],
+            disconnected_nodes: disconnected_component_sizes.iter().sum(),
+            disconnected_component_sizes,
+        });
+    }
+    report
+}
+
+fn collect_length_statistics(statistics: &mut EdgeLengthStatistics, mut lengths: Vec<f64>) {
+    if lengths.is_empty() {
+        return;
+    }
+    lengths.par_sort_unstable_by(|a, b| a.total_cmp(b));
+    let mean = lengths.iter().sum::<f64>() / lengths.len() as f64;
+    let variance = lengths.iter().map(|length| (length - mean).powi(2)).sum::<f64>() / lengths.len() as f64;
+    statistics.min = lengths[0];
+    statistics.max = lengths[lengths.len() - 1];
+    statistics.mean = mean;
+    statistics.median = lengths[lengths.len() / 2];
+    statistics.standard_deviation = variance.sqrt();
+    statistics.short_edges = lengths.iter().filter(|length| **length < statistics.expected_spacing * SHORT_EDGE_FACTOR).count();
+    statistics.long_edges = lengths.iter().filter(|length| **length > statistics.expected_spacing * LONG_EDGE_FACTOR).count();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::model::grid_graph::Edge;
+    use crate::model::weight::meters_to_weight;
+
+    fn graph(nodes: Vec<Node>, edges: &[(u32, u32)]) -> GridGraph {
+        let mut graph = GridGraph::default();
+        graph.edges = vec![vec![]; nodes.len()];
+        for (source, target) in edges.iter() {
+            let (node1, node2) = (&nodes[*source as usize], &nodes[*target as usize]);
+            let distance = meters_to_weight(distance(node1.lon, node1.lat, node2.lon, node2.lat));
+            graph.edges[*source as usize].push(Edge { source: *source, target: *target, distance });
+        }
+        graph.number_nodes = nodes.len() as i64;
+        graph.number_edges = edges.len() as i64;
+        graph.nodes = nodes;
+        graph
+    }
+
+    #[test]
+    fn symmetric_graph_is_valid() {
+        Config::init_for_tests();
+        let nodes = vec![Node { lon: 10.0, lat: 50.0 }, Node { lon: 10.01, lat: 50.0 }];
+        let report = validate_graph(&graph(nodes, &[(0, 1), (1, 0)]), None, 700.0);
+        assert!(report.is_valid());
+        assert!(report.offending_edges.is_empty());
+        assert_eq!(report.components.unwrap().number_components, 1);
+    }
+
+    #[test]
+    fn edge_issues_are_reported() {
+        Config::init_for_tests();
+        // nodes 3 and 4 are at the same position
+        let nodes = vec![Node { lon: 10.0, lat: 50.0 }, Node { lon: 10.01, lat: 50.0 }, Node { lon: 10.02, lat: 50.0 },
+                         Node { lon: 10.03, lat: 50.0 }, Node { lon: 10.03, lat: 50.0 }];
+        let edges = [(0, 1), (1, 0), (0, 1), (1, 2), (2, 2), (3, 4), (4, 3)];
+        let graph = graph(nodes, &edges);
+        let report = validate_graph(&graph, None, 700.0);
+        assert!(!report.is_valid());
+        assert!(!report.inconsistent_counts);
+        assert_eq!(report.asymmetric_edges, 1);
+        assert_eq!(report.duplicate_edges, 1);
+        assert_eq!(report.self_loops, 1);
+        // the self loop has length zero as well
+        assert_eq!(report.zero_length_edges, 3);
+        assert_eq!(report.invalid_edges, 0);
+
+        let listed = |kind: IssueKind| report.offending_edges.iter().filter(|edge| edge.kind == kind)
+            .map(|edge| (edge.source, edge.target)).collect::<Vec<(u32, u32)>>();
+        assert_eq!(listed(IssueKind::AsymmetricEdge), vec![(1, 2)]);
+        assert_eq!(listed(IssueKind::DuplicateEdge), vec![(0, 1)]);
+        assert_eq!(listed(IssueKind::SelfLoop), vec![(2, 2)]);
+        assert_eq!(listed(IssueKind::ZeroLengthEdge), vec![(2, 2), (3, 4), (4, 3)]);
+
+        let geojson = report.offending_elements_geojson(&graph);
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), report.offending_edges.len() + report.offending_nodes.len());
+        for issue in ["asymmetricEdge", "duplicateEdge", "selfLoop", "zeroLengthEdge"] {
+            assert!(features.iter().any(|feature| feature["properties"]["issue"] == issue && feature["geometry"]["type"] == "LineString"));
+        }
+        let self_loop = features.iter().find(|feature| feature["properties"]["issue"] == "selfLoop").unwrap();
+        assert_eq!(self_loop["geometry"]["coordinates"], json!([[10.02, 50.0], [10.02, 50.0]]));
+    }
+}
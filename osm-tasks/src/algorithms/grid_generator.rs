@@ -0,0 +1,48 @@
+use crate::algorithms::land_test::LandTest;
+use crate::algorithms::geodesic_grid::{IcosahedralGridGenerator, HexagonalGridGenerator};
+use crate::config::Config;
+use crate::model::grid_graph::{GridGraph, GridType};
+use crate::model::region::Region;
+
+/// Places the nodes of the graph on the sphere and connects neighboring nodes. Points which are on
+/// land according to the land test are not part of the graph.
+pub trait GridGenerator {
+    fn generate(&self, land_test: &dyn LandTest) -> GridGraph;
+}
+
+/// equally distributed nodes in rows of equal latitude
+pub struct SphereGridGenerator {
+    pub(crate) number_nodes: usize,
+}
+
+impl GridGenerator for SphereGridGenerator {
+    fn generate(&self, land_test: &dyn LandTest) -> GridGraph {
+        GridGraph::new(land_test, self.number_nodes)
+    }
+}
+
+/// rows of equal latitude with a fixed spacing inside of a region
+pub struct RegionalGridGenerator {
+    pub(crate) region: &'static Region,
+    pub(crate) spacing_km: f64,
+}
+
+impl GridGenerator for RegionalGridGenerator {
+    fn generate(&self, land_test: &dyn LandTest) -> GridGraph {
+        GridGraph::new_regional(land_test, self.region, self.spacing_km)
+    }
+}
+
+/// Returns the generator for the configured region or grid type. The number of nodes is only an
+/// approximation for the icosahedral and hexagonal grids, which only exist for certain node counts.
+pub fn configured_generator(number_nodes: usize) -> Box<dyn GridGenerator> {
+    let config = Config::global();
+    if let Some(region) = Region::configured() {
+        return Box::new(RegionalGridGenerator { region, spacing_km: config.grid_spacing() });
+    }
+    match config.grid_type() {
+        GridType::Icosahedral => Box::new(IcosahedralGridGenerator { number_nodes }),
+        GridType::Hexagonal => Box::new(HexagonalGridGenerator { number_nodes }),
+        _ => Box::new(SphereGridGenerator { number_nodes }),
+    }
+}
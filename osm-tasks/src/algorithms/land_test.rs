@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+use crate::model::grid_graph::GridGraph;
+use crate::model::region::BoundingBox;
+
+/// Classifies grid points as land or water during the graph generation
+pub trait LandTest: Sync {
+    /// true if the point (lon, lat) is on land
+    fn check_intersection(&self, point: (f64, f64)) -> bool;
+}
+
+/// Reuses the classification of an existing graph outside of the areas in which the polygons changed.
+/// Only points inside of the affected areas are tested against the new polygons, all other points are
+/// water if the existing graph has a node at exactly this position or removed one of a small component there.
+pub struct IncrementalLandTest<'a> {
+    water_positions: HashSet<(u64, u64)>,
+    affected_areas: Vec<BoundingBox>,
+    polygon_test: &'a NestedPolygonTest,
+    number_tested_points: AtomicUsize,
+}
+
+impl<'a> IncrementalLandTest<'a> {
+    pub fn new(existing_graph: &GridGraph, affected_areas: Vec<BoundingBox>, polygon_test: &'a NestedPolygonTest) -> IncrementalLandTest<'a> {
+        let water_positions = existing_graph.nodes.iter().chain(existing_graph.removed_nodes.iter()).map(|node| (node.lon.to_bits(), node.lat.to_bits())).collect();
+        IncrementalLandTest { water_positions, affected_areas, polygon_test, number_tested_points: AtomicUsize::new(0) }
+    }
+
+    /// number of points which were tested against the new polygons
+    pub fn number_tested_points(&self) -> usize {
+        self.number_tested_points.load(Ordering::Relaxed)
+    }
+}
+
+impl LandTest for IncrementalLandTest<'_> {
+    fn check_intersection(&self, (lon, lat): (f64, f64)) -> bool {
+        if self.affected_areas.iter().any(|area| area.contains(lon, lat)) {
+            self.number_tested_points.fetch_add(1, Ordering::Relaxed);
+            return self.polygon_test.check_intersection((lon, lat));
+        }
+        !self.water_positions.contains(&(lon.to_bits(), lat.to_bits()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::grid_graph::Node;
+    use crate::model::land_polygons::LandPolygons;
+
+    #[test]
+    fn removed_nodes_stay_water() {
+        let polygons = LandPolygons::from_coastline_rings(vec![vec![(10.0, 0.0), (20.0, 0.0), (20.0, 10.0), (10.0, 10.0), (10.0, 0.0)]]);
+        let polygon_test = NestedPolygonTest::new(&polygons, false);
+        let mut graph = GridGraph::default();
+        graph.nodes = vec![Node { lon: 0.0, lat: 0.0 }];
+        graph.removed_nodes = vec![Node { lon: 1.0, lat: 0.0 }];
+        let affected_areas = vec![BoundingBox { lon_min: 10.0, lat_min: 0.0, lon_max: 30.0, lat_max: 10.0 }];
+        let land_test = IncrementalLandTest::new(&graph, affected_areas, &polygon_test);
+
+        assert!(!land_test.check_intersection((0.0, 0.0)));
+        assert!(!land_test.check_intersection((1.0, 0.0)));
+        assert!(land_test.check_intersection((2.0, 0.0)));
+        assert_eq!(land_test.number_tested_points(), 0);
+        // points in the affected area are tested against the polygons
+        assert!(land_test.check_intersection((15.0, 5.0)));
+        assert!(!land_test.check_intersection((25.0, 5.0)));
+        assert_eq!(land_test.number_tested_points(), 2);
+    }
+}
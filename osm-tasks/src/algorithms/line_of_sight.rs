@@ -0,0 +1,124 @@
+use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+use crate::algorithms::land_test::LandTest;
+use crate::model::grid_graph::{Node, distance};
+
+// distance between two tested points on a great circle segment in meters
+const SAMPLE_DISTANCE: f64 = 250.0;
+// maximum distance in meters a segment may run over land from its start before it reaches the water
+const MAX_LAND_DISTANCE: f64 = 1000.0;
+
+/// Tests great circle segments against the land polygons. Points are sampled along the segment, the
+/// pieces between two samples are tested for crossings of the coastlines, so that land narrower than the
+/// sample distance is found as well.
+pub(crate) struct LineOfSight<'a> {
+    polygon_test: &'a NestedPolygonTest,
+}
+
+impl<'a> LineOfSight<'a> {
+    pub fn new(polygon_test: &'a NestedPolygonTest) -> LineOfSight<'a> {
+        LineOfSight { polygon_test }
+    }
+
+    /// true if no point of the great circle segment between the two nodes is on land
+    pub fn is_free(&self, from: &Node, to: &Node) -> bool {
+        let mut previous: Option<(f64, f64)> = None;
+        for point in sample_great_circle(from, to, SAMPLE_DISTANCE) {
+            if self.polygon_test.check_intersection(point) || previous.is_some_and(|previous| self.crosses_coastline(previous, point)) {
+                return false;
+            }
+            previous = Some(point);
+        }
+        true
+    }
+
+    /// true if the segment does not enter land again after it reached the water. The start point may be
+    /// on land (e.g. a harbour inside of a coastline polygon), but the segment has to reach the water
+    /// within MAX_LAND_DISTANCE and must not cross any land after it has left the coast.
+    pub fn reaches_water_without_crossing_land(&self, from: &Node, to: &Node) -> bool {
+        let mut reached_water = false;
+        let mut previous = (from.lon, from.lat);
+        for point in sample_great_circle(from, to, SAMPLE_DISTANCE) {
+            let is_land = self.polygon_test.check_intersection(point);
+            if !is_land {
+                if reached_water && self.crosses_coastline(previous, point) {
+                    return false;
+                }
+                reached_water = true;
+            } else if reached_water || distance(from.lon, from.lat, point.0, point.1) > MAX_LAND_DISTANCE {
+                return false;
+            }
+            previous = point;
+        }
+        reached_water
+    }
+
+    // pieces crossing the antimeridian would wrap around the globe in (lon, lat), only their end points are tested
+    fn crosses_coastline(&self, from: (f64, f64), to: (f64, f64)) -> bool {
+        (from.0 - to.0).abs() <= 180.0 && self.polygon_test.crosses_boundary(from, to)
+    }
+}
+
+/// returns points (lon, lat) along the great circle segment including both end points, so that two
+/// consecutive points are at most max_step_distance meters apart. The points are computed lazily, so
+/// that tests can stop at the first point on land.
+pub fn sample_great_circle(from: &Node, to: &Node, max_step_distance: f64) -> impl Iterator<Item = (f64, f64)> {
+    let segment_length = distance(from.lon, from.lat, to.lon, to.lat);
+    let number_steps = (segment_length / max_step_distance).ceil().max(1.0) as usize;
+    let a = to_unit_vector(from);
+    let b = to_unit_vector(to);
+    let angle = (a[0] * b[0] + a[1] * b[1] + a[2] * b[2]).clamp(-1.0, 1.0).acos();
+    let start = (from.lon, from.lat);
+    (0..=number_steps).map(move |i| {
+        let fraction = i as f64 / number_steps as f64;
+        if angle < 1e-12 {
+            return start;
+        }
+        // spherical linear interpolation between both unit vectors
+        let weight_a = ((1.0 - fraction) * angle).sin() / angle.sin();
+        let weight_b = (fraction * angle).sin() / angle.sin();
+        let x = weight_a * a[0] + weight_b * b[0];
+        let y = weight_a * a[1] + weight_b * b[1];
+        let z = weight_a * a[2] + weight_b * b[2];
+        (y.atan2(x).to_degrees(), z.clamp(-1.0, 1.0).asin().to_degrees())
+    })
+}
+
+fn to_unit_vector(node: &Node) -> [f64; 3] {
+    let lon = node.lon.to_radians();
+    let lat = node.lat.to_radians();
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::land_polygons::LandPolygons;
+
+    // island of 100 m width from north to south at lon 10
+    fn narrow_island() -> NestedPolygonTest {
+        let polygons = LandPolygons::from_coastline_rings(vec![vec![(10.0, 53.9), (10.0015, 53.9), (10.0015, 54.1), (10.0, 54.1), (10.0, 53.9)]]);
+        NestedPolygonTest::new(&polygons, false)
+    }
+
+    #[test]
+    fn land_narrower_than_the_sample_distance_blocks_the_line_of_sight() {
+        let polygon_test = narrow_island();
+        assert!(polygon_test.check_intersection((10.00075, 54.0)));
+        let line_of_sight = LineOfSight::new(&polygon_test);
+        let (west, east) = (Node { lon: 9.9, lat: 54.0 }, Node { lon: 10.1, lat: 54.0 });
+        assert!(!line_of_sight.is_free(&west, &east));
+        assert!(!line_of_sight.is_free(&east, &west));
+        assert!(line_of_sight.is_free(&west, &Node { lon: 9.95, lat: 54.05 }));
+        assert!(line_of_sight.is_free(&Node { lon: 9.9, lat: 54.2 }, &Node { lon: 10.1, lat: 54.2 }));
+    }
+
+    #[test]
+    fn snapping_does_not_cross_narrow_land_after_reaching_water() {
+        let polygon_test = narrow_island();
+        let line_of_sight = LineOfSight::new(&polygon_test);
+        // from the island to the water on the west side
+        assert!(line_of_sight.reaches_water_without_crossing_land(&Node { lon: 10.00075, lat: 54.0 }, &Node { lon: 9.99, lat: 54.0 }));
+        // from the water on the west side across the island
+        assert!(!line_of_sight.reaches_water_without_crossing_land(&Node { lon: 9.99, lat: 54.0 }, &Node { lon: 10.01, lat: 54.0 }));
+    }
+}
@@ -6,3 +6,16 @@ pub(crate) mod cn_graph_creator;
 pub(crate) mod cn_search;
 pub mod polygon_test;
 mod witness_search;
+pub(crate) mod facility_search;
+pub(crate) mod line_of_sight;
+pub(crate) mod water_snapping;
+pub(crate) mod connected_components;
+pub mod nested_polygon_test;
+pub(crate) mod polygon_simplification;
+pub(crate) mod polygon_clipping;
+pub(crate) mod land_test;
+pub(crate) mod grid_generator;
+pub(crate) mod geodesic_grid;
+pub(crate) mod graph_validator;
+pub(crate) mod route_simplification;
+pub(crate) mod theta_star;
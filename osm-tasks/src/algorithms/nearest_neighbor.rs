@@ -1,9 +1,26 @@
-use crate::model::grid_graph::{Node, distance};
+use crate::model::grid_graph::{Node, distance, EARTH_RADIUS};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f64::consts::PI;
+
+/*
+ Spatial index over the graph nodes. The nodes are stored as unit vectors in a 3D kd-tree, so the
+ search is exact everywhere on the globe, including the poles and the antimeridian. The straight
+ line (chord) distance between two unit vectors grows monotonically with their great circle distance,
+ so the nearest nodes by chord are also the nearest nodes on the sphere.
+ The tree is implicit: the median of each range is the split node and the ranges are halved until
+ they contain at most LEAF_SIZE nodes, so the depth adapts to the number of nodes in the graph.
+*/
+
+const LEAF_SIZE: usize = 8;
+// half of the circumference, no two points on the sphere are further apart
+const MAX_DISTANCE: f64 = PI * EARTH_RADIUS;
 
 #[derive(Clone, Copy, Debug)]
 struct NodeWithId {
     lon: f64,
     lat: f64,
+    position: [f64; 3],
     id: u32,
 }
 
@@ -11,127 +28,312 @@ impl NodeWithId {
     fn distance_to(&self, node: &Node) -> f64 {
         distance(self.lon, self.lat, node.lon, node.lat)
     }
+}
 
-    fn min<'a>(node1: Option<(&'a NodeWithId, f64)>, node2: Option<(&'a NodeWithId, f64)>) -> Option<(&'a NodeWithId, f64)> {
-        match (node1, node2) {
-            (None, None) => None,
-            (Some(node), None) | (None, Some(node)) => Some(node),
-            (Some((_, dst1)), Some((_, dst2))) => {
-                if dst1 > dst2 {
-                    node2
-                } else {
-                    node1
-                }
-            }
-        }
+// candidate of a k nearest neighbor search, the heap keeps the candidate with the largest distance on top
+struct Candidate {
+    squared_chord: f64,
+    index: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.squared_chord == other.squared_chord
     }
 }
 
-pub struct NearestNeighbor {
-    grid: Vec<Vec<NodeWithId>>,
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.squared_chord.partial_cmp(&other.squared_chord).unwrap_or(Ordering::Equal)
+    }
 }
 
-const X_SIZE: usize = 100;
-const Y_SIZE: usize = 100;
+pub struct NearestNeighbor {
+    // nodes in kd-tree order
+    nodes: Vec<NodeWithId>,
+    // split axis of the range whose median is stored at the same index in nodes
+    split_axes: Vec<u8>,
+}
 
 impl NearestNeighbor {
-    pub fn new(nodes: &Vec<Node>) -> NearestNeighbor {
-        let mut grid = vec![Vec::new(); X_SIZE * Y_SIZE];
-        for i in 0..nodes.len() {
-            let node = &nodes[i];
-            grid[NearestNeighbor::get_cell_for_node(node)].push(NodeWithId { id: i as u32, lon: node.lon, lat: node.lat });
-        }
-        NearestNeighbor { grid }
-    }
-
-    pub fn find_nearest_neighbor(&self, node: &Node) -> u32 {
-        // first check the cell itself and all eight cells around
-        let node_index = NearestNeighbor::get_cell_for_node(node);
-        let mut nearest_node_and_distance = self.find_nearest_neighbor_in_cell(node_index, node);
-        for i in 1..X_SIZE.max(Y_SIZE) {
-            let (new_nearest_node_and_distance, radius) = self.find_nearest_neighbor_for_radius(i, node_index, node);
-            nearest_node_and_distance = NodeWithId::min(nearest_node_and_distance, new_nearest_node_and_distance);
-            if let Some((nearest_node, distance)) = nearest_node_and_distance {
-                if distance <= radius {
-                    return nearest_node.id;
-                }
+    pub fn new(nodes: &[Node]) -> NearestNeighbor {
+        let mut tree_nodes: Vec<NodeWithId> = nodes.iter().enumerate().map(|(i, node)| {
+            NodeWithId { id: i as u32, lon: node.lon, lat: node.lat, position: to_unit_vector(node.lon, node.lat) }
+        }).collect();
+        let mut split_axes = vec![0; tree_nodes.len()];
+        NearestNeighbor::build(&mut tree_nodes, &mut split_axes);
+        NearestNeighbor { nodes: tree_nodes, split_axes }
+    }
+
+    fn build(nodes: &mut [NodeWithId], split_axes: &mut [u8]) {
+        if nodes.len() <= LEAF_SIZE {
+            return;
+        }
+        // split along the axis with the largest extent
+        let mut min = [f64::MAX; 3];
+        let mut max = [f64::MIN; 3];
+        for node in nodes.iter() {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(node.position[axis]);
+                max[axis] = max[axis].max(node.position[axis]);
             }
         }
-        panic!("Invariant violated: Could not find nearest neighbor node for coords {} {}", node.lon, node.lat);
-    }
-
-    /// returns the nearest node and the distance to this node as well as the covered radius for this query
-    fn find_nearest_neighbor_for_radius(&self, distance_to_center: usize, center_cell: usize, node: &Node) -> (Option<(&NodeWithId, f64)>, f64) {
-        let mut nearest_node_and_distance: Option<(&NodeWithId, f64)> = None;
-        // Calculate the radius through the minimal distance to the midpoints of all checked cells
-        let mut radius = f64::MAX;
-        let (center_x, center_y) = NearestNeighbor::get_x_y_for_index(center_cell);
-        for y in vec![center_y as isize - distance_to_center as isize, center_y as isize + distance_to_center as isize] {
-            if y < 0 || y >= Y_SIZE as isize - 1 { continue; }
-            for x in (center_x as isize - distance_to_center as isize)..(center_x as isize + distance_to_center as isize) {
-                let x_mod = ((x + X_SIZE as isize) % X_SIZE as isize) as usize; // X_SIZE that the result can not be negative
-                nearest_node_and_distance = NodeWithId::min(nearest_node_and_distance, self.find_nearest_neighbor_in_cell(NearestNeighbor::get_index_for_x_y(x_mod, y as usize), node));
-                let (cell_midpoint_lon, cell_midpoint_lat) = NearestNeighbor::calc_mid_point_of_cell(x_mod, y as usize);
-                radius = radius.min(distance(node.lon, node.lat, cell_midpoint_lon, cell_midpoint_lat));
+        let axis = (0..3).max_by(|a, b| (max[*a] - min[*a]).total_cmp(&(max[*b] - min[*b]))).unwrap();
+        let mid = nodes.len() / 2;
+        nodes.select_nth_unstable_by(mid, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+        split_axes[mid] = axis as u8;
+        let (left, right) = nodes.split_at_mut(mid);
+        let (left_axes, right_axes) = split_axes.split_at_mut(mid);
+        NearestNeighbor::build(left, left_axes);
+        NearestNeighbor::build(&mut right[1..], &mut right_axes[1..]);
+    }
+
+    /// returns the id of the nearest node or None if the index does not contain any nodes
+    pub fn find_nearest_neighbor(&self, node: &Node) -> Option<u32> {
+        self.find_k_nearest_neighbors(node, 1).first().map(|(id, _)| *id)
+    }
+
+    /// returns the ids of the k nearest nodes and their distances in meters, ordered by distance
+    pub fn find_k_nearest_neighbors(&self, node: &Node, k: usize) -> Vec<(u32, f64)> {
+        if k == 0 || self.nodes.is_empty() {
+            return Vec::new();
+        }
+        let k = k.min(self.nodes.len());
+        let position = to_unit_vector(node.lon, node.lat);
+        let mut candidates: BinaryHeap<Candidate> = BinaryHeap::with_capacity(k + 1);
+        self.search_k_nearest(0, self.nodes.len(), &position, k, &mut candidates);
+        let mut neighbors: Vec<(u32, f64)> = candidates.into_sorted_vec().into_iter()
+            .map(|c| (self.nodes[c.index].id, self.nodes[c.index].distance_to(node)))
+            .collect();
+        // the haversine distance may differ in the last digits from the chord order
+        neighbors.sort_by(|a, b| a.1.total_cmp(&b.1));
+        neighbors
+    }
+
+    fn search_k_nearest(&self, start: usize, end: usize, position: &[f64; 3], k: usize, candidates: &mut BinaryHeap<Candidate>) {
+        if end - start <= LEAF_SIZE {
+            for index in start..end {
+                NearestNeighbor::offer_candidate(index, squared_chord(&self.nodes[index].position, position), k, candidates);
             }
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let axis = self.split_axes[mid] as usize;
+        let plane_distance = position[axis] - self.nodes[mid].position[axis];
+        NearestNeighbor::offer_candidate(mid, squared_chord(&self.nodes[mid].position, position), k, candidates);
+        let (near, far) = if plane_distance < 0.0 { ((start, mid), (mid + 1, end)) } else { ((mid + 1, end), (start, mid)) };
+        self.search_k_nearest(near.0, near.1, position, k, candidates);
+        if candidates.len() < k || plane_distance * plane_distance < candidates.peek().unwrap().squared_chord {
+            self.search_k_nearest(far.0, far.1, position, k, candidates);
+        }
+    }
+
+    #[inline]
+    fn offer_candidate(index: usize, squared_chord: f64, k: usize, candidates: &mut BinaryHeap<Candidate>) {
+        if candidates.len() < k {
+            candidates.push(Candidate { squared_chord, index });
+        } else if squared_chord < candidates.peek().unwrap().squared_chord {
+            candidates.pop();
+            candidates.push(Candidate { squared_chord, index });
         }
-        for x in vec![center_x as isize - distance_to_center as isize, center_x as isize + distance_to_center as isize] {
-            let x_mod = ((x + X_SIZE as isize) % X_SIZE as isize) as usize; // X_SIZE that the result can not be negative
-            // the first and last element has been taken into account when iterating the x lines
-            for y in (center_y as isize - distance_to_center as isize + 1)..(center_y as isize + distance_to_center as isize - 1) {
-                if y < 0 || y >= Y_SIZE as isize - 1 { continue; }
-                nearest_node_and_distance = NodeWithId::min(nearest_node_and_distance, self.find_nearest_neighbor_in_cell(NearestNeighbor::get_index_for_x_y(x_mod, y as usize), node));
-                let (cell_midpoint_lon, cell_midpoint_lat) = NearestNeighbor::calc_mid_point_of_cell(x_mod, y as usize);
-                radius = radius.min(distance(node.lon, node.lat, cell_midpoint_lon, cell_midpoint_lat));
+    }
+
+    /// returns the ids of all nodes within the radius (in meters) and their distances, ordered by distance
+    pub fn find_neighbors_in_radius(&self, node: &Node, radius: f64) -> Vec<(u32, f64)> {
+        if self.nodes.is_empty() || radius < 0.0 {
+            return Vec::new();
+        }
+        let position = to_unit_vector(node.lon, node.lat);
+        // chord length of the radius on the unit sphere
+        let max_chord = 2.0 * (radius.min(MAX_DISTANCE) / EARTH_RADIUS / 2.0).sin();
+        let mut indices = Vec::new();
+        self.search_radius(0, self.nodes.len(), &position, max_chord * max_chord, &mut indices);
+        let mut neighbors: Vec<(u32, f64)> = indices.into_iter()
+            .map(|index| (self.nodes[index].id, self.nodes[index].distance_to(node)))
+            .filter(|(_, dist)| *dist <= radius)
+            .collect();
+        neighbors.sort_by(|a, b| a.1.total_cmp(&b.1));
+        neighbors
+    }
+
+    fn search_radius(&self, start: usize, end: usize, position: &[f64; 3], max_squared_chord: f64, indices: &mut Vec<usize>) {
+        if end - start <= LEAF_SIZE {
+            for index in start..end {
+                // allow a small tolerance, the exact radius is checked with the haversine distance
+                if squared_chord(&self.nodes[index].position, position) <= max_squared_chord * (1.0 + 1e-9) {
+                    indices.push(index);
+                }
             }
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let axis = self.split_axes[mid] as usize;
+        let plane_distance = position[axis] - self.nodes[mid].position[axis];
+        if squared_chord(&self.nodes[mid].position, position) <= max_squared_chord * (1.0 + 1e-9) {
+            indices.push(mid);
+        }
+        let covers_plane = plane_distance * plane_distance <= max_squared_chord * (1.0 + 1e-9);
+        if plane_distance < 0.0 || covers_plane {
+            self.search_radius(start, mid, position, max_squared_chord, indices);
+        }
+        if plane_distance >= 0.0 || covers_plane {
+            self.search_radius(mid + 1, end, position, max_squared_chord, indices);
         }
-        (nearest_node_and_distance, if radius == f64::MAX { 0.0 } else { radius })
     }
 
-    fn find_nearest_neighbor_in_cell(&self, cell_index: usize, node: &Node) -> Option<(&NodeWithId, f64)> {
-        let mut nearest_node = None;
-        let mut distance_to_nearest_node = f64::MAX;
-        for x in &self.grid[cell_index] {
-            let distance = x.distance_to(node);
-            if distance_to_nearest_node > distance {
-                distance_to_nearest_node = distance;
-                nearest_node = Some(x);
+    /// returns the ids of all nodes within the bounding box. If lon_min is greater than lon_max,
+    /// the box is assumed to cross the antimeridian.
+    pub fn find_neighbors_in_bbox(&self, lon_min: f64, lat_min: f64, lon_max: f64, lat_max: f64) -> Vec<u32> {
+        if self.nodes.is_empty() || lat_min > lat_max {
+            return Vec::new();
+        }
+        let crosses_antimeridian = lon_min > lon_max;
+        let (min, max) = bbox_to_cartesian_bounds(lon_min, lat_min, if crosses_antimeridian { lon_max + 360.0 } else { lon_max }, lat_max);
+        let mut indices = Vec::new();
+        self.search_bounds(0, self.nodes.len(), &min, &max, &mut indices);
+        indices.into_iter().map(|index| &self.nodes[index]).filter(|n| {
+            let lon_inside = if crosses_antimeridian { n.lon >= lon_min || n.lon <= lon_max } else { n.lon >= lon_min && n.lon <= lon_max };
+            lon_inside && n.lat >= lat_min && n.lat <= lat_max
+        }).map(|n| n.id).collect()
+    }
+
+    fn search_bounds(&self, start: usize, end: usize, min: &[f64; 3], max: &[f64; 3], indices: &mut Vec<usize>) {
+        let inside = |position: &[f64; 3]| (0..3).all(|axis| position[axis] >= min[axis] && position[axis] <= max[axis]);
+        if end - start <= LEAF_SIZE {
+            for index in start..end {
+                if inside(&self.nodes[index].position) {
+                    indices.push(index);
+                }
             }
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let axis = self.split_axes[mid] as usize;
+        let split = self.nodes[mid].position[axis];
+        if inside(&self.nodes[mid].position) {
+            indices.push(mid);
+        }
+        if min[axis] <= split {
+            self.search_bounds(start, mid, min, max, indices);
         }
-        if nearest_node.is_none() {
-            None
-        } else {
-            Some((nearest_node.unwrap(), distance_to_nearest_node))
+        if max[axis] >= split {
+            self.search_bounds(mid + 1, end, min, max, indices);
         }
     }
+}
+
+fn to_unit_vector(lon_deg: f64, lat_deg: f64) -> [f64; 3] {
+    let lon = lon_deg.to_radians();
+    let lat = lat_deg.to_radians();
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
 
-    fn calc_mid_point_of_cell(x: usize, y: usize) -> (f64, f64) {
-        let (lon1_deg, lat1_deg) = NearestNeighbor::get_coords_of_x_y(x, y);
-        (lon1_deg + (x as f64 / X_SIZE as f64) * 0.5, lat1_deg + (y as f64 / Y_SIZE as f64) * 0.5)
+#[inline]
+fn squared_chord(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+/*
+ Calculates the axis aligned box in 3D which contains the whole lat/lon box on the unit sphere.
+ The coordinates are separable into a lat and lon part, so the extrema are located at the borders of
+ the box or at the equator respectively at the longitudes where sin or cos have their extrema.
+*/
+fn bbox_to_cartesian_bounds(lon_min: f64, lat_min: f64, lon_max: f64, lat_max: f64) -> ([f64; 3], [f64; 3]) {
+    let lat_min = lat_min.max(-90.0);
+    let lat_max = lat_max.min(90.0);
+    let mut lats = vec![lat_min, lat_max];
+    if lat_min < 0.0 && lat_max > 0.0 {
+        lats.push(0.0);
+    }
+    let mut lons = vec![lon_min, lon_max];
+    if lon_max - lon_min >= 360.0 {
+        lons.extend_from_slice(&[-180.0, -90.0, 0.0, 90.0]);
+    } else {
+        let mut extremum = (lon_min / 90.0).ceil() * 90.0;
+        while extremum < lon_max {
+            lons.push(extremum);
+            extremum += 90.0;
+        }
+    }
+    let mut min = [f64::MAX; 3];
+    let mut max = [f64::MIN; 3];
+    for lat in &lats {
+        for lon in &lons {
+            let position = to_unit_vector(*lon, *lat);
+            for axis in 0..3 {
+                min[axis] = min[axis].min(position[axis]);
+                max[axis] = max[axis].max(position[axis]);
+            }
+        }
+    }
+    // compensate rounding errors at the borders
+    for axis in 0..3 {
+        min[axis] -= 1e-12;
+        max[axis] += 1e-12;
+    }
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    fn random_nodes(rng: &mut XorShiftRng, count: usize) -> Vec<Node> {
+        (0..count).map(|_| Node { lat: rng.gen_range(-90.0..90.0), lon: rng.gen_range(-180.0..180.0) }).collect()
     }
 
-    fn get_coords_of_x_y(x: usize, y: usize) -> (f64, f64) {
-        let p_x = (x as f64 / X_SIZE as f64) * 360.0;
-        let p_y = (y as f64 / Y_SIZE as f64) * 180.0;
-        (p_x - 180.0, p_y as f64 - 90.0)
+    fn brute_force(nodes: &[Node], node: &Node) -> Vec<f64> {
+        let mut distances: Vec<f64> = nodes.iter().map(|n| distance(n.lon, n.lat, node.lon, node.lat)).collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+        distances
     }
 
-    #[inline]
-    fn get_index_for_x_y(x: usize, y: usize) -> usize {
-        x + (y * X_SIZE)
+    #[test]
+    fn k_nearest_matches_brute_force() {
+        let mut rng = XorShiftRng::seed_from_u64(7);
+        let nodes = random_nodes(&mut rng, 2000);
+        let nearest_neighbor = NearestNeighbor::new(&nodes);
+        for query in random_nodes(&mut rng, 100) {
+            let expected = brute_force(&nodes, &query);
+            let neighbors = nearest_neighbor.find_k_nearest_neighbors(&query, 10);
+            assert_eq!(neighbors.len(), 10);
+            for (i, (id, dist)) in neighbors.iter().enumerate() {
+                assert!((dist - expected[i]).abs() < 1e-3, "neighbor {} of {:?}", i, query);
+                let node = &nodes[*id as usize];
+                assert!((distance(node.lon, node.lat, query.lon, query.lat) - dist).abs() < 1e-6);
+            }
+        }
     }
 
-    #[inline]
-    fn get_x_y_for_index(index: usize) -> (usize, usize) {
-        let y = index / X_SIZE;
-        let x = index - (y * X_SIZE);
-        (x, y)
+    #[test]
+    fn radius_matches_brute_force() {
+        let mut rng = XorShiftRng::seed_from_u64(11);
+        let nodes = random_nodes(&mut rng, 2000);
+        let nearest_neighbor = NearestNeighbor::new(&nodes);
+        for query in random_nodes(&mut rng, 50) {
+            let radius = 1_000_000.0;
+            let expected = brute_force(&nodes, &query).into_iter().filter(|dist| *dist <= radius).count();
+            assert_eq!(nearest_neighbor.find_neighbors_in_radius(&query, radius).len(), expected);
+        }
     }
 
-    fn get_cell_for_node(node: &Node) -> usize {
-        let lon = if node.lon >= 180.0 { -180.0 } else { node.lon };
-        let x = (((lon + 180.0) / 360.0) * X_SIZE as f64) as usize;
-        let y = (((node.lat + 90.0)/  180.0) * Y_SIZE as f64) as usize;
-        NearestNeighbor::get_index_for_x_y(x, y)
+    #[test]
+    fn k_is_limited_to_the_number_of_nodes() {
+        let nodes = vec![Node { lat: 0.0, lon: 179.9 }, Node { lat: 0.0, lon: -179.9 }, Node { lat: 0.0, lon: 0.0 }];
+        let nearest_neighbor = NearestNeighbor::new(&nodes);
+        let neighbors = nearest_neighbor.find_k_nearest_neighbors(&Node { lat: 0.0, lon: 180.0 }, usize::MAX);
+        assert_eq!(neighbors.len(), 3);
+        assert_eq!(neighbors[2].0, 2);
+        assert!(nearest_neighbor.find_k_nearest_neighbors(&Node { lat: 0.0, lon: 0.0 }, 0).is_empty());
     }
 }
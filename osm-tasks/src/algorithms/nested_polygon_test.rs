@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use crate::algorithms::polygon_test::PointInPolygonTest;
+use crate::algorithms::land_test::LandTest;
+use crate::model::land_polygons::{LandPolygons, ring_contains, bounding_box};
+
+// size of the grid cells used to find the water rings around a ring in degrees
+const CELL_SIZE: f64 = 1.0;
+
+/// Land test for polygons with holes and inland waters. The rings are grouped by their nesting level
+/// (land, water enclosed by land, island in this water, ...), so that a point is on land if it is
+/// inside of an odd number of nested rings.
+pub struct NestedPolygonTest {
+    // one test per nesting level, the rings of level i are inside of the rings of level i - 1
+    levels: Vec<PointInPolygonTest>,
+}
+
+impl NestedPolygonTest {
+    /// inland waters (lakes, inland seas) are only treated as water if include_inland_water is set,
+    /// otherwise they are part of the surrounding land.
+    pub fn new(polygons: &LandPolygons, include_inland_water: bool) -> NestedPolygonTest {
+        // rings with a flag if the inside of the ring is land
+        let mut rings: Vec<(&Vec<(f64, f64)>, bool)> = Vec::new();
+        for polygon in polygons.land.iter() {
+            rings.push((&polygon.outer, true));
+            rings.extend(polygon.holes.iter().map(|hole| (hole, false)));
+        }
+        if include_inland_water && !polygons.inland_water.is_empty() {
+            // inland waters which are not inside of land (e.g. mapped lagoons at the coast) would turn the sea into land
+            let land_test = PointInPolygonTest::new(&polygons.land_outer_rings());
+            let mut number_dropped_waters = 0;
+            for polygon in polygons.inland_water.iter() {
+                if !land_test.check_intersection(polygon.outer[0]) {
+                    number_dropped_waters += 1;
+                    continue;
+                }
+                rings.push((&polygon.outer, false));
+                rings.extend(polygon.holes.iter().map(|hole| (hole, true)));
+            }
+            if number_dropped_waters > 0 {
+                println!("Ignored {} inland waters which are not inside of a land polygon", number_dropped_waters);
+            }
+        }
+
+        // index the water rings by the grid cells their bounding box overlaps
+        let mut water_cells: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (i, (ring, is_land)) in rings.iter().enumerate() {
+            if *is_land {
+                continue;
+            }
+            let (lon_min, lat_min, lon_max, lat_max) = bounding_box(ring);
+            for x in cell_index(lon_min)..=cell_index(lon_max) {
+                for y in cell_index(lat_min)..=cell_index(lat_max) {
+                    water_cells.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+
+        // land and water alternate, so the level is twice the number of enclosing water rings (plus one for water)
+        let mut levels: Vec<Vec<&Vec<(f64, f64)>>> = Vec::new();
+        for (i, (ring, is_land)) in rings.iter().enumerate() {
+            let point = ring[0];
+            let number_enclosing_waters = water_cells.get(&(cell_index(point.0), cell_index(point.1)))
+                .map(|candidates| candidates.iter().filter(|j| **j != i && ring_contains(rings[**j].0, point)).count())
+                .unwrap_or(0);
+            let level = 2 * number_enclosing_waters + if *is_land { 0 } else { 1 };
+            if levels.len() <= level {
+                levels.resize(level + 1, Vec::new());
+            }
+            levels[level].push(*ring);
+        }
+        println!("Sorted {} rings into {} nesting levels", rings.len(), levels.len());
+        NestedPolygonTest { levels: levels.iter().map(|level| PointInPolygonTest::new(level)).collect() }
+    }
+
+    /// true if the segment between the points (lon, lat) crosses the boundary between land and water
+    pub fn crosses_boundary(&self, from: (f64, f64), to: (f64, f64)) -> bool {
+        self.levels.iter().any(|level| level.crosses_edge(from, to))
+    }
+}
+
+impl LandTest for NestedPolygonTest {
+    fn check_intersection(&self, point: (f64, f64)) -> bool {
+        let mut depth = 0;
+        for level in self.levels.iter() {
+            if !level.check_intersection(point) {
+                break;
+            }
+            depth += 1;
+        }
+        depth % 2 == 1
+    }
+}
+
+fn cell_index(degrees: f64) -> i32 {
+    (degrees / CELL_SIZE).floor() as i32
+}
@@ -0,0 +1,67 @@
+use crate::model::land_polygons::{LandPolygons, PolygonWithHoles, bounding_box};
+use crate::model::region::BoundingBox;
+
+/// Clips all polygons to the bounding box. Polygons outside of the box are removed, the rings of the
+/// other polygons are clipped with the Sutherland-Hodgman algorithm. Clipped rings may contain
+/// segments along the border of the box, which does not affect the point in polygon test inside of it.
+pub fn clip_polygons_to_bbox(polygons: &LandPolygons, bbox: &BoundingBox) -> LandPolygons {
+    LandPolygons::new(clip_polygon_list(&polygons.land, bbox), clip_polygon_list(&polygons.inland_water, bbox))
+}
+
+fn clip_polygon_list(polygons: &[PolygonWithHoles], bbox: &BoundingBox) -> Vec<PolygonWithHoles> {
+    polygons.iter().filter_map(|polygon| {
+        let outer = clip_ring(&polygon.outer, bbox)?;
+        let holes = polygon.holes.iter().filter_map(|hole| clip_ring(hole, bbox)).collect();
+        Some(PolygonWithHoles { outer, holes })
+    }).collect()
+}
+
+fn clip_ring(ring: &[(f64, f64)], bbox: &BoundingBox) -> Option<Vec<(f64, f64)>> {
+    let (lon_min, lat_min, lon_max, lat_max) = bounding_box(ring);
+    if lon_max < bbox.lon_min || lon_min > bbox.lon_max || lat_max < bbox.lat_min || lat_min > bbox.lat_max {
+        return None;
+    }
+    if lon_min >= bbox.lon_min && lon_max <= bbox.lon_max && lat_min >= bbox.lat_min && lat_max <= bbox.lat_max {
+        return Some(ring.to_vec());
+    }
+    // clip against each border, the ring is handled as open ring and closed again afterwards
+    let mut points: Vec<(f64, f64)> = ring[..ring.len() - 1].to_vec();
+    points = clip_against_border(&points, |(lon, _)| lon >= bbox.lon_min, |a, b| intersect_lon(a, b, bbox.lon_min));
+    points = clip_against_border(&points, |(lon, _)| lon <= bbox.lon_max, |a, b| intersect_lon(a, b, bbox.lon_max));
+    points = clip_against_border(&points, |(_, lat)| lat >= bbox.lat_min, |a, b| intersect_lat(a, b, bbox.lat_min));
+    points = clip_against_border(&points, |(_, lat)| lat <= bbox.lat_max, |a, b| intersect_lat(a, b, bbox.lat_max));
+    if points.len() < 3 {
+        return None;
+    }
+    points.push(points[0]);
+    Some(points)
+}
+
+fn clip_against_border<I, S>(points: &[(f64, f64)], is_inside: I, intersection: S) -> Vec<(f64, f64)>
+    where I: Fn((f64, f64)) -> bool, S: Fn((f64, f64), (f64, f64)) -> (f64, f64) {
+    let mut result = Vec::with_capacity(points.len());
+    for i in 0..points.len() {
+        let current = points[i];
+        let previous = points[(i + points.len() - 1) % points.len()];
+        match (is_inside(previous), is_inside(current)) {
+            (true, true) => result.push(current),
+            (true, false) => result.push(intersection(previous, current)),
+            (false, true) => {
+                result.push(intersection(previous, current));
+                result.push(current);
+            }
+            (false, false) => {}
+        }
+    }
+    result
+}
+
+fn intersect_lon(a: (f64, f64), b: (f64, f64), lon: f64) -> (f64, f64) {
+    let t = (lon - a.0) / (b.0 - a.0);
+    (lon, a.1 + t * (b.1 - a.1))
+}
+
+fn intersect_lat(a: (f64, f64), b: (f64, f64), lat: f64) -> (f64, f64) {
+    let t = (lat - a.1) / (b.1 - a.1);
+    (a.0 + t * (b.0 - a.0), lat)
+}
@@ -0,0 +1,303 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use rayon::prelude::*;
+use serde::Serialize;
+use crate::import::coastline_merger::{find_self_intersections, segment_intersection};
+use crate::model::grid_graph::EARTH_RADIUS;
+use crate::model::land_polygons::{LandPolygons, PolygonWithHoles};
+
+// number of times the tolerance is halved if the simplified ring intersects itself
+const MAX_RETRIES: usize = 3;
+
+/// result of the simplification of all rings with the number of removed vertices
+#[derive(Serialize, Debug, Default, Clone)]
+pub struct SimplificationReport {
+    pub tolerance: f64,
+    pub number_of_rings: usize,
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+    // rings which were simplified with a smaller tolerance to avoid self intersections
+    pub reduced_tolerance_rings: usize,
+    // rings which could not be simplified without self intersections, intersections with other rings or collapsing
+    pub unsimplified_rings: usize,
+}
+
+impl SimplificationReport {
+    pub fn removed_vertices(&self) -> usize {
+        self.vertices_before - self.vertices_after
+    }
+}
+
+impl fmt::Display for SimplificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let removed_percentage = if self.vertices_before > 0 { self.removed_vertices() as f64 / self.vertices_before as f64 * 100.0 } else { 0.0 };
+        write!(f, "simplified {} rings with a tolerance of {} m, removed {} of {} vertices ({:.1}%), {} rings with reduced tolerance, {} rings unchanged",
+               self.number_of_rings, self.tolerance, self.removed_vertices(), self.vertices_before, removed_percentage, self.reduced_tolerance_rings, self.unsimplified_rings)
+    }
+}
+
+#[derive(PartialEq)]
+enum RingOutcome {
+    Simplified,
+    ReducedTolerance,
+    Unchanged,
+}
+
+/// Simplifies all rings with the Douglas-Peucker algorithm. The tolerance is the maximum distance in
+/// meters between the original and the simplified ring. A ring which would intersect itself after the
+/// simplification is simplified again with a smaller tolerance or kept unchanged. Rings which would
+/// intersect another ring of the same polygon or of a neighboring polygon are kept unchanged as well.
+pub fn simplify_polygons(polygons: &LandPolygons, tolerance: f64) -> (LandPolygons, SimplificationReport) {
+    let mut report = SimplificationReport { tolerance, ..Default::default() };
+    let land = simplify_polygon_list(&polygons.land, tolerance, &mut report);
+    let inland_water = simplify_polygon_list(&polygons.inland_water, tolerance, &mut report);
+    (LandPolygons::new(land, inland_water), report)
+}
+
+fn simplify_polygon_list(polygons: &Vec<PolygonWithHoles>, tolerance: f64, report: &mut SimplificationReport) -> Vec<PolygonWithHoles> {
+    let mut simplified: Vec<(PolygonWithHoles, Vec<RingOutcome>)> = polygons.par_iter().map(|polygon| {
+        let (outer, outer_outcome) = simplify_ring(&polygon.outer, tolerance);
+        let mut outcomes = vec![outer_outcome];
+        let holes = polygon.holes.iter().map(|hole| {
+            let (hole, outcome) = simplify_ring(hole, tolerance);
+            outcomes.push(outcome);
+            hole
+        }).collect();
+        (PolygonWithHoles { outer, holes }, outcomes)
+    }).collect();
+    restore_intersecting_rings(polygons, &mut simplified);
+    report.vertices_before += polygons.iter().map(|polygon| polygon.number_of_vertices()).sum::<usize>();
+    report.vertices_after += simplified.iter().map(|(polygon, _)| polygon.number_of_vertices()).sum::<usize>();
+    simplified.into_iter().map(|(polygon, outcomes)| {
+        for outcome in outcomes {
+            report.number_of_rings += 1;
+            match outcome {
+                RingOutcome::Simplified => {}
+                RingOutcome::ReducedTolerance => report.reduced_tolerance_rings += 1,
+                RingOutcome::Unchanged => report.unsimplified_rings += 1,
+            }
+        }
+        polygon
+    }).collect()
+}
+
+fn simplify_ring(ring: &[(f64, f64)], tolerance: f64) -> (Vec<(f64, f64)>, RingOutcome) {
+    if tolerance <= 0.0 || ring.len() <= 4 {
+        return (ring.to_vec(), RingOutcome::Unchanged);
+    }
+    let mut current_tolerance = tolerance;
+    for retry in 0..=MAX_RETRIES {
+        let simplified = simplify_closed_ring(ring, current_tolerance);
+        // a ring needs at least three distinct points and the closing point
+        if simplified.len() >= 4 && find_self_intersections(&simplified).is_empty() {
+            let outcome = if retry == 0 { RingOutcome::Simplified } else { RingOutcome::ReducedTolerance };
+            return (simplified, outcome);
+        }
+        current_tolerance /= 2.0;
+    }
+    (ring.to_vec(), RingOutcome::Unchanged)
+}
+
+/*
+ Restores the original of each simplified ring whose segments cross a segment of another ring, e.g. an
+ outer ring which cuts through one of its holes or through a neighboring island. Restoring a ring can
+ cause new crossings with simplified rings next to it, so the check is repeated until no simplified
+ ring crosses another ring. The original rings are assumed to be free of crossings.
+*/
+fn restore_intersecting_rings(polygons: &[PolygonWithHoles], simplified: &mut [(PolygonWithHoles, Vec<RingOutcome>)]) {
+    loop {
+        // (polygon index, ring index) of the rings, ring 0 is the outer ring and ring i the hole i - 1
+        let ring_ids: Vec<(usize, usize)> = simplified.iter().enumerate()
+            .flat_map(|(i, (polygon, _))| (0..=polygon.holes.len()).map(move |ring| (i, ring)))
+            .collect();
+        let rings: Vec<(&Vec<(f64, f64)>, bool)> = ring_ids.iter().map(|(i, ring)| {
+            let (polygon, outcomes) = &simplified[*i];
+            (if *ring == 0 { &polygon.outer } else { &polygon.holes[*ring - 1] }, outcomes[*ring] != RingOutcome::Unchanged)
+        }).collect();
+        let intersecting_rings = find_intersecting_rings(&rings);
+        if intersecting_rings.is_empty() {
+            return;
+        }
+        for index in intersecting_rings {
+            let (i, ring) = ring_ids[index];
+            let (polygon, outcomes) = &mut simplified[i];
+            if ring == 0 {
+                polygon.outer = polygons[i].outer.clone();
+            } else {
+                polygon.holes[ring - 1] = polygons[i].holes[ring - 1].clone();
+            }
+            outcomes[ring] = RingOutcome::Unchanged;
+        }
+    }
+}
+
+// indices of the simplified rings (flag is set) whose segments cross a segment of another ring
+fn find_intersecting_rings(rings: &[(&Vec<(f64, f64)>, bool)]) -> HashSet<usize> {
+    let number_segments: usize = rings.iter().map(|(ring, _)| ring.len().saturating_sub(1)).sum();
+    if !rings.iter().any(|(_, simplified)| *simplified) || number_segments == 0 {
+        return HashSet::new();
+    }
+    let (mut lon_min, mut lat_min, mut lon_max, mut lat_max) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for (lon, lat) in rings.iter().flat_map(|(ring, _)| ring.iter()) {
+        lon_min = lon_min.min(*lon);
+        lon_max = lon_max.max(*lon);
+        lat_min = lat_min.min(*lat);
+        lat_max = lat_max.max(*lat);
+    }
+    let cells_per_axis = ((number_segments as f64).sqrt().ceil() as usize).max(1);
+    let cell_width = ((lon_max - lon_min) / cells_per_axis as f64).max(1e-9);
+    let cell_height = ((lat_max - lat_min) / cells_per_axis as f64).max(1e-9);
+    let cell_of = |(lon, lat): (f64, f64)| -> (usize, usize) {
+        ((((lon - lon_min) / cell_width) as usize).min(cells_per_axis - 1), (((lat - lat_min) / cell_height) as usize).min(cells_per_axis - 1))
+    };
+    // (ring, segment) pairs of each cell the bounding box of the segment overlaps
+    let mut grid: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+    for (ring_index, (ring, _)) in rings.iter().enumerate() {
+        for segment in 0..ring.len().saturating_sub(1) {
+            let (x1, y1) = cell_of(ring[segment]);
+            let (x2, y2) = cell_of(ring[segment + 1]);
+            for x in x1.min(x2)..=x1.max(x2) {
+                for y in y1.min(y2)..=y1.max(y2) {
+                    grid.entry((x, y)).or_default().push((ring_index, segment));
+                }
+            }
+        }
+    }
+    grid.par_iter().map(|(_, segments)| {
+        let mut intersecting = HashSet::new();
+        if !segments.iter().any(|(ring, _)| rings[*ring].1) {
+            return intersecting;
+        }
+        for a in 0..segments.len() {
+            for b in (a + 1)..segments.len() {
+                let ((ring_a, segment_a), (ring_b, segment_b)) = (segments[a], segments[b]);
+                if ring_a == ring_b || !(rings[ring_a].1 || rings[ring_b].1) {
+                    continue;
+                }
+                let (p1, p2) = (rings[ring_a].0[segment_a], rings[ring_a].0[segment_a + 1]);
+                let (p3, p4) = (rings[ring_b].0[segment_b], rings[ring_b].0[segment_b + 1]);
+                // rings may touch each other at a shared node
+                if p1 == p3 || p1 == p4 || p2 == p3 || p2 == p4 {
+                    continue;
+                }
+                if segment_intersection(p1, p2, p3, p4).is_some() {
+                    intersecting.extend([ring_a, ring_b].iter().filter(|ring| rings[**ring].1));
+                }
+            }
+        }
+        intersecting
+    }).reduce(HashSet::new, |mut a, b| {
+        a.extend(b);
+        a
+    })
+}
+
+// splits the closed ring at the point farthest from the first point and simplifies both halves
+fn simplify_closed_ring(ring: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    let first = ring[0];
+    let split = (1..ring.len() - 1)
+        .max_by(|a, b| squared_planar_distance(first, ring[*a]).partial_cmp(&squared_planar_distance(first, ring[*b])).unwrap())
+        .unwrap();
+    let mut simplified = simplify_line(&ring[..=split], tolerance);
+    simplified.pop();
+    simplified.extend(simplify_line(&ring[split..], tolerance));
+    simplified
+}
+
+/// Douglas-Peucker simplification of a line, the first and last point are always kept
+pub fn simplify_line(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if points.len() <= 2 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        let mut max_distance = 0.0;
+        let mut max_index = start;
+        for i in start + 1..end {
+            let distance = segment_distance(points[i], points[start], points[end]);
+            if distance > max_distance {
+                max_distance = distance;
+                max_index = i;
+            }
+        }
+        if max_distance > tolerance {
+            keep[max_index] = true;
+            stack.push((start, max_index));
+            stack.push((max_index, end));
+        }
+    }
+    points.iter().zip(keep.iter()).filter(|(_, keep)| **keep).map(|(point, _)| *point).collect()
+}
+
+// distance in meters between the point and the segment in an equirectangular projection around the segment start
+fn segment_distance(point: (f64, f64), start: (f64, f64), end: (f64, f64)) -> f64 {
+    let (px, py) = project(point, start);
+    let (ex, ey) = project(end, start);
+    let length_squared = ex * ex + ey * ey;
+    let t = if length_squared > 0.0 { ((px * ex + py * ey) / length_squared).clamp(0.0, 1.0) } else { 0.0 };
+    let (dx, dy) = (px - t * ex, py - t * ey);
+    (dx * dx + dy * dy).sqrt()
+}
+
+fn squared_planar_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (x, y) = project(b, a);
+    x * x + y * y
+}
+
+fn project((lon, lat): (f64, f64), (origin_lon, origin_lat): (f64, f64)) -> (f64, f64) {
+    let x = (lon - origin_lon).to_radians() * origin_lat.to_radians().cos() * EARTH_RADIUS;
+    let y = (lat - origin_lat).to_radians() * EARTH_RADIUS;
+    (x, y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::land_polygons::ring_contains;
+
+    // tolerance which removes the bumps and notches of 0.03 degrees (about 3.3 km) of the test polygons
+    const TOLERANCE: f64 = 5000.0;
+
+    fn square_with_top(top: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+        let mut ring = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)];
+        ring.extend(top);
+        ring.extend(vec![(0.0, 1.0), (0.0, 0.0)]);
+        ring
+    }
+
+    fn square(lon_min: f64, lat_min: f64, lon_max: f64, lat_max: f64) -> Vec<(f64, f64)> {
+        vec![(lon_min, lat_min), (lon_max, lat_min), (lon_max, lat_max), (lon_min, lat_max), (lon_min, lat_min)]
+    }
+
+    #[test]
+    fn removes_small_bumps() {
+        let polygon = PolygonWithHoles::new(square_with_top(vec![(0.55, 1.0), (0.5, 1.03), (0.45, 1.0)]));
+        let (simplified, report) = simplify_polygons(&LandPolygons::new(vec![polygon], vec![]), TOLERANCE);
+        assert_eq!(simplified.land[0].outer, vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)]);
+        assert_eq!(report.unsimplified_rings, 0);
+    }
+
+    #[test]
+    fn outer_ring_does_not_cut_through_its_hole() {
+        let mut polygon = PolygonWithHoles::new(square_with_top(vec![(0.55, 1.0), (0.5, 1.03), (0.45, 1.0)]));
+        // hole inside of the bump, the straightened top of the outer ring would cross it
+        polygon.holes.push(square(0.49, 0.99, 0.51, 1.01));
+        let (simplified, report) = simplify_polygons(&LandPolygons::new(vec![polygon.clone()], vec![]), TOLERANCE);
+        assert_eq!(simplified.land[0].outer, polygon.outer);
+        assert!(ring_contains(&simplified.land[0].outer, (0.5, 1.005)));
+        assert!(report.unsimplified_rings >= 1);
+    }
+
+    #[test]
+    fn outer_ring_does_not_cut_through_a_neighboring_island() {
+        let coast = PolygonWithHoles::new(square_with_top(vec![(0.55, 1.0), (0.5, 0.97), (0.45, 1.0)]));
+        // island in the notch, the straightened top of the coast would cross it
+        let island = PolygonWithHoles::new(square(0.49, 0.985, 0.51, 1.005));
+        let (simplified, _) = simplify_polygons(&LandPolygons::new(vec![coast.clone(), island], vec![]), TOLERANCE);
+        assert_eq!(simplified.land[0].outer, coast.outer);
+        assert!(!ring_contains(&simplified.land[0].outer, (0.5, 0.99)));
+    }
+}
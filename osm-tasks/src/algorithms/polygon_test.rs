@@ -0,0 +1,268 @@
+use crate::model::land_polygons::bounding_box;
+
+// size of the grid cells in degrees
+const CELL_SIZE: f64 = 0.1;
+
+/// Point in polygon test for a set of rings (lon, lat). The bounding box of the rings is divided into
+/// grid cells which store the edges overlapping the cell and whether the center of the cell is inside.
+/// A point is inside if the segment from the point to the center of its cell crosses the edges of the
+/// cell an even number of times and the center is inside, so only the edges of one cell are tested.
+pub struct PointInPolygonTest {
+    edges: Vec<((f64, f64), (f64, f64))>,
+    lon_min: f64,
+    lat_min: f64,
+    number_columns: usize,
+    number_rows: usize,
+    // edge indices of cell i are cell_edges[cell_offsets[i]..cell_offsets[i + 1]]
+    cell_offsets: Vec<u32>,
+    cell_edges: Vec<u32>,
+    center_inside: Vec<bool>,
+}
+
+impl PointInPolygonTest {
+    pub fn new<R: AsRef<[(f64, f64)]>>(rings: &[R]) -> PointInPolygonTest {
+        let mut edges = Vec::new();
+        for ring in rings.iter().map(|ring| ring.as_ref()).filter(|ring| ring.len() > 2) {
+            for i in 0..ring.len() {
+                let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+                if a != b {
+                    edges.push((a, b));
+                }
+            }
+        }
+        let (mut lon_min, mut lat_min, mut lon_max, mut lat_max) = rings.iter()
+            .map(|ring| ring.as_ref())
+            .filter(|ring| !ring.is_empty())
+            .map(bounding_box)
+            .fold((f64::MAX, f64::MAX, f64::MIN, f64::MIN), |a, b| (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)));
+        if edges.is_empty() {
+            lon_min = 0.0;
+            lat_min = 0.0;
+            lon_max = 0.0;
+            lat_max = 0.0;
+        }
+        // one additional column on the left, its centers are outside of all rings
+        lon_min -= CELL_SIZE;
+        let number_columns = ((lon_max - lon_min) / CELL_SIZE).floor() as usize + 1;
+        let number_rows = ((lat_max - lat_min) / CELL_SIZE).floor() as usize + 1;
+        let mut polygon_test = PointInPolygonTest {
+            edges,
+            lon_min,
+            lat_min,
+            number_columns,
+            number_rows,
+            cell_offsets: vec![],
+            cell_edges: vec![],
+            center_inside: vec![],
+        };
+        polygon_test.index_edges();
+        polygon_test.classify_centers();
+        polygon_test
+    }
+
+    /// true if the point (lon, lat) is inside of an odd number of rings
+    pub fn check_intersection(&self, point: (f64, f64)) -> bool {
+        let (column, row) = match self.cell_of(point) {
+            Some(cell) => cell,
+            None => return false,
+        };
+        let cell = row * self.number_columns + column;
+        let center = self.cell_center(column, row);
+        let mut inside = self.center_inside[cell];
+        for edge_index in self.edges_of_cell(cell) {
+            let (a, b) = self.edges[*edge_index as usize];
+            if segments_cross(point, center, a, b) {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// true if the segment p-q intersects an edge of the rings. Only the edges of the cells overlapping the
+    /// bounding box of the segment are tested, so the segment should be short compared to the cells.
+    pub fn crosses_edge(&self, p: (f64, f64), q: (f64, f64)) -> bool {
+        if self.edges.is_empty() {
+            return false;
+        }
+        let mut crosses = false;
+        self.for_each_cell_of_edge(p, q, |cell| {
+            crosses = crosses || self.edges_of_cell(cell).iter().any(|edge_index| {
+                let (a, b) = self.edges[*edge_index as usize];
+                segments_intersect(p, q, a, b)
+            });
+        });
+        crosses
+    }
+
+    fn cell_of(&self, (lon, lat): (f64, f64)) -> Option<(usize, usize)> {
+        let column = ((lon - self.lon_min) / CELL_SIZE).floor();
+        let row = ((lat - self.lat_min) / CELL_SIZE).floor();
+        if column < 0.0 || row < 0.0 || column >= self.number_columns as f64 || row >= self.number_rows as f64 {
+            return None;
+        }
+        Some((column as usize, row as usize))
+    }
+
+    fn cell_center(&self, column: usize, row: usize) -> (f64, f64) {
+        (self.lon_min + (column as f64 + 0.5) * CELL_SIZE, self.lat_min + (row as f64 + 0.5) * CELL_SIZE)
+    }
+
+    fn edges_of_cell(&self, cell: usize) -> &[u32] {
+        &self.cell_edges[self.cell_offsets[cell] as usize..self.cell_offsets[cell + 1] as usize]
+    }
+
+    // assigns each edge to all cells its bounding box overlaps, the edges of a cell are sorted by index
+    fn index_edges(&mut self) {
+        let number_cells = self.number_columns * self.number_rows;
+        let mut counts = vec![0u32; number_cells + 1];
+        for (a, b) in self.edges.iter() {
+            self.for_each_cell_of_edge(*a, *b, |cell| counts[cell] += 1);
+        }
+        let mut offsets = Vec::with_capacity(number_cells + 1);
+        let mut sum = 0;
+        for count in counts.iter() {
+            offsets.push(sum);
+            sum += count;
+        }
+        let mut next = offsets.clone();
+        let mut cell_edges = vec![0u32; sum as usize];
+        for (i, (a, b)) in self.edges.iter().enumerate() {
+            self.for_each_cell_of_edge(*a, *b, |cell| {
+                cell_edges[next[cell] as usize] = i as u32;
+                next[cell] += 1;
+            });
+        }
+        self.cell_offsets = offsets;
+        self.cell_edges = cell_edges;
+    }
+
+    fn for_each_cell_of_edge<F: FnMut(usize)>(&self, a: (f64, f64), b: (f64, f64), mut f: F) {
+        let clamp_column = |lon: f64| (((lon - self.lon_min) / CELL_SIZE).floor().max(0.0) as usize).min(self.number_columns - 1);
+        let clamp_row = |lat: f64| (((lat - self.lat_min) / CELL_SIZE).floor().max(0.0) as usize).min(self.number_rows - 1);
+        for row in clamp_row(a.1.min(b.1))..=clamp_row(a.1.max(b.1)) {
+            for column in clamp_column(a.0.min(b.0))..=clamp_column(a.0.max(b.0)) {
+                f(row * self.number_columns + column);
+            }
+        }
+    }
+
+    // walks along each row from the first column, which is outside, and counts the crossings between
+    // the centers of neighboring cells
+    fn classify_centers(&mut self) {
+        let mut center_inside = vec![false; self.number_columns * self.number_rows];
+        for row in 0..self.number_rows {
+            let lat = self.cell_center(0, row).1;
+            let mut inside = false;
+            for column in 1..self.number_columns {
+                let cell = row * self.number_columns + column;
+                let (lon_from, lon_to) = (self.cell_center(column - 1, row).0, self.cell_center(column, row).0);
+                let crossings = merge_unique(self.edges_of_cell(cell - 1), self.edges_of_cell(cell)).into_iter().filter(|edge_index| {
+                    let (a, b) = self.edges[*edge_index as usize];
+                    if (a.1 > lat) == (b.1 > lat) {
+                        return false;
+                    }
+                    let lon = a.0 + (lat - a.1) * (b.0 - a.0) / (b.1 - a.1);
+                    lon >= lon_from && lon < lon_to
+                }).count();
+                if crossings % 2 == 1 {
+                    inside = !inside;
+                }
+                center_inside[cell] = inside;
+            }
+        }
+        self.center_inside = center_inside;
+    }
+}
+
+// union of two sorted lists of edge indices
+fn merge_unique(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let next = if j >= b.len() || (i < a.len() && a[i] <= b[j]) { a[i] } else { b[j] };
+        if i < a.len() && a[i] == next {
+            i += 1;
+        }
+        if j < b.len() && b[j] == next {
+            j += 1;
+        }
+        merged.push(next);
+    }
+    merged
+}
+
+// true if the segment p-q crosses the edge a-b, end points of the edge on the line through p and q are
+// counted on one side only, so that a vertex is crossed exactly once by its two edges
+fn segments_cross(p: (f64, f64), q: (f64, f64), a: (f64, f64), b: (f64, f64)) -> bool {
+    let side_a = orientation(p, q, a) > 0.0;
+    let side_b = orientation(p, q, b) > 0.0;
+    if side_a == side_b {
+        return false;
+    }
+    let side_p = orientation(a, b, p);
+    let side_q = orientation(a, b, q);
+    (side_p > 0.0) != (side_q > 0.0)
+}
+
+// true if the segments p-q and a-b have a common point, collinear segments are not counted
+fn segments_intersect(p: (f64, f64), q: (f64, f64), a: (f64, f64), b: (f64, f64)) -> bool {
+    let (side_a, side_b) = (orientation(p, q, a), orientation(p, q, b));
+    let (side_p, side_q) = (orientation(a, b, p), orientation(a, b, q));
+    if side_a == 0.0 && side_b == 0.0 {
+        return false;
+    }
+    side_a * side_b <= 0.0 && side_p * side_q <= 0.0
+}
+
+fn orientation(a: (f64, f64), b: (f64, f64), c: (f64, f64)) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+    use crate::model::land_polygons::ring_contains;
+
+    // concave star with 12 spikes around (10, 50)
+    fn star() -> Vec<(f64, f64)> {
+        (0..24).map(|i| {
+            let angle = (i as f64 * 15.0).to_radians();
+            let radius = if i % 2 == 0 { 2.0 } else { 0.7 };
+            (10.0 + radius * angle.cos(), 50.0 + radius * angle.sin())
+        }).collect()
+    }
+
+    #[test]
+    fn matches_ray_casting_for_concave_ring_with_hole() {
+        let hole = vec![(9.8, 49.8), (10.2, 49.8), (10.2, 50.2), (9.8, 50.2)];
+        let rings = vec![star(), hole];
+        let polygon_test = PointInPolygonTest::new(&rings);
+        let mut rng = XorShiftRng::seed_from_u64(42);
+        for _ in 0..20_000 {
+            let point = (rng.gen_range(7.5..12.5), rng.gen_range(47.5..52.5));
+            let expected = rings.iter().filter(|ring| ring_contains(ring, point)).count() % 2 == 1;
+            assert_eq!(polygon_test.check_intersection(point), expected, "point {:?}", point);
+        }
+    }
+
+    #[test]
+    fn finds_segments_crossing_edges() {
+        let polygon_test = PointInPolygonTest::new(&[vec![(0.0, 0.0), (1.0, 0.0), (1.0, 0.001), (0.0, 0.001)]]);
+        // both end points are outside of the thin ring
+        assert!(polygon_test.crosses_edge((0.5, -0.001), (0.5, 0.002)));
+        assert!(!polygon_test.crosses_edge((0.5, 0.002), (0.6, 0.003)));
+        assert!(!polygon_test.crosses_edge((1.5, -0.001), (1.5, 0.002)));
+        assert!(!PointInPolygonTest::new::<Vec<(f64, f64)>>(&[]).crosses_edge((0.0, 0.0), (1.0, 1.0)));
+    }
+
+    #[test]
+    fn points_outside_of_the_grid_are_outside() {
+        let polygon_test = PointInPolygonTest::new(&[star()]);
+        assert!(polygon_test.check_intersection((10.0, 51.0)));
+        assert!(!polygon_test.check_intersection((-170.0, 0.0)));
+        assert!(!polygon_test.check_intersection((10.0, 89.0)));
+        assert!(!PointInPolygonTest::new::<Vec<(f64, f64)>>(&[]).check_intersection((0.0, 0.0)));
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use crate::algorithms::line_of_sight::LineOfSight;
+use crate::model::grid_graph::{Node, distance, EARTH_RADIUS};
+
+// the tolerance is doubled until the maximum number of waypoints is reached, above this it is ignored
+const MAX_TOLERANCE: f64 = 1_000_000.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RouteSimplificationOptions {
+    // maximum number of waypoints including start and destination
+    pub max_waypoints: Option<usize>,
+    // maximum distance in meters between a removed node and the leg which replaces it
+    pub tolerance: Option<f64>,
+}
+
+/// number of waypoints and length of the route before and after the simplification, lengths in meters
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteSimplificationReport {
+    pub(crate) waypoints_before: usize,
+    pub(crate) waypoints_after: usize,
+    pub(crate) distance_before: f64,
+    pub(crate) distance_after: f64,
+    // distance_after - distance_before, negative if the simplified route is shorter
+    pub(crate) distance_change: f64,
+    // tolerance which has been used, it is increased to reach the maximum number of waypoints
+    pub(crate) tolerance: Option<f64>,
+    // false if the maximum number of waypoints could not be reached without crossing land
+    pub(crate) max_waypoints_reached: bool,
+}
+
+impl fmt::Display for RouteSimplificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "reduced {} to {} waypoints, distance changed by {:.1} m ({:.1} m to {:.1} m)",
+               self.waypoints_before, self.waypoints_after, self.distance_change, self.distance_before, self.distance_after)
+    }
+}
+
+/// Removes nodes of the route whose great circle legs do not cross land. Starting at the first node, the
+/// next waypoint is the farthest node which can be reached with a free leg (found by binary search) and
+/// whose skipped nodes are at most tolerance meters away from the leg. Legs of the original route are
+/// always kept, even if they touch land. If there are more than max_waypoints waypoints, the tolerance
+/// is doubled until the limit is reached or the tolerance is dropped. Without a tolerance, the number
+/// of waypoints is only limited by the land.
+pub(crate) fn simplify_route(nodes: &[Node], line_of_sight: &LineOfSight, options: &RouteSimplificationOptions) -> (Vec<Node>, RouteSimplificationReport) {
+    let mut tolerance = options.tolerance;
+    let mut waypoints = simplify_with_tolerance(nodes, line_of_sight, tolerance);
+    if let Some(max_waypoints) = options.max_waypoints {
+        while waypoints.len() > max_waypoints && tolerance.is_some() {
+            let next_tolerance = tolerance.unwrap().max(500.0) * 2.0;
+            tolerance = if next_tolerance > MAX_TOLERANCE { None } else { Some(next_tolerance) };
+            waypoints = simplify_with_tolerance(nodes, line_of_sight, tolerance);
+        }
+    }
+    let distance_before = route_length(nodes);
+    let distance_after = route_length(&waypoints);
+    let report = RouteSimplificationReport {
+        waypoints_before: nodes.len(),
+        waypoints_after: waypoints.len(),
+        distance_before,
+        distance_after,
+        distance_change: distance_after - distance_before,
+        tolerance,
+        max_waypoints_reached: options.max_waypoints.is_none_or(|max_waypoints| waypoints.len() <= max_waypoints),
+    };
+    (waypoints, report)
+}
+
+/// sum of the great circle distances between consecutive nodes in meters
+pub fn route_length(nodes: &[Node]) -> f64 {
+    nodes.windows(2).map(|leg| distance(leg[0].lon, leg[0].lat, leg[1].lon, leg[1].lat)).sum()
+}
+
+fn simplify_with_tolerance(nodes: &[Node], line_of_sight: &LineOfSight, tolerance: Option<f64>) -> Vec<Node> {
+    if nodes.len() <= 2 {
+        return nodes.to_vec();
+    }
+    let is_valid_leg = |from: usize, to: usize| {
+        let within_tolerance = match tolerance {
+            Some(tolerance) => nodes[from + 1..to].iter().all(|node| distance_to_leg(node, &nodes[from], &nodes[to]) <= tolerance),
+            None => true,
+        };
+        within_tolerance && line_of_sight.is_free(&nodes[from], &nodes[to])
+    };
+    let mut waypoints = vec![nodes[0]];
+    let mut current = 0;
+    let last = nodes.len() - 1;
+    while current < last {
+        // binary search for the farthest valid leg, current + 1 is always valid
+        let next = if is_valid_leg(current, last) {
+            last
+        } else {
+            let (mut valid, mut invalid) = (current + 1, last);
+            while invalid - valid > 1 {
+                let middle = (valid + invalid) / 2;
+                if is_valid_leg(current, middle) {
+                    valid = middle;
+                } else {
+                    invalid = middle;
+                }
+            }
+            valid
+        };
+        waypoints.push(nodes[next]);
+        current = next;
+    }
+    waypoints
+}
+
+// distance in meters between the node and the great circle segment between from and to
+fn distance_to_leg(node: &Node, from: &Node, to: &Node) -> f64 {
+    let (p, a, b) = (to_unit_vector(node), to_unit_vector(from), to_unit_vector(to));
+    let normal = cross(&a, &b);
+    let normal_length = dot(&normal, &normal).sqrt();
+    let distance_to_end_points = distance(node.lon, node.lat, from.lon, from.lat).min(distance(node.lon, node.lat, to.lon, to.lat));
+    if normal_length < 1e-12 {
+        return distance_to_end_points;
+    }
+    // the projection of the node lies between both end points if it is on the inner side of both
+    let inside_from = dot(&cross(&normal, &a), &p) >= 0.0;
+    let inside_to = dot(&cross(&b, &normal), &p) >= 0.0;
+    if !inside_from || !inside_to {
+        return distance_to_end_points;
+    }
+    (dot(&normal, &p) / normal_length).abs().min(1.0).asin() * EARTH_RADIUS
+}
+
+fn to_unit_vector(node: &Node) -> [f64; 3] {
+    let lon = node.lon.to_radians();
+    let lat = node.lat.to_radians();
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn cross(a: &[f64; 3], b: &[f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+    use crate::model::land_polygons::LandPolygons;
+
+    fn nodes(positions: &[(f64, f64)]) -> Vec<Node> {
+        positions.iter().map(|(lon, lat)| Node { lon: *lon, lat: *lat }).collect()
+    }
+
+    fn positions(nodes: &[Node]) -> Vec<(f64, f64)> {
+        nodes.iter().map(|node| (node.lon, node.lat)).collect()
+    }
+
+    #[test]
+    fn distance_to_leg_is_the_distance_to_the_closest_point_of_the_leg() {
+        let (from, to) = (Node { lon: 0.0, lat: 0.0 }, Node { lon: 2.0, lat: 0.0 });
+        let expected = 0.1_f64.to_radians() * EARTH_RADIUS;
+        assert!((distance_to_leg(&Node { lon: 1.0, lat: 0.1 }, &from, &to) - expected).abs() < 1.0);
+        assert!((distance_to_leg(&Node { lon: 1.0, lat: -0.1 }, &from, &to) - expected).abs() < 1.0);
+        assert!(distance_to_leg(&Node { lon: 1.5, lat: 0.0 }, &from, &to) < 1e-6);
+        // beyond the end points the distance to the nearest end point counts
+        assert!((distance_to_leg(&Node { lon: 3.0, lat: 0.0 }, &from, &to) - distance(3.0, 0.0, 2.0, 0.0)).abs() < 1e-6);
+        assert!((distance_to_leg(&Node { lon: -1.0, lat: 1.0 }, &from, &to) - distance(-1.0, 1.0, 0.0, 0.0)).abs() < 1e-6);
+        // degenerated leg
+        assert!((distance_to_leg(&Node { lon: 1.0, lat: 1.0 }, &from, &from) - distance(1.0, 1.0, 0.0, 0.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn legs_of_the_simplified_route_do_not_cross_land() {
+        // island of 100 m width, the route goes around its northern end
+        let polygons = LandPolygons::from_coastline_rings(vec![vec![(10.0, 53.9), (10.0015, 53.9), (10.0015, 54.1), (10.0, 54.1), (10.0, 53.9)]]);
+        let polygon_test = NestedPolygonTest::new(&polygons, false);
+        let line_of_sight = LineOfSight::new(&polygon_test);
+        let route = nodes(&[(9.9, 54.0), (9.95, 54.08), (9.98, 54.12), (10.00075, 54.15), (10.02, 54.12), (10.05, 54.08), (10.1, 54.0)]);
+        let (waypoints, report) = simplify_route(&route, &line_of_sight, &RouteSimplificationOptions::default());
+        assert!(waypoints.len() > 2 && waypoints.len() < route.len());
+        let (waypoint_positions, route_positions) = (positions(&waypoints), positions(&route));
+        assert_eq!(waypoint_positions[0], route_positions[0]);
+        assert_eq!(waypoint_positions.last(), route_positions.last());
+        assert!(waypoints.windows(2).all(|leg| line_of_sight.is_free(&leg[0], &leg[1])));
+        assert_eq!(report.waypoints_after, waypoints.len());
+        assert!(report.distance_change < 0.0);
+    }
+
+    #[test]
+    fn tolerance_and_max_waypoints_limit_the_simplification() {
+        let polygon_test = NestedPolygonTest::new(&LandPolygons::from_coastline_rings(vec![]), false);
+        let line_of_sight = LineOfSight::new(&polygon_test);
+        // the middle node is about 1.1 km away from the direct leg
+        let route = nodes(&[(0.0, 0.0), (0.5, 0.0), (1.0, 0.01), (1.5, 0.0), (2.0, 0.0)]);
+        assert_eq!(simplify_route(&route, &line_of_sight, &RouteSimplificationOptions::default()).0.len(), 2);
+        let tolerance = RouteSimplificationOptions { max_waypoints: None, tolerance: Some(600.0) };
+        assert_eq!(positions(&simplify_route(&route, &line_of_sight, &tolerance).0), vec![(0.0, 0.0), (1.0, 0.01), (2.0, 0.0)]);
+        let max_waypoints = RouteSimplificationOptions { max_waypoints: Some(2), tolerance: Some(600.0) };
+        let (waypoints, report) = simplify_route(&route, &line_of_sight, &max_waypoints);
+        assert_eq!(waypoints.len(), 2);
+        assert!(report.max_waypoints_reached && report.tolerance.unwrap() > 1000.0);
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::{BinaryHeap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::algorithms::line_of_sight::LineOfSight;
+use crate::model::adjacency_array::AdjacencyArray;
+use crate::model::grid_graph::{GridGraph, distance};
+use crate::model::priority_heap_item::PriorityHeapItem;
+use crate::model::weight::{Weight, INFINITE_WEIGHT, meters_to_weight};
+
+// number of popped nodes after which the cancel flag is checked
+const CANCEL_CHECK_INTERVAL: u32 = 1024;
+// the cache is cleared when it holds this many legs, one entry takes about 16 bytes
+const MAX_CACHED_LEGS: usize = 1_000_000;
+
+/// Results of the line of sight tests between two graph nodes. The cache is kept between the searches on
+/// the same graph, as the legs from the nodes at the corners of the coastline are tested again and again.
+#[derive(Default)]
+pub(crate) struct LineOfSightCache {
+    // the node ids of a leg are ordered, the test is symmetric
+    legs: HashMap<(u32, u32), bool>,
+}
+
+impl LineOfSightCache {
+    pub fn clear(&mut self) {
+        self.legs.clear();
+    }
+
+    fn is_free(&mut self, line_of_sight: &LineOfSight, graph: &GridGraph, from: u32, to: u32) -> bool {
+        let leg = (from.min(to), from.max(to));
+        if let Some(is_free) = self.legs.get(&leg) {
+            return *is_free;
+        }
+        if self.legs.len() >= MAX_CACHED_LEGS {
+            self.legs.clear();
+        }
+        let is_free = line_of_sight.is_free(&graph.nodes[from as usize], &graph.nodes[to as usize]);
+        self.legs.insert(leg, is_free);
+        is_free
+    }
+}
+
+/// Any-angle search (Lazy Theta*) on the grid graph. A node reached over an edge inherits the parent of
+/// its predecessor, so the route can leave the grid edges and connect non-adjacent nodes by a great
+/// circle leg. The line of sight to the parent is only tested against the land polygons when the node
+/// is expanded, if the leg crosses land the best closed neighbor becomes the parent instead.
+pub(crate) struct ThetaStar<'a> {
+    adj_ref: AdjacencyArray,
+    graph_ref: &'a GridGraph,
+    line_of_sight: LineOfSight<'a>,
+    line_of_sight_cache: &'a mut LineOfSightCache,
+    heap: BinaryHeap<PriorityHeapItem>,
+    distances: Vec<Weight>,
+    parents: Vec<u32>,
+    closed: Vec<bool>,
+    source_node: u32,
+    amount_nodes_popped: u32,
+}
+
+impl<'a> ThetaStar<'a> {
+    pub fn new(grid_graph: &'a GridGraph, line_of_sight: LineOfSight<'a>, line_of_sight_cache: &'a mut LineOfSightCache, source_node: u32) -> ThetaStar<'a> {
+        let number_of_nodes = grid_graph.nodes.len();
+        let mut heap = BinaryHeap::with_capacity(number_of_nodes);
+        let mut distances = vec![INFINITE_WEIGHT; number_of_nodes];
+        let mut parents = vec![u32::MAX; number_of_nodes];
+        distances[source_node as usize] = 0;
+        parents[source_node as usize] = source_node;
+        heap.push(PriorityHeapItem {
+            node_id: source_node,
+            distance: 0,
+            priority: 0,
+            previous_node: source_node,
+        });
+        ThetaStar {
+            adj_ref: grid_graph.adjacency_array(),
+            graph_ref: grid_graph,
+            line_of_sight,
+            line_of_sight_cache,
+            heap,
+            distances,
+            parents,
+            closed: vec![false; number_of_nodes],
+            source_node,
+            amount_nodes_popped: 0,
+        }
+    }
+
+    pub fn find_route(&mut self, destination_node: u32) -> Option<(Vec<u32>, Weight, u32)> {
+        self.find_route_cancellable(destination_node, &AtomicBool::new(false))
+    }
+
+    /// Returns the waypoints of the route, only start, destination and the nodes where the route changes
+    /// its direction are part of it. The consecutive waypoints are connected by great circle legs.
+    pub fn find_route_cancellable(&mut self, destination_node: u32, cancelled: &AtomicBool) -> Option<(Vec<u32>, Weight, u32)> {
+        self.theta_star(destination_node, cancelled);
+        if self.closed[destination_node as usize] {
+            Some((self.traverse_route(destination_node), self.distances[destination_node as usize], self.amount_nodes_popped))
+        } else {
+            None
+        }
+    }
+
+    fn theta_star(&mut self, destination_node: u32, cancelled: &AtomicBool) {
+        loop {
+            if self.amount_nodes_popped % CANCEL_CHECK_INTERVAL == 0 && cancelled.load(Ordering::Relaxed) {
+                println!("Cancelled search from {} to {}", self.source_node, destination_node);
+                return;
+            }
+            if let Some(heap_element) = self.heap.pop() {
+                let node = heap_element.node_id;
+                if self.closed[node as usize] || heap_element.distance != self.distances[node as usize] {
+                    // outdated heap element
+                    continue;
+                }
+                self.amount_nodes_popped += 1;
+                self.set_parent(node, heap_element.previous_node);
+                self.closed[node as usize] = true;
+                if node == destination_node {
+                    // found dest
+                    break;
+                }
+                let parent = self.parents[node as usize];
+                let parent_distance = self.distances[parent as usize];
+                let (neighbors, _) = self.adj_ref.get_neighbors_of_node_and_distances(node);
+                for &next_node in neighbors.iter() {
+                    if self.closed[next_node as usize] {
+                        continue;
+                    }
+                    // assume the line of sight from the parent, it is tested when next_node is expanded
+                    let distance = parent_distance.saturating_add(self.leg_weight(parent, next_node));
+                    if distance < self.distances[next_node as usize] {
+                        self.distances[next_node as usize] = distance;
+                        self.parents[next_node as usize] = parent;
+                        self.heap.push(PriorityHeapItem {
+                            node_id: next_node,
+                            distance,
+                            priority: distance.saturating_add(self.graph_ref.get_distance(next_node, destination_node)),
+                            previous_node: node,
+                        });
+                    }
+                }
+            } else {
+                println!("Heap is empty but dest node not found. src {}, dest {}", self.source_node, destination_node);
+                return;
+            }
+        }
+    }
+
+    // if the leg from the parent crosses land, the closed node with the shortest distance over a graph
+    // edge becomes the parent. The predecessor, which relaxed the node over its edge, is always closed,
+    // the closed neighbors are only candidates if the graph has the reverse edge.
+    fn set_parent(&mut self, node: u32, predecessor: u32) {
+        let parent = self.parents[node as usize];
+        if parent == node || self.line_of_sight_cache.is_free(&self.line_of_sight, self.graph_ref, parent, node) {
+            return;
+        }
+        let mut best = (self.distances[predecessor as usize].saturating_add(self.edge_weight(predecessor, node)), predecessor);
+        let (neighbors, edge_weights) = self.adj_ref.get_neighbors_of_node_and_distances(node);
+        for i in 0..neighbors.len() {
+            let neighbor = neighbors[i];
+            if self.closed[neighbor as usize] {
+                let distance = self.distances[neighbor as usize].saturating_add(edge_weights[i]);
+                if distance < best.0 {
+                    best = (distance, neighbor);
+                }
+            }
+        }
+        self.distances[node as usize] = best.0;
+        self.parents[node as usize] = best.1;
+    }
+
+    // weight of the graph edge, the length of the leg if the nodes are not connected
+    fn edge_weight(&self, from: u32, to: u32) -> Weight {
+        let (neighbors, edge_weights) = self.adj_ref.get_neighbors_of_node_and_distances(from);
+        match neighbors.iter().position(|neighbor| *neighbor == to) {
+            Some(i) => edge_weights[i],
+            None => self.leg_weight(from, to),
+        }
+    }
+
+    fn leg_weight(&self, from: u32, to: u32) -> Weight {
+        let (from, to) = (&self.graph_ref.nodes[from as usize], &self.graph_ref.nodes[to as usize]);
+        meters_to_weight(distance(from.lon, from.lat, to.lon, to.lat))
+    }
+
+    fn traverse_route(&self, destination_node: u32) -> Vec<u32> {
+        let mut nodes = vec![destination_node];
+        let mut node = destination_node;
+        while node != self.source_node {
+            node = self.parents[node as usize];
+            nodes.push(node);
+        }
+        nodes.reverse();
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::algorithms::dijkstra::Dijkstra;
+    use crate::algorithms::land_test::LandTest;
+    use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+    use crate::config::Config;
+    use crate::model::grid_graph::{Edge, Node, GridType};
+    use crate::model::land_polygons::LandPolygons;
+
+    fn graph(nodes: Vec<Node>, edges: &[(u32, u32)]) -> GridGraph {
+        let mut graph = GridGraph { number_edges: 0, number_nodes: nodes.len() as i64, edges: vec![vec![]; nodes.len()], nodes, component_ids: vec![], removed_nodes: vec![], grid_type: GridType::Sphere };
+        for (source, target) in edges.iter() {
+            let (a, b) = (graph.nodes[*source as usize], graph.nodes[*target as usize]);
+            graph.add_new_edge(Edge { source: *source, target: *target, distance: meters_to_weight(distance(a.lon, a.lat, b.lon, b.lat)) });
+        }
+        graph
+    }
+
+    // grid of size x size nodes with a spacing of 0.01 degrees, each node is connected to its eight neighbors
+    fn grid(size: u32) -> GridGraph {
+        let nodes = (0..size * size).map(|i| Node { lon: (i % size) as f64 * 0.01, lat: (i / size) as f64 * 0.01 }).collect();
+        let mut edges = Vec::new();
+        for i in 0..size * size {
+            let (column, row) = ((i % size) as i64, (i / size) as i64);
+            for (dx, dy) in [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)] {
+                let (x, y) = (column + dx, row + dy);
+                if x >= 0 && y >= 0 && x < size as i64 && y < size as i64 {
+                    edges.push((i, (y * size as i64 + x) as u32));
+                }
+            }
+        }
+        graph(nodes, &edges)
+    }
+
+    fn land(rings: Vec<Vec<(f64, f64)>>) -> NestedPolygonTest {
+        NestedPolygonTest::new(&LandPolygons::from_coastline_rings(rings), false)
+    }
+
+    #[test]
+    fn routes_are_free_of_land_and_not_longer_than_dijkstra() {
+        Config::init_for_tests();
+        let size = 20;
+        let mut grid = grid(size);
+        // wall between lon 0.095 and 0.105 with a passage at the northern end, the nodes on it are removed
+        let polygon_test = land(vec![vec![(0.095, -0.01), (0.105, -0.01), (0.105, 0.155), (0.095, 0.155), (0.095, -0.01)]]);
+        for node in 0..grid.nodes.len() as u32 {
+            let position = grid.nodes[node as usize];
+            if polygon_test.check_intersection((position.lon, position.lat)) {
+                grid.remove_node(node);
+                for edges in grid.edges.iter_mut() {
+                    edges.retain(|edge| edge.target != node);
+                }
+            }
+        }
+        let (start, end) = (2 * size + 2, 3 * size + 17);
+        let mut dijkstra = Dijkstra::new(Arc::new(grid.adjacency_array()), start);
+        let (_, dijkstra_distance, _) = dijkstra.find_route(end).unwrap();
+        let mut cache = LineOfSightCache::default();
+        let line_of_sight = LineOfSight::new(&polygon_test);
+        let (route, theta_star_distance, _) = ThetaStar::new(&grid, LineOfSight::new(&polygon_test), &mut cache, start).find_route(end).unwrap();
+        assert_eq!((route[0], route[route.len() - 1]), (start, end));
+        // the route has to turn at the end of the wall
+        assert!(route.len() > 2);
+        assert!(route.windows(2).all(|leg| line_of_sight.is_free(&grid.nodes[leg[0] as usize], &grid.nodes[leg[1] as usize])));
+        assert!(theta_star_distance <= dijkstra_distance);
+        let length: Weight = route.windows(2).map(|leg| {
+            let (a, b) = (grid.nodes[leg[0] as usize], grid.nodes[leg[1] as usize]);
+            meters_to_weight(distance(a.lon, a.lat, b.lon, b.lat))
+        }).sum();
+        assert_eq!(length, theta_star_distance);
+    }
+
+    #[test]
+    fn open_water_routes_are_a_single_leg() {
+        Config::init_for_tests();
+        let grid = grid(10);
+        let polygon_test = land(vec![]);
+        let mut cache = LineOfSightCache::default();
+        let (route, _, _) = ThetaStar::new(&grid, LineOfSight::new(&polygon_test), &mut cache, 0).find_route(99).unwrap();
+        assert_eq!(route, vec![0, 99]);
+    }
+
+    #[test]
+    fn predecessor_is_the_parent_without_reverse_edges() {
+        Config::init_for_tests();
+        // one way edges 0 -> 1 -> 2, the leg 0 -> 2 crosses a wall
+        let nodes = vec![Node { lon: 0.0, lat: 0.0 }, Node { lon: 0.01, lat: 0.02 }, Node { lon: 0.02, lat: 0.0 }];
+        let one_way = graph(nodes, &[(0, 1), (1, 2)]);
+        let polygon_test = land(vec![vec![(0.0095, -0.01), (0.0105, -0.01), (0.0105, 0.01), (0.0095, 0.01), (0.0095, -0.01)]]);
+        let mut cache = LineOfSightCache::default();
+        let (route, distance, _) = ThetaStar::new(&one_way, LineOfSight::new(&polygon_test), &mut cache, 0).find_route(2).unwrap();
+        assert_eq!(route, vec![0, 1, 2]);
+        assert_eq!(distance, one_way.edges[0][0].distance + one_way.edges[1][0].distance);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::algorithms::line_of_sight::LineOfSight;
+use crate::algorithms::nearest_neighbor::NearestNeighbor;
+use crate::model::grid_graph::Node;
+
+// number of nearest nodes which are checked for a connection over water
+const NUMBER_SNAP_CANDIDATES: usize = 32;
+
+/// Snaps coordinates to the nearest graph node which can be reached over water
+pub(crate) struct WaterSnapping<'a> {
+    nearest_neighbor: &'a NearestNeighbor,
+    line_of_sight: LineOfSight<'a>,
+}
+
+impl<'a> WaterSnapping<'a> {
+    pub fn new(nearest_neighbor: &'a NearestNeighbor, line_of_sight: LineOfSight<'a>) -> WaterSnapping<'a> {
+        WaterSnapping { nearest_neighbor, line_of_sight }
+    }
+
+    /// returns the id of the nearest node with a land free connection to the position and the distance
+    /// to this node in meters. The candidates are checked ordered by their distance, so the globally
+    /// nearest node is used if it can be reached over water.
+    pub fn snap(&self, position: &Node, nodes: &[Node]) -> Option<(u32, f64)> {
+        let candidates = self.nearest_neighbor.find_k_nearest_neighbors(position, NUMBER_SNAP_CANDIDATES);
+        for (id, distance) in candidates.iter() {
+            if self.line_of_sight.reaches_water_without_crossing_land(position, &nodes[*id as usize]) {
+                return Some((*id, *distance));
+            }
+        }
+        println!("Could not snap {:?} to one of {} nearest nodes without crossing land", position, candidates.len());
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+    use crate::model::land_polygons::LandPolygons;
+
+    #[test]
+    fn nodes_across_a_peninsula_are_skipped() {
+        // peninsula of about 7 km width between lon 10.0 and 10.1
+        let polygons = LandPolygons::from_coastline_rings(vec![vec![(10.0, 49.0), (10.1, 49.0), (10.1, 51.0), (10.0, 51.0), (10.0, 49.0)]]);
+        let polygon_test = NestedPolygonTest::new(&polygons, false);
+        let nodes = vec![Node { lon: 9.995, lat: 50.0 }, Node { lon: 10.21, lat: 50.0 }];
+        let nearest_neighbor = NearestNeighbor::new(&nodes);
+        let snapping = WaterSnapping::new(&nearest_neighbor, LineOfSight::new(&polygon_test));
+        // a harbour on land close to the east coast, the nearest node is on the west side
+        let harbour = Node { lon: 10.095, lat: 50.0 };
+        assert_eq!(nearest_neighbor.find_nearest_neighbor(&harbour), Some(0));
+        assert_eq!(snapping.snap(&harbour, &nodes).map(|(id, _)| id), Some(1));
+        // without a node on the east side the harbour cannot be snapped
+        let west_only = vec![nodes[0]];
+        let nearest_neighbor = NearestNeighbor::new(&west_only);
+        let snapping = WaterSnapping::new(&nearest_neighbor, LineOfSight::new(&polygon_test));
+        assert_eq!(snapping.snap(&harbour, &west_only), None);
+    }
+}
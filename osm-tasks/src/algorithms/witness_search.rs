@@ -1,11 +1,12 @@
 use std::collections::{BinaryHeap, HashMap};
 use crate::model::adjacency_array::AdjacencyArray;
 use crate::model::heap_item::HeapItem;
+use crate::model::weight::{Weight, INFINITE_WEIGHT};
 
 pub(crate) struct WitnessSearch<'a> {
     graph_ref: &'a AdjacencyArray,
     heap: BinaryHeap<HeapItem>,
-    distances: Vec<u32>,
+    distances: Vec<Weight>,
     previous_nodes: Vec<u32>,
     source_node: u32,
     amount_nodes_popped: u32,
@@ -18,14 +19,14 @@ impl<'a> WitnessSearch<'a> {
         //println!("New dijkstra instance with source node {}", source_node);
         let number_of_nodes = graph.get_nodes_count() as usize;
         let mut heap = BinaryHeap::with_capacity(number_of_nodes);
-        let distances = vec![u32::MAX; number_of_nodes];
+        let distances = vec![INFINITE_WEIGHT; number_of_nodes];
         let previous_nodes = vec![u32::MAX; number_of_nodes];
         heap.push(HeapItem {
             node_id: source_node,
             distance: 0,
             previous_node: source_node,
         });
-        return WitnessSearch { graph_ref: graph, removed_nodes, heap, distances, previous_nodes, source_node, amount_nodes_popped: 0 };
+        WitnessSearch { graph_ref: graph, removed_nodes, heap, distances, previous_nodes, source_node, amount_nodes_popped: 0 }
     }
 
     pub fn change_source_node(&mut self, source_node: u32) {
@@ -40,22 +41,22 @@ impl<'a> WitnessSearch<'a> {
             distance: 0,
             previous_node: source_node,
         });
-        self.distances.fill(u32::MAX);
+        self.distances.fill(INFINITE_WEIGHT);
         self.previous_nodes.fill(u32::MAX);
     }
 
-    pub fn find_route(&mut self, destination_nodes: &[u32]) -> Option<(Vec<Vec<u32>>, Vec<u32>, u32)> {
+    pub fn find_route(&mut self, destination_nodes: &[u32]) -> Option<(Vec<Vec<u32>>, Vec<Weight>, u32)> {
         let result = self.dijkstra(destination_nodes);
-        if result.1.len() > 0 {
+        if !result.1.is_empty() {
             Some((result.0, result.1, self.amount_nodes_popped))
         } else {
             None
         }
     }
 
-    fn dijkstra(&mut self, destination_nodes: &[u32]) -> (Vec<Vec<u32>>, Vec<u32>) {
+    fn dijkstra(&mut self, destination_nodes: &[u32]) -> (Vec<Vec<u32>>, Vec<Weight>) {
         let mut results: Vec<Vec<u32>> = vec![];
-        let mut distances: Vec<u32> = vec![];
+        let mut distances: Vec<Weight> = vec![];
         loop {
             if let Some(heap_element) = self.heap.pop() {
                 if heap_element.distance >= self.distances[heap_element.node_id as usize] {
@@ -64,20 +65,20 @@ impl<'a> WitnessSearch<'a> {
                 }
                 self.previous_nodes[heap_element.node_id as usize] = heap_element.previous_node;
                 self.distances[heap_element.node_id as usize] = heap_element.distance;
-                let neighbors_and_distances = self.graph_ref.get_neighbors_of_node_and_distances(heap_element.node_id);
-                for i in (0..neighbors_and_distances.len()).step_by(2) {
-                    let next_node = neighbors_and_distances[i];
-                    let next_node_distance = neighbors_and_distances[i + 1];
+                let (neighbors, edge_distances) = self.graph_ref.get_neighbors_of_node_and_distances(heap_element.node_id);
+                for i in 0..neighbors.len() {
+                    let next_node = neighbors[i];
+                    let next_node_distance = edge_distances[i];
 
-                    if self.removed_nodes.contains_key(&next_node) || next_node == u32::MAX || next_node_distance == u32::MAX {
+                    if self.removed_nodes.contains_key(&next_node) || next_node == u32::MAX || next_node_distance == INFINITE_WEIGHT {
                         continue;
                     }
 
-                    if self.distances[next_node as usize] == u32::MAX {
+                    if self.distances[next_node as usize] == INFINITE_WEIGHT {
                         //println!("add edge form {} to {} with dist {}", heap_element.node_id, next_node, next_node_distance);
                         self.heap.push(HeapItem {
                             node_id: next_node,
-                            distance: next_node_distance + heap_element.distance,
+                            distance: heap_element.distance.saturating_add(next_node_distance),
                             previous_node: heap_element.node_id,
                         });
                     }
@@ -106,6 +107,6 @@ impl<'a> WitnessSearch<'a> {
         }
         nodes.push(self.source_node);
         nodes.reverse();
-        return nodes;
+        nodes
     }
 }
@@ -0,0 +1,171 @@
+use std::fmt;
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket_contrib::json::Json;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::response::OpenApiResponder;
+use rocket_okapi::util::add_schema_response;
+use okapi::openapi3::Responses;
+use serde::{Deserialize, Serialize};
+use crate::model::route_error::RouteError;
+use crate::model::route_job::{JobState, RouteJob};
+
+/// result of the handlers, errors are returned as JSON body with a code and a message
+pub type ApiResult<T> = Result<Json<T>, ApiError>;
+
+/// machine readable codes of the error bodies
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ApiErrorCode {
+    GraphNotBuilt,
+    GraphBuildRunning,
+    JobNotFound,
+    JobNotFinished,
+    JobCancelled,
+    SnappingFailed,
+    Unreachable,
+    NoRouteFound,
+    NoFacilities,
+    BenchmarkNotRun,
+    InvalidParameter,
+    TooManyStreams,
+    // no endpoint matches the request, e.g. because a parameter is missing or has the wrong type
+    NotFound,
+    InvalidRequest,
+    Internal,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiErrorBody {
+    code: ApiErrorCode,
+    message: String,
+}
+
+impl ApiErrorBody {
+    pub fn new(code: ApiErrorCode, message: String) -> ApiErrorBody {
+        ApiErrorBody { code, message }
+    }
+}
+
+/// errors of the REST API, each error is mapped to a HTTP status code and an error code
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiError {
+    GraphNotBuilt,
+    GraphBuildRunning,
+    JobNotFound(u32),
+    // the job is pending or running, so there is no route yet
+    JobNotFinished(u32),
+    Route(RouteError),
+    NoFacilities,
+    // no benchmark has finished yet
+    BenchmarkNotRun,
+    InvalidParameter(String),
+    // the maximum number of open event streams is reached
+    TooManyStreams(usize),
+    Internal(String),
+}
+
+impl ApiError {
+    /// the error for the result of a job which has no route
+    pub fn for_job_without_route(job: &RouteJob) -> ApiError {
+        match job.state {
+            JobState::Pending | JobState::Running => ApiError::JobNotFinished(job.id),
+            JobState::Cancelled => ApiError::Route(RouteError::Cancelled),
+            JobState::Failed => ApiError::Route(job.error.unwrap_or(RouteError::NoRouteFound)),
+            JobState::Finished => ApiError::Internal(format!("route of job {} is missing", job.id)),
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::GraphNotBuilt | ApiError::Route(RouteError::NoGraph) | ApiError::TooManyStreams(_) => Status::ServiceUnavailable,
+            ApiError::GraphBuildRunning | ApiError::JobNotFinished(_) | ApiError::Route(RouteError::Cancelled) => Status::Conflict,
+            ApiError::JobNotFound(_) | ApiError::NoFacilities | ApiError::BenchmarkNotRun => Status::NotFound,
+            ApiError::Internal(_) | ApiError::Route(RouteError::Internal) => Status::InternalServerError,
+            ApiError::Route(_) => Status::UnprocessableEntity,
+            ApiError::InvalidParameter(_) => Status::BadRequest,
+        }
+    }
+
+    pub fn code(&self) -> ApiErrorCode {
+        match self {
+            ApiError::GraphNotBuilt => ApiErrorCode::GraphNotBuilt,
+            ApiError::GraphBuildRunning => ApiErrorCode::GraphBuildRunning,
+            ApiError::JobNotFound(_) => ApiErrorCode::JobNotFound,
+            ApiError::JobNotFinished(_) => ApiErrorCode::JobNotFinished,
+            ApiError::Route(RouteError::NoGraph) => ApiErrorCode::GraphNotBuilt,
+            ApiError::Route(RouteError::SnappingFailed) => ApiErrorCode::SnappingFailed,
+            ApiError::Route(RouteError::Unreachable) => ApiErrorCode::Unreachable,
+            ApiError::Route(RouteError::NoRouteFound) => ApiErrorCode::NoRouteFound,
+            ApiError::Route(RouteError::Cancelled) => ApiErrorCode::JobCancelled,
+            ApiError::Route(RouteError::Internal) => ApiErrorCode::Internal,
+            ApiError::NoFacilities => ApiErrorCode::NoFacilities,
+            ApiError::BenchmarkNotRun => ApiErrorCode::BenchmarkNotRun,
+            ApiError::InvalidParameter(_) => ApiErrorCode::InvalidParameter,
+            ApiError::TooManyStreams(_) => ApiErrorCode::TooManyStreams,
+            ApiError::Internal(_) => ApiErrorCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiError::GraphNotBuilt => write!(f, "graph has not been built"),
+            ApiError::GraphBuildRunning => write!(f, "another graph build is running"),
+            ApiError::JobNotFound(id) => write!(f, "job {} does not exist", id),
+            ApiError::JobNotFinished(id) => write!(f, "job {} is not finished yet", id),
+            ApiError::Route(error) => write!(f, "{}", error),
+            ApiError::NoFacilities => write!(f, "no facilities are registered"),
+            ApiError::BenchmarkNotRun => write!(f, "no benchmark has finished yet"),
+            ApiError::InvalidParameter(message) => write!(f, "invalid parameter: {}", message),
+            ApiError::TooManyStreams(max) => write!(f, "at most {} event streams can be open at the same time", max),
+            ApiError::Internal(message) => write!(f, "internal error: {}", message),
+        }
+    }
+}
+
+impl From<RouteError> for ApiError {
+    fn from(error: RouteError) -> Self {
+        ApiError::Route(error)
+    }
+}
+
+impl<'r> Responder<'r> for ApiError {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let body = ApiErrorBody::new(self.code(), self.to_string());
+        Response::build_from(Json(body).respond_to(request)?)
+            .status(self.status())
+            .ok()
+    }
+}
+
+impl OpenApiResponder<'_> for ApiError {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = Responses::default();
+        let schema = gen.json_schema::<ApiErrorBody>();
+        // all status codes an ApiError can be mapped to
+        for status in &[400, 404, 409, 422, 500, 503] {
+            add_schema_response(&mut responses, *status, "application/json", schema.clone())?;
+        }
+        Ok(responses)
+    }
+}
+
+/// checks that the position is a valid WGS84 coordinate
+pub fn check_position(name: &str, lat: f64, lon: f64) -> Result<(), ApiError> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(ApiError::InvalidParameter(format!("{} ({}, {}) is not a valid coordinate", name, lat, lon)));
+    }
+    Ok(())
+}
+
+/// checks that the number of requested results k is between 1 and max
+pub fn check_k(k: usize, max: usize) -> Result<(), ApiError> {
+    if k == 0 || k > max {
+        return Err(ApiError::InvalidParameter(format!("k {} must be between 1 and {}", k, max)));
+    }
+    Ok(())
+}
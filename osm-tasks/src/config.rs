@@ -1,6 +1,11 @@
 use clap::Clap;
 use once_cell::sync::OnceCell;
 use std::path::Path;
+use crate::import::coastline_merger::MergeStrictness;
+use crate::import::polygon_source::PolygonFormat;
+use crate::model::region::BoundingBox;
+use crate::model::grid_graph::GridType;
+use crate::model::weight::WeightUnit;
 
 static INSTANCE: OnceCell<Config> = OnceCell::new();
 
@@ -13,7 +18,27 @@ pub struct Config {
     #[clap(short, long)]
     geojson_export_path: Option<String>,
 
-    /// Coastlines file used to generate the polygons
+    /// Path to save the exported KML file with the generated polygons, if the polygons should be exported. If no file is specified, the file is not generated.
+    #[clap(long)]
+    kml_export_path: Option<String>,
+
+    /// Validates the graph at startup and saves the report (asymmetric, duplicate and zero length edges, self loops, nodes on land, edge length and degree statistics, connected components) as JSON to this path.
+    #[clap(long)]
+    validation_report_path: Option<String>,
+
+    /// Validates the graph at startup and saves the offending edges and nodes as geoJSON to this path.
+    #[clap(long)]
+    validation_geojson_path: Option<String>,
+
+    /// Tolerance in meters for the simplification of the polygons in the geoJSON and KML exports. 0 exports the polygons unchanged.
+    #[clap(long, default_value = "0")]
+    export_simplify_tolerance: f64,
+
+    /// Tolerance in meters for the simplification of the polygons used by the point in polygon test during the graph generation. Speeds up the graph generation, 0 uses the polygons unchanged.
+    #[clap(long, default_value = "0")]
+    simplify_tolerance: f64,
+
+    /// Coastlines file used to generate the polygons. Either an OSM PBF file with coastline ways or a file with land polygons (GeoJSON, ESRI shapefile or WKT).
     #[clap()]
     coastlines_file: String,
 
@@ -25,9 +50,61 @@ pub struct Config {
     #[clap(short = 'n', long = "nodes", default_value = "1000000")]
     number_of_nodes: u32,
 
+    /// Generate a regional graph for this bounding box (lon_min,lat_min,lon_max,lat_max) instead of a graph for the whole sphere. The number of nodes is then defined by --grid-spacing. Bounding boxes crossing the antimeridian are not supported.
+    #[clap(long)]
+    region_bbox: Option<BoundingBox>,
+
+    /// GeoJSON or WKT file with a polygon. Generates a regional graph for the area of the polygon instead of a graph for the whole sphere.
+    #[clap(long)]
+    region_polygon: Option<String>,
+
+    /// Tessellation of the sphere used to place the nodes: sphere (rows of equal latitude), icosahedral (vertices of a subdivided icosahedron, six neighbors per node) or hexagonal (hexagonal cells of a subdivided icosahedron). Ignored for regional graphs.
+    #[clap(long, default_value = "sphere")]
+    grid_type: GridType,
+
+    /// Unit of the edge weights: m, dm, cm or mm. Weights are stored as 64 bit integers in this unit, smaller units reduce the rounding error of long routes.
+    #[clap(long, default_value = "cm")]
+    weight_unit: WeightUnit,
+
+    /// Speed of the ships in knots, used to estimate the duration of routes.
+    #[clap(long, default_value = "12")]
+    ship_speed: f64,
+
+    /// Distance between the nodes of regional graphs in km.
+    #[clap(long, default_value = "10")]
+    grid_spacing: f64,
+
+    /// OSM change file (.osc) or new coastlines file. Updates the polygons and the graph of the coastlines file at startup, only grid points in areas with changed polygons are reclassified.
+    #[clap(long)]
+    update_file: Option<String>,
+
     /// Build graph on startup. Sets weather the graph generation should be triggered at startup. Generation trough REST API will be available anyway.
     #[clap(short = 'b', long = "build-graph")]
     build_graph_on_startup: bool,
+
+    /// Connected components of the graph with less nodes than this (e.g. lakes or isolated grid points) are removed from the graph.
+    #[clap(long, default_value = "2")]
+    min_component_size: usize,
+
+    /// Defines how issues found while merging the coastline ways are handled: ignore (only print a summary), warn (print all issues) or strict (fail if the coastlines could not be merged to closed rings).
+    #[clap(long, default_value = "warn")]
+    merge_strictness: MergeStrictness,
+
+    /// Gaps between coastline ways up to this length in meters are closed automatically.
+    #[clap(long, default_value = "0")]
+    gap_tolerance: f64,
+
+    /// Format of the coastlines file: pbf, geojson, shapefile or wkt. If not set, the format is chosen by the file extension.
+    #[clap(long)]
+    polygon_format: Option<PolygonFormat>,
+
+    /// Treat inland waters (lakes, inland seas) as water, so that the graph contains nodes on them. By default they are treated as land.
+    #[clap(long)]
+    include_inland_water: bool,
+
+    /// CSV (name,lat,lon[,kind]) or GeoJSON file with ports or bunkering stations which can be queried by their distance by sea. If no file is specified, the facility search is not available.
+    #[clap(long)]
+    facilities_file: Option<String>,
 }
 
 impl Config {
@@ -44,9 +121,28 @@ impl Config {
         if !Path::new(config.coastlines_file()).is_file() {
             panic!("Could not open coastlines file: {}", config.coastlines_file());
         }
+        if let Some(region_polygon) = config.region_polygon() {
+            if !Path::new(region_polygon).is_file() {
+                panic!("Could not open region polygon file: {}", region_polygon);
+            }
+        }
+        if let Some(update_file) = config.update_file() {
+            if !Path::new(update_file).is_file() {
+                panic!("Could not open update file: {}", update_file);
+            }
+        }
+        if config.grid_spacing() <= 0.0 {
+            panic!("Grid spacing has to be greater than 0");
+        }
         INSTANCE.set(config).unwrap();
     }
 
+    /// default configuration for the unit tests, the coastlines file is not read
+    #[cfg(test)]
+    pub fn init_for_tests() {
+        INSTANCE.get_or_init(|| Config::parse_from(["osm-tasks", "coastlines.osm.pbf"]));
+    }
+
     pub fn coastlines_file(&self) -> &str {
         &self.coastlines_file
     }
@@ -54,7 +150,7 @@ impl Config {
         self.force_rebuild_graph
     }
     pub fn number_of_nodes(&self) -> usize {
-        return 1000000;
+        self.number_of_nodes as usize
     }
     pub fn build_graph_on_startup(&self) -> bool {
         self.build_graph_on_startup
@@ -62,4 +158,58 @@ impl Config {
     pub fn geojson_export_path(&self) -> &Option<String> {
         &self.geojson_export_path
     }
+    pub fn kml_export_path(&self) -> &Option<String> {
+        &self.kml_export_path
+    }
+    pub fn validation_report_path(&self) -> &Option<String> {
+        &self.validation_report_path
+    }
+    pub fn validation_geojson_path(&self) -> &Option<String> {
+        &self.validation_geojson_path
+    }
+    pub fn export_simplify_tolerance(&self) -> f64 {
+        self.export_simplify_tolerance
+    }
+    pub fn simplify_tolerance(&self) -> f64 {
+        self.simplify_tolerance
+    }
+    pub fn region_bbox(&self) -> Option<BoundingBox> {
+        self.region_bbox
+    }
+    pub fn region_polygon(&self) -> &Option<String> {
+        &self.region_polygon
+    }
+    pub fn grid_type(&self) -> GridType {
+        self.grid_type
+    }
+    pub fn weight_unit(&self) -> WeightUnit {
+        self.weight_unit
+    }
+    pub fn grid_spacing(&self) -> f64 {
+        self.grid_spacing
+    }
+    pub fn ship_speed(&self) -> f64 {
+        self.ship_speed
+    }
+    pub fn update_file(&self) -> &Option<String> {
+        &self.update_file
+    }
+    pub fn min_component_size(&self) -> usize {
+        self.min_component_size
+    }
+    pub fn merge_strictness(&self) -> MergeStrictness {
+        self.merge_strictness
+    }
+    pub fn gap_tolerance(&self) -> f64 {
+        self.gap_tolerance
+    }
+    pub fn polygon_format(&self) -> Option<PolygonFormat> {
+        self.polygon_format
+    }
+    pub fn include_inland_water(&self) -> bool {
+        self.include_inland_water
+    }
+    pub fn facilities_file(&self) -> &Option<String> {
+        &self.facilities_file
+    }
 }
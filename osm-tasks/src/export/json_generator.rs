@@ -6,41 +6,43 @@ generates a file following the GeoJSON format https://datatracker.ietf.org/doc/h
  */
 pub struct JsonFile {
     file_name: String,
-    polygons: Vec<Vec<(f64, f64)>>,
+    // rings of each polygon, the first ring is the outer ring
+    polygons: Vec<Vec<Vec<(f64, f64)>>>,
     // polygon in geojson
     points: Vec<(f64, f64)>, // multipoint in geojson
 }
 
 impl JsonFile {
-    pub fn to_string(&self) -> String {
+    pub fn to_geojson(&self) -> String {
         let mut result = String::new() + "  { \"type\": \"FeatureCollection\",
             \"features\": [";
 
         // polygons
         for polygon in &self.polygons {
-            if polygon.len() > 1 {
+            if polygon[0].len() > 1 {
                 // ensure first equals last node
-                let mut temp = polygon.to_vec();
-                if polygon.first().unwrap() != polygon.last().unwrap() {
-                    temp.push(*polygon.first().unwrap());
-                }
+                let rings: Vec<Vec<(f64, f64)>> = polygon.iter().filter(|ring| ring.len() > 1).map(|ring| {
+                    let mut temp = ring.to_vec();
+                    if ring.first().unwrap() != ring.last().unwrap() {
+                        temp.push(*ring.first().unwrap());
+                    }
+                    temp
+                }).collect();
 
-                let mut coords_string = format!("{:?}", temp).replace("(", "[").replace(")", "]");
+                let mut coords_string = format!("{:?}", rings).replace("(", "[").replace(")", "]");
                 coords_string = format!("{{
               \"type\": \"Feature\",
               \"properties\": {{}},
               \"geometry\": {{
                 \"type\": \"Polygon\",
-                \"coordinates\": [
-                    {}
-                ]
+                \"coordinates\": {}
               }}
              }},", coords_string);
-                result.push_str(&*coords_string);
+                result.push_str(&coords_string);
             }
         }
         // mutli points
-        if self.points.len() > 0 {
+        if !self.points.is_empty() {
             let mut coords_string = format!("{:?}", self.points).replace("(", "[").replace(")", "]");
            // println!("{}", coords_string);
             coords_string = format!("{{
@@ -52,11 +54,11 @@ impl JsonFile {
                     {}
               }}
              }},", coords_string);
-            result.push_str(&*coords_string);
+            result.push_str(&coords_string);
         }
         // remove last comma
         result.pop();
-        result = result + "]}";
+        result += "]}";
         result
     }
 }
@@ -74,12 +76,18 @@ impl JsonBuilder {
     }
 
     pub fn add_polygon(&mut self, polygon: Vec<(f64, f64)>) -> &mut JsonBuilder {
-        self.json.polygons.push(polygon);
+        self.json.polygons.push(vec![polygon]);
         self
     }
 
     pub fn add_polygons(&mut self, polygons: Vec<Vec<(f64, f64)>>) -> &mut JsonBuilder {
-        self.json.polygons.extend(polygons);
+        self.json.polygons.extend(polygons.into_iter().map(|polygon| vec![polygon]));
+        self
+    }
+
+    /// adds polygons given by their outer ring followed by their holes
+    pub fn add_polygons_with_holes(&mut self, polygons: Vec<Vec<Vec<(f64, f64)>>>) -> &mut JsonBuilder {
+        self.json.polygons.extend(polygons.into_iter().filter(|rings| !rings.is_empty()));
         self
     }
 
@@ -90,7 +98,7 @@ impl JsonBuilder {
 
     pub fn build(&mut self) -> File {
         let mut file = File::create(&self.json.file_name).expect("could not open file");
-        file.write_all(self.json.to_string().as_ref()).expect("could not write to file");
+        file.write_all(self.json.to_geojson().as_ref()).expect("could not write to file");
         file
     }
 }
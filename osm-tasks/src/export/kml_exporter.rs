@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::fs::File;
+use std::io::Write;
 
 use kml::{Kml, KmlWriter, types::{ Coord, LineString, Point, Polygon}};
 use kml::types::{Geometry, LinearRing, Placemark};
@@ -10,9 +11,9 @@ pub struct KmlExport {
 
 impl KmlExport {
     pub fn init() -> KmlExport {
-        return KmlExport {
+        KmlExport {
             elements: vec![]
-        };
+        }
     }
 
     pub fn add_rect_with_size_one(&mut self, lon: i16, lat: i16, name: Option<String>) {
@@ -20,14 +21,14 @@ impl KmlExport {
     }
 
     fn as_placemarker(name: Option<String>, geometry: Geometry) -> Kml {
-        return Kml::Placemark(Placemark {
+        Kml::Placemark(Placemark {
             name,
             description: None,
             geometry: Some(geometry),
             attrs: HashMap::new(),
             children: vec![],
         }
-        );
+        )
     }
 
     pub fn convert_coords(points: Vec<(f64, f64)>) -> Vec<Coord> {
@@ -39,6 +40,12 @@ impl KmlExport {
         self.elements.push(KmlExport::as_placemarker(name, Geometry::Polygon(Polygon::new(LinearRing::from(points), vec![]))));
     }
 
+    pub fn add_polygon_with_holes(&mut self, outer: Vec<(f64, f64)>, holes: Vec<Vec<(f64, f64)>>, name: Option<String>) {
+        let outer = LinearRing::from(KmlExport::convert_coords(outer));
+        let holes = holes.into_iter().map(|hole| LinearRing::from(KmlExport::convert_coords(hole))).collect();
+        self.elements.push(KmlExport::as_placemarker(name, Geometry::Polygon(Polygon::new(outer, holes))));
+    }
+
     pub fn add_point(&mut self, point: (f64, f64), name: Option<String>) {
         self.elements.push(KmlExport::as_placemarker(name, Geometry::Point(Point::new(point.0, point.1, None))));
     }
@@ -48,15 +55,37 @@ impl KmlExport {
         self.elements.push(KmlExport::as_placemarker(name, Geometry::LineString(LineString::from(points))));
     }
 
+    pub fn add_linestring_with_description(&mut self, line: Vec<(f64, f64)>, name: Option<String>, description: String) {
+        let points: Vec<Coord> = KmlExport::convert_coords(line);
+        let mut placemark = KmlExport::as_placemarker(name, Geometry::LineString(LineString::from(points)));
+        if let Kml::Placemark(placemark) = &mut placemark {
+            placemark.description = Some(description);
+        }
+        self.elements.push(placemark);
+    }
+
     pub fn write_file(&self, path: String) {
         let mut file = File::create(path).unwrap();
-        let mut writer = KmlWriter::<_, f64>::from_writer(&mut file);
+        if let Err(e) = self.write(&mut file) {
+            println!("Could not write kml file: {:?}", e)
+        }
+    }
+
+    fn write<W: Write>(&self, output: &mut W) -> Result<(), kml::Error> {
+        let mut writer = KmlWriter::<_, f64>::from_writer(output);
         let kml = Kml::Document {
             attrs: HashMap::new(),
             elements: self.elements.clone(),
         };
-        if let Err(e) = writer.write(&kml) {
-            println!("Could not write kml file: {:?}", e)
+        writer.write(&kml)
+    }
+
+    /// returns the document as KML string, e.g. to send it as response
+    pub fn to_kml(&self) -> String {
+        let mut buffer: Vec<u8> = Vec::new();
+        if let Err(e) = self.write(&mut buffer) {
+            println!("Could not write kml: {:?}", e)
         }
+        String::from_utf8(buffer).expect("kml is not valid UTF-8")
     }
 }
\ No newline at end of file
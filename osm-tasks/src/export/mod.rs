@@ -1,2 +1,6 @@
 pub(crate) mod json_generator;
 pub(crate) mod kml_exporter;
+pub(crate) mod validation_export;
+pub(crate) mod route_geojson;
+pub(crate) mod route_export;
+pub(crate) mod rtz_exporter;
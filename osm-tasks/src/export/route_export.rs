@@ -0,0 +1,95 @@
+use std::fmt::Write;
+use crate::export::kml_exporter::KmlExport;
+use crate::model::ship_route::ShipRoute;
+
+/// name of the waypoint at the index, the first and last waypoint are named start and destination
+pub fn waypoint_name(index: usize, number_waypoints: usize) -> String {
+    if index == 0 {
+        return String::from("Start");
+    }
+    if index + 1 == number_waypoints {
+        return String::from("Destination");
+    }
+    format!("WP{:03}", index)
+}
+
+/// short description of the route with distance in nautical miles and number of waypoints, simplified
+/// routes also state the number of nodes and the distance change of the simplification
+pub fn route_description(route: &ShipRoute) -> String {
+    let description = format!("{:.1} nm, {} waypoints", route.distance_nm(), route.nodes().len());
+    match route.simplification() {
+        Some(report) => format!("{} (simplified from {} nodes, {:+.1} m)", description, report.waypoints_before, report.distance_change),
+        None => description,
+    }
+}
+
+/// Converts the route to a GPX 1.1 document with one rte, each node of the route is a named rtept.
+pub fn route_to_gpx(route: &ShipRoute, name: &str) -> String {
+    let mut gpx = String::new();
+    gpx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    gpx.push_str("<gpx version=\"1.1\" creator=\"osm-tasks\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n");
+    gpx.push_str("  <rte>\n");
+    writeln!(gpx, "    <name>{}</name>", escape_xml(name)).unwrap();
+    writeln!(gpx, "    <desc>{}</desc>", route_description(route)).unwrap();
+    let number_waypoints = route.nodes().len();
+    for (i, node) in route.nodes().iter().enumerate() {
+        writeln!(gpx, "    <rtept lat=\"{:.6}\" lon=\"{:.6}\"><name>{}</name></rtept>", node.lat, node.lon, waypoint_name(i, number_waypoints)).unwrap();
+    }
+    gpx.push_str("  </rte>\n");
+    gpx.push_str("</gpx>\n");
+    gpx
+}
+
+/// Converts the route to a KML document with the route as LineString and a named point for each waypoint.
+pub fn route_to_kml(route: &ShipRoute, name: &str) -> String {
+    let mut kml = KmlExport::init();
+    let line: Vec<(f64, f64)> = route.nodes().iter().map(|node| (node.lon, node.lat)).collect();
+    kml.add_linestring_with_description(line.clone(), Some(name.to_string()), route_description(route));
+    let number_waypoints = line.len();
+    for (i, point) in line.into_iter().enumerate() {
+        kml.add_point(point, Some(waypoint_name(i, number_waypoints)));
+    }
+    kml.to_kml()
+}
+
+pub fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::grid_graph::Node;
+    use crate::model::ship_route::{SnapInfo, RoutingAlgorithm};
+
+    fn route(positions: &[(f64, f64)]) -> ShipRoute {
+        let nodes: Vec<Node> = positions.iter().map(|(lon, lat)| Node { lon: *lon, lat: *lat }).collect();
+        let start = SnapInfo::new(nodes[0], nodes[0], 0.0);
+        let end = SnapInfo::new(nodes[nodes.len() - 1], nodes[nodes.len() - 1], 0.0);
+        ShipRoute::with_distance(nodes, 1852.0, start, end, RoutingAlgorithm::Dijkstra)
+    }
+
+    #[test]
+    fn gpx_contains_a_named_route_point_per_node() {
+        let gpx = route_to_gpx(&route(&[(10.0, 54.0), (10.5, 54.25), (11.0, 54.5)]), "Kiel & back");
+        assert!(gpx.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\""));
+        assert_eq!(gpx.matches("<rte>").count(), 1);
+        assert!(gpx.contains("<name>Kiel &amp; back</name>"));
+        assert!(gpx.contains("<desc>1.0 nm, 3 waypoints</desc>"));
+        let points: Vec<&str> = gpx.lines().filter(|line| line.trim_start().starts_with("<rtept")).collect();
+        assert_eq!(points, vec![
+            "    <rtept lat=\"54.000000\" lon=\"10.000000\"><name>Start</name></rtept>",
+            "    <rtept lat=\"54.250000\" lon=\"10.500000\"><name>WP001</name></rtept>",
+            "    <rtept lat=\"54.500000\" lon=\"11.000000\"><name>Destination</name></rtept>",
+        ]);
+        assert!(gpx.ends_with("  </rte>\n</gpx>\n"));
+    }
+
+    #[test]
+    fn kml_contains_the_line_and_a_point_per_node() {
+        let kml = route_to_kml(&route(&[(10.0, 54.0), (10.5, 54.25), (11.0, 54.5)]), "route");
+        assert_eq!(kml.matches("<LineString>").count(), 1);
+        assert_eq!(kml.matches("<Point>").count(), 3);
+        assert!(kml.contains("Start") && kml.contains("WP001") && kml.contains("Destination"));
+    }
+}
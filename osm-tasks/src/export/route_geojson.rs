@@ -0,0 +1,145 @@
+use serde_json::{json, Value};
+use crate::model::grid_graph::Node;
+use crate::model::ship_route::ShipRoute;
+
+/// Converts the route to a GeoJSON Feature (RFC 7946) with the distance in meters and nautical miles, the
+/// estimated duration in seconds at the given speed, the snapping of start and destination, the
+/// algorithm and the report of the simplification as properties. Routes crossing the antimeridian are split into a MultiLineString,
+/// routes with a single distinct position (start and destination snapped to the same node) are a Point.
+pub fn route_to_geojson(route: &ShipRoute, speed_knots: f64) -> Value {
+    let parts = split_at_antimeridian(route.nodes());
+    let geometry = match parts.as_slice() {
+        [part] if part.len() < 2 => part.first().map_or(Value::Null, |position| json!({ "type": "Point", "coordinates": position })),
+        [part] => json!({ "type": "LineString", "coordinates": part }),
+        _ => json!({ "type": "MultiLineString", "coordinates": parts }),
+    };
+    json!({
+        "type": "Feature",
+        "geometry": geometry,
+        "properties": {
+            "distance": route.distance(),
+            "distanceNm": route.distance_nm(),
+            "duration": estimated_duration(route.distance_nm(), speed_knots),
+            "speed": speed_knots,
+            "startSnap": route.start_snap(),
+            "endSnap": route.end_snap(),
+            "algorithm": route.algorithm(),
+            "simplification": route.simplification(),
+        }
+    })
+}
+
+/// estimated duration in seconds to travel the distance at the speed in knots, None for speeds <= 0
+pub fn estimated_duration(distance_nm: f64, speed_knots: f64) -> Option<f64> {
+    if speed_knots > 0.0 {
+        return Some(distance_nm / speed_knots * 3600.0);
+    }
+    None
+}
+
+/// Splits the route into line strings of [lon, lat] positions which do not cross the antimeridian. Each
+/// crossing ends a line string at +-180 and starts the next one at the opposite side, the latitude of the
+/// crossing is the latitude of the great circle through both nodes. Routes with less than two distinct
+/// positions result in a single part with at most one position.
+pub fn split_at_antimeridian(nodes: &[Node]) -> Vec<Vec<[f64; 2]>> {
+    let mut parts: Vec<Vec<[f64; 2]>> = vec![vec![]];
+    for (i, node) in nodes.iter().enumerate() {
+        if i > 0 {
+            let previous = &nodes[i - 1];
+            if (node.lon - previous.lon).abs() > 180.0 {
+                let lat = crossing_latitude(previous, node);
+                let previous_side = if previous.lon > 0.0 { 180.0 } else { -180.0 };
+                push_position(parts.last_mut().unwrap(), [previous_side, lat]);
+                parts.push(vec![[-previous_side, lat]]);
+            }
+        }
+        push_position(parts.last_mut().unwrap(), [node.lon, node.lat]);
+    }
+    // parts which only consist of a crossing point, e.g. of a route starting at +-180
+    if parts.len() > 1 {
+        parts.retain(|part| part.len() > 1);
+        if parts.is_empty() {
+            // the route only consists of crossing points, e.g. of nodes at 180 and -180
+            return vec![vec![[nodes[0].lon, nodes[0].lat]]];
+        }
+    }
+    parts
+}
+
+// skips duplicated positions, e.g. of nodes lying exactly on the antimeridian
+fn push_position(part: &mut Vec<[f64; 2]>, position: [f64; 2]) {
+    if part.last() != Some(&position) {
+        part.push(position);
+    }
+}
+
+// latitude where the great circle through both nodes crosses the antimeridian
+fn crossing_latitude(a: &Node, b: &Node) -> f64 {
+    // nodes of the sphere grid can lie exactly on the antimeridian
+    if a.lon.abs() == 180.0 {
+        return a.lat;
+    }
+    if b.lon.abs() == 180.0 {
+        return b.lat;
+    }
+    let (lat_a, lat_b) = (a.lat.to_radians(), b.lat.to_radians());
+    let (lon_a, lon_b) = (a.lon.to_radians(), b.lon.to_radians());
+    let lon = std::f64::consts::PI;
+    let denominator = lat_a.cos() * lat_b.cos() * (lon_a - lon_b).sin();
+    if denominator.abs() < 1e-12 {
+        // both nodes are on the same meridian or at a pole
+        return (a.lat + b.lat) / 2.0;
+    }
+    let numerator = lat_a.sin() * lat_b.cos() * (lon - lon_b).sin() - lat_b.sin() * lat_a.cos() * (lon - lon_a).sin();
+    (numerator / denominator).atan().to_degrees()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ship_route::{SnapInfo, RoutingAlgorithm};
+
+    fn nodes(positions: &[(f64, f64)]) -> Vec<Node> {
+        positions.iter().map(|(lon, lat)| Node { lon: *lon, lat: *lat }).collect()
+    }
+
+    #[test]
+    fn route_without_crossing_is_one_part() {
+        let parts = split_at_antimeridian(&nodes(&[(10.0, 50.0), (11.0, 51.0), (12.0, 52.0)]));
+        assert_eq!(parts, vec![vec![[10.0, 50.0], [11.0, 51.0], [12.0, 52.0]]]);
+    }
+
+    #[test]
+    fn crossing_ends_and_starts_a_part_at_the_same_latitude() {
+        let parts = split_at_antimeridian(&nodes(&[(178.0, 10.0), (179.0, 10.0), (-179.0, 10.0), (-178.0, 10.0)]));
+        assert_eq!(parts.len(), 2);
+        let (end, start) = (parts[0].last().unwrap(), parts[1][0]);
+        assert_eq!((end[0], start[0]), (180.0, -180.0));
+        assert_eq!(end[1], start[1]);
+        assert!((end[1] - 10.0).abs() < 0.01);
+        assert_eq!(parts[0][0], [178.0, 10.0]);
+        assert_eq!(*parts[1].last().unwrap(), [-178.0, 10.0]);
+    }
+
+    #[test]
+    fn nodes_on_the_antimeridian_do_not_create_empty_parts() {
+        let parts = split_at_antimeridian(&nodes(&[(179.0, 0.0), (180.0, 0.0), (-179.0, 0.0)]));
+        assert_eq!(parts, vec![vec![[179.0, 0.0], [180.0, 0.0]], vec![[-180.0, 0.0], [-179.0, 0.0]]]);
+    }
+
+    #[test]
+    fn route_of_one_position_is_a_single_position() {
+        assert_eq!(split_at_antimeridian(&nodes(&[(10.0, 50.0), (10.0, 50.0)])), vec![vec![[10.0, 50.0]]]);
+        assert_eq!(split_at_antimeridian(&nodes(&[(180.0, 0.0), (-180.0, 0.0)])), vec![vec![[180.0, 0.0]]]);
+    }
+
+    #[test]
+    fn route_of_one_position_is_a_point() {
+        let node = Node { lon: 180.0, lat: 0.0 };
+        let snap = SnapInfo::new(node, node, 0.0);
+        let route = ShipRoute::with_distance(nodes(&[(180.0, 0.0), (-180.0, 0.0)]), 0.0, snap, snap, RoutingAlgorithm::Dijkstra);
+        let geometry = &route_to_geojson(&route, 10.0)["geometry"];
+        assert_eq!(geometry["type"], "Point");
+        assert_eq!(geometry["coordinates"], json!([180.0, 0.0]));
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+use crate::export::route_export::{escape_xml, waypoint_name};
+use crate::model::grid_graph::Node;
+
+/// geometry of the legs between two waypoints
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LegGeometry {
+    // great circle, the edges of the graph are great circle segments
+    Orthodrome,
+    // rhumb line with constant course
+    Loxodrome,
+}
+
+impl FromStr for LegGeometry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "orthodrome" | "greatcircle" => Ok(LegGeometry::Orthodrome),
+            "loxodrome" | "rhumbline" => Ok(LegGeometry::Loxodrome),
+            _ => Err(format!("Unknown leg geometry {}, expected greatCircle or rhumbLine", s)),
+        }
+    }
+}
+
+impl fmt::Display for LegGeometry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LegGeometry::Orthodrome => write!(f, "Orthodrome"),
+            LegGeometry::Loxodrome => write!(f, "Loxodrome"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RtzOptions {
+    pub leg_geometry: LegGeometry,
+    // planned speed in knots of all legs, no schedule is written if it is not set
+    pub speed_knots: Option<f64>,
+}
+
+impl Default for RtzOptions {
+    fn default() -> Self {
+        RtzOptions { leg_geometry: LegGeometry::Orthodrome, speed_knots: None }
+    }
+}
+
+/// Converts the waypoints to an RTZ 1.1 (IEC 61174) route. The leg of a waypoint is the leg from the previous
+/// waypoint to this one, so the first waypoint has no leg. With a planned speed, a manual schedule with the
+/// speed of every leg is added.
+pub fn waypoints_to_rtz(waypoints: &[Node], name: &str, options: &RtzOptions) -> String {
+    let mut rtz = String::new();
+    rtz.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    rtz.push_str("<route xmlns=\"http://www.cirm.org/RTZ/1/1\" version=\"1.1\">\n");
+    writeln!(rtz, "  <routeInfo routeName=\"{}\"/>", escape_xml(name)).unwrap();
+    rtz.push_str("  <waypoints>\n");
+    for (i, node) in waypoints.iter().enumerate() {
+        writeln!(rtz, "    <waypoint id=\"{}\" name=\"{}\">", i + 1, waypoint_name(i, waypoints.len())).unwrap();
+        writeln!(rtz, "      <position lat=\"{:.6}\" lon=\"{:.6}\"/>", node.lat, node.lon).unwrap();
+        if i > 0 {
+            writeln!(rtz, "      <leg geometryType=\"{}\"/>", options.leg_geometry).unwrap();
+        }
+        rtz.push_str("    </waypoint>\n");
+    }
+    rtz.push_str("  </waypoints>\n");
+    if let Some(speed) = options.speed_knots {
+        rtz.push_str("  <schedules>\n");
+        rtz.push_str("    <schedule id=\"1\" name=\"Planned speed\">\n");
+        rtz.push_str("      <manual>\n");
+        for i in 1..waypoints.len() {
+            writeln!(rtz, "        <scheduleElement waypointId=\"{}\" speed=\"{:.1}\"/>", i + 1, speed).unwrap();
+        }
+        rtz.push_str("      </manual>\n");
+        rtz.push_str("    </schedule>\n");
+        rtz.push_str("  </schedules>\n");
+    }
+    rtz.push_str("</route>\n");
+    rtz
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn waypoints() -> Vec<Node> {
+        vec![Node { lon: 10.0, lat: 54.0 }, Node { lon: 10.5, lat: 54.25 }, Node { lon: 11.0, lat: 54.5 }]
+    }
+
+    #[test]
+    fn every_waypoint_but_the_first_has_a_leg() {
+        let rtz = waypoints_to_rtz(&waypoints(), "Kiel <> Fehmarn", &RtzOptions::default());
+        assert!(rtz.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<route xmlns=\"http://www.cirm.org/RTZ/1/1\" version=\"1.1\">\n"));
+        assert!(rtz.contains("<routeInfo routeName=\"Kiel &lt;&gt; Fehmarn\"/>"));
+        assert_eq!(rtz.matches("<waypoint ").count(), 3);
+        assert_eq!(rtz.matches("<leg geometryType=\"Orthodrome\"/>").count(), 2);
+        assert!(rtz.contains("<waypoint id=\"1\" name=\"Start\">\n      <position lat=\"54.000000\" lon=\"10.000000\"/>\n    </waypoint>"));
+        assert!(rtz.contains("<waypoint id=\"3\" name=\"Destination\">"));
+        assert!(!rtz.contains("<schedules>"));
+        assert!(rtz.ends_with("  </waypoints>\n</route>\n"));
+    }
+
+    #[test]
+    fn speed_adds_a_schedule_element_per_leg() {
+        let options = RtzOptions { leg_geometry: LegGeometry::Loxodrome, speed_knots: Some(12.0) };
+        let rtz = waypoints_to_rtz(&waypoints(), "route", &options);
+        assert_eq!(rtz.matches("<leg geometryType=\"Loxodrome\"/>").count(), 2);
+        assert_eq!(rtz.matches("<scheduleElement ").count(), 2);
+        assert!(rtz.contains("<scheduleElement waypointId=\"2\" speed=\"12.0\"/>"));
+        assert!(rtz.contains("<scheduleElement waypointId=\"3\" speed=\"12.0\"/>"));
+    }
+}
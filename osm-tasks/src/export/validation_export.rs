@@ -0,0 +1,32 @@
+use std::path::Path;
+use crate::algorithms::graph_validator::{validate_graph, expected_spacing};
+use crate::config::Config;
+use crate::import::pbf_reader::{read_or_create_polygons, create_polygon_test, read_or_create_graph, write_to_file};
+
+/// Validates the graph of the coastlines file (the graph is generated if it does not exist yet) and
+/// writes the report as JSON and the offending edges and nodes as GeoJSON.
+pub fn validate_and_export(osm_path: &str, number_nodes: usize, report_path: Option<&str>, geojson_path: Option<&str>) {
+    let config = Config::global();
+    let polygons = match read_or_create_polygons(osm_path, config.force_rebuild_graph()) {
+        Ok(polygons) => polygons,
+        Err(e) => {
+            println!("Could not validate graph: {}", e);
+            return;
+        }
+    };
+    let polygon_test = create_polygon_test(&polygons);
+    let graph = read_or_create_graph(osm_path, config.force_rebuild_graph(), number_nodes, &polygon_test);
+    let report = validate_graph(&graph, Some(&polygon_test), expected_spacing(graph.grid_type, number_nodes, config.grid_spacing()));
+    println!("Validated graph of {}: {}", Path::new(osm_path).display(), report);
+    if !report.is_valid() {
+        println!("Graph is not valid, see the report for the offending edges and nodes");
+    }
+    if let Some(report_path) = report_path {
+        write_to_file(report_path.to_string(), serde_json::to_string_pretty(&report).expect("could not serialize validation report"));
+        println!("Saved validation report to {}", report_path);
+    }
+    if let Some(geojson_path) = geojson_path {
+        write_to_file(geojson_path.to_string(), report.offending_elements_geojson(&graph).to_string());
+        println!("Saved offending elements to {}", geojson_path);
+    }
+}
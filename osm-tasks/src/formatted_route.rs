@@ -0,0 +1,109 @@
+use std::str::FromStr;
+use rocket::http::{ContentType, Header};
+use rocket::request::Request;
+use rocket::response::{self, Responder, Response};
+use rocket::response::content::Content;
+use rocket_contrib::json::Json;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::response::OpenApiResponder;
+use rocket_okapi::util::add_schema_response;
+use okapi::openapi3::Responses;
+use serde_json::Value;
+use crate::config::Config;
+use crate::export::route_geojson::route_to_geojson;
+use crate::export::route_export::{route_to_gpx, route_to_kml};
+use crate::export::rtz_exporter::{waypoints_to_rtz, RtzOptions};
+use crate::model::ship_route::ShipRoute;
+
+/// formats of the route returned by /jobResult, gpx, kml and rtz are downloaded as file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RouteFormat {
+    Json,
+    GeoJson,
+    Gpx,
+    Kml,
+    Rtz,
+}
+
+impl RouteFormat {
+    // content type and file extension of the formats which are downloaded as file
+    fn download_type(&self) -> Option<(ContentType, &'static str)> {
+        match self {
+            RouteFormat::Json | RouteFormat::GeoJson => None,
+            RouteFormat::Gpx => Some((ContentType::new("application", "gpx+xml"), "gpx")),
+            RouteFormat::Kml => Some((ContentType::new("application", "vnd.google-earth.kml+xml"), "kml")),
+            // there is no registered media type for RTZ, bridge equipment identifies the files by their extension
+            RouteFormat::Rtz => Some((ContentType::XML, "rtz")),
+        }
+    }
+
+    /// the route is simplified by default for the downloaded formats, navigation equipment expects waypoints and not every node of the graph
+    pub fn is_simplified_by_default(&self) -> bool {
+        self.download_type().is_some()
+    }
+}
+
+impl FromStr for RouteFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(RouteFormat::Json),
+            "geojson" => Ok(RouteFormat::GeoJson),
+            "gpx" => Ok(RouteFormat::Gpx),
+            "kml" => Ok(RouteFormat::Kml),
+            "rtz" => Ok(RouteFormat::Rtz),
+            _ => Err(format!("Unknown route format {}, expected json, geojson, gpx, kml or rtz", s)),
+        }
+    }
+}
+
+/// the route in the requested format
+pub enum FormattedRoute {
+    Json(ShipRoute),
+    // RFC 7946 Feature, sent as application/geo+json
+    GeoJson(Value),
+    // file which is sent as attachment, so that browsers download it
+    Download { content_type: ContentType, file_name: String, body: String },
+}
+
+impl FormattedRoute {
+    /// formats the route of the job, downloaded files are named route-<job id>.<extension>
+    pub fn new(job_id: u32, route: ShipRoute, format: RouteFormat, rtz_options: &RtzOptions) -> FormattedRoute {
+        let name = format!("route-{}", job_id);
+        let body = match format {
+            RouteFormat::Json => return FormattedRoute::Json(route),
+            RouteFormat::GeoJson => return FormattedRoute::GeoJson(route_to_geojson(&route, Config::global().ship_speed())),
+            RouteFormat::Gpx => route_to_gpx(&route, &name),
+            RouteFormat::Kml => route_to_kml(&route, &name),
+            RouteFormat::Rtz => waypoints_to_rtz(route.nodes(), &name, rtz_options),
+        };
+        let (content_type, extension) = format.download_type().expect("format is downloaded as file");
+        FormattedRoute::Download { content_type, file_name: format!("{}.{}", name, extension), body }
+    }
+}
+
+impl<'r> Responder<'r> for FormattedRoute {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        match self {
+            FormattedRoute::Json(route) => Json(route).respond_to(request),
+            FormattedRoute::GeoJson(feature) => Content(ContentType::new("application", "geo+json"), Json(feature)).respond_to(request),
+            FormattedRoute::Download { content_type, file_name, body } => Response::build_from(body.respond_to(request)?)
+                .header(content_type)
+                .header(Header::new("Content-Disposition", format!("attachment; filename=\"{}\"", file_name)))
+                .ok(),
+        }
+    }
+}
+
+impl OpenApiResponder<'_> for FormattedRoute {
+    fn responses(gen: &mut OpenApiGenerator) -> rocket_okapi::Result<Responses> {
+        let mut responses = Responses::default();
+        add_schema_response(&mut responses, 200, "application/json", gen.json_schema::<ShipRoute>())?;
+        add_schema_response(&mut responses, 200, "application/geo+json", gen.json_schema::<Value>())?;
+        add_schema_response(&mut responses, 200, "application/gpx+xml", gen.json_schema::<String>())?;
+        add_schema_response(&mut responses, 200, "application/vnd.google-earth.kml+xml", gen.json_schema::<String>())?;
+        add_schema_response(&mut responses, 200, "application/xml", gen.json_schema::<String>())?;
+        Ok(responses)
+    }
+}
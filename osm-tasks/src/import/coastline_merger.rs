@@ -0,0 +1,503 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use serde::Serialize;
+use crate::model::grid_graph::distance;
+use crate::import::node_index::NodeIndex;
+
+// maximum number of issues per category which are printed in warn mode
+const MAX_PRINTED_ISSUES: usize = 20;
+
+/// closed rings (lon, lat) of the merged coastlines
+pub type Rings = Vec<Vec<(f64, f64)>>;
+
+/// Defines how the merger reacts to open rings and self intersections
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MergeStrictness {
+    // only print a summary and continue with the closed rings
+    Ignore,
+    // print the issues and continue with the closed rings
+    Warn,
+    // fail if the coastlines could not be merged without issues
+    Strict,
+}
+
+impl FromStr for MergeStrictness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ignore" => Ok(MergeStrictness::Ignore),
+            "warn" => Ok(MergeStrictness::Warn),
+            "strict" => Ok(MergeStrictness::Strict),
+            _ => Err(format!("Unknown merge strictness {}, expected ignore, warn or strict", s)),
+        }
+    }
+}
+
+pub struct CoastlineWay {
+    pub(crate) id: i64,
+    pub(crate) nodes: Vec<i64>,
+}
+
+/// a chain of ways which could not be closed to a ring
+#[derive(Serialize, Clone, Debug)]
+pub struct OpenRing {
+    pub(crate) first_node: i64,
+    pub(crate) last_node: i64,
+    pub(crate) start: (f64, f64),
+    pub(crate) end: (f64, f64),
+    pub(crate) number_of_ways: usize,
+    pub(crate) number_of_coords: usize,
+}
+
+/// gap between the end of a chain and the nearest start of a chain
+#[derive(Serialize, Clone, Debug)]
+pub struct Gap {
+    pub(crate) from_node: i64,
+    pub(crate) to_node: i64,
+    pub(crate) from: (f64, f64),
+    pub(crate) to: (f64, f64),
+    // gap length in meters
+    pub(crate) distance: f64,
+    // true if the gap was within the tolerance and has been closed
+    pub(crate) closed: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DuplicateWay {
+    pub(crate) way_id: i64,
+    pub(crate) duplicate_of: i64,
+    // false if only the first node is equal but the ways differ
+    pub(crate) identical: bool,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SelfIntersection {
+    // index of the ring in the merged polygons
+    pub(crate) ring_index: usize,
+    pub(crate) location: (f64, f64),
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct MergeReport {
+    pub(crate) number_of_ways: usize,
+    pub(crate) number_of_rings: usize,
+    pub(crate) open_rings: Vec<OpenRing>,
+    pub(crate) gaps: Vec<Gap>,
+    pub(crate) duplicate_ways: Vec<DuplicateWay>,
+    pub(crate) self_intersections: Vec<SelfIntersection>,
+    // nodes referenced by coastline ways without known coordinates
+    pub(crate) missing_nodes: Vec<i64>,
+}
+
+impl MergeReport {
+    pub fn has_issues(&self) -> bool {
+        !self.open_rings.is_empty() || !self.self_intersections.is_empty() || !self.missing_nodes.is_empty()
+            || self.duplicate_ways.iter().any(|d| !d.identical)
+    }
+
+    pub fn print_details(&self) {
+        for open_ring in self.open_rings.iter().take(MAX_PRINTED_ISSUES) {
+            println!("Open ring from node {} {:?} to node {} {:?} with {} ways", open_ring.first_node, open_ring.start, open_ring.last_node, open_ring.end, open_ring.number_of_ways);
+        }
+        for gap in self.gaps.iter().filter(|g| !g.closed).take(MAX_PRINTED_ISSUES) {
+            println!("Gap of {:.1} m between node {} {:?} and node {} {:?}", gap.distance, gap.from_node, gap.from, gap.to_node, gap.to);
+        }
+        for duplicate in self.duplicate_ways.iter().take(MAX_PRINTED_ISSUES) {
+            println!("Way {} duplicates way {} (identical: {})", duplicate.way_id, duplicate.duplicate_of, duplicate.identical);
+        }
+        for intersection in self.self_intersections.iter().take(MAX_PRINTED_ISSUES) {
+            println!("Ring {} intersects itself at {:?}", intersection.ring_index, intersection.location);
+        }
+        if !self.missing_nodes.is_empty() {
+            println!("Could not find coords for nodes {:?}", &self.missing_nodes[..self.missing_nodes.len().min(MAX_PRINTED_ISSUES)]);
+        }
+    }
+}
+
+impl fmt::Display for MergeReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "merged {} ways to {} rings: {} open rings, {} gaps ({} closed), {} duplicate ways, {} self intersections, {} missing nodes",
+               self.number_of_ways, self.number_of_rings, self.open_rings.len(), self.gaps.len(), self.gaps.iter().filter(|g| g.closed).count(),
+               self.duplicate_ways.len(), self.self_intersections.len(), self.missing_nodes.len())
+    }
+}
+
+// chain of merged ways
+struct Chain {
+    first_node: i64,
+    last_node: i64,
+    coords: Vec<(f64, f64)>,
+    number_of_ways: usize,
+}
+
+/// Merges coastline ways to closed rings and collects all issues found while merging
+pub struct CoastlineMerger {
+    strictness: MergeStrictness,
+    // maximum gap length in meters which is closed automatically
+    gap_tolerance: f64,
+}
+
+impl CoastlineMerger {
+    pub fn new(strictness: MergeStrictness, gap_tolerance: f64) -> CoastlineMerger {
+        CoastlineMerger { strictness, gap_tolerance }
+    }
+
+    /// Returns the closed rings and the report. Fails with the report if the strictness is set to strict and issues were found.
+    pub fn merge(&self, ways: Vec<CoastlineWay>, node_to_location: &NodeIndex) -> Result<(Rings, MergeReport), Box<MergeReport>> {
+        let mut report = MergeReport { number_of_ways: ways.len(), ..Default::default() };
+
+        // key is the first node of the way
+        let mut ways_by_first_node: HashMap<i64, CoastlineWay> = HashMap::with_capacity(ways.len());
+        let mut way_ids: HashSet<i64> = HashSet::with_capacity(ways.len());
+        for way in ways {
+            if way.nodes.len() < 2 {
+                continue;
+            }
+            if !way_ids.insert(way.id) {
+                report.duplicate_ways.push(DuplicateWay { way_id: way.id, duplicate_of: way.id, identical: true });
+                continue;
+            }
+            if let Some(existing) = ways_by_first_node.get(&way.nodes[0]) {
+                report.duplicate_ways.push(DuplicateWay { way_id: way.id, duplicate_of: existing.id, identical: existing.nodes == way.nodes });
+                continue;
+            }
+            ways_by_first_node.insert(way.nodes[0], way);
+        }
+
+        let (mut rings, open_chains) = self.build_chains(&ways_by_first_node, node_to_location, &mut report);
+        let (closed_rings, open_chains) = self.close_gaps(open_chains, &mut report);
+        rings.extend(closed_rings);
+
+        for chain in open_chains {
+            report.open_rings.push(OpenRing {
+                first_node: chain.first_node,
+                last_node: chain.last_node,
+                start: *chain.coords.first().unwrap(),
+                end: *chain.coords.last().unwrap(),
+                number_of_ways: chain.number_of_ways,
+                number_of_coords: chain.coords.len(),
+            });
+        }
+        for (ring_index, ring) in rings.iter().enumerate() {
+            for location in find_self_intersections(ring) {
+                report.self_intersections.push(SelfIntersection { ring_index, location });
+            }
+        }
+        report.number_of_rings = rings.len();
+
+        println!("Coastline merge report: {}", report);
+        match self.strictness {
+            MergeStrictness::Ignore => Ok((rings, report)),
+            MergeStrictness::Warn => {
+                report.print_details();
+                Ok((rings, report))
+            }
+            MergeStrictness::Strict => {
+                if report.has_issues() {
+                    report.print_details();
+                    return Err(Box::new(report));
+                }
+                Ok((rings, report))
+            }
+        }
+    }
+
+    // follows the ways from their first to their last node, returns the closed rings and the open chains
+    fn build_chains(&self, ways_by_first_node: &HashMap<i64, CoastlineWay>, node_to_location: &NodeIndex, report: &mut MergeReport) -> (Vec<Vec<(f64, f64)>>, Vec<Chain>) {
+        let last_nodes: HashSet<i64> = ways_by_first_node.values().map(|way| *way.nodes.last().unwrap()).collect();
+        // start with ways which are not continuing another way, so that open chains are merged completely,
+        // the node ids are sorted so that the rings do not depend on the iteration order of the map
+        let mut start_nodes: Vec<i64> = ways_by_first_node.keys().filter(|first_node| !last_nodes.contains(first_node)).cloned().collect();
+        start_nodes.sort_unstable();
+        let mut ring_nodes: Vec<i64> = ways_by_first_node.keys().filter(|first_node| last_nodes.contains(first_node)).cloned().collect();
+        ring_nodes.sort_unstable();
+        start_nodes.extend(ring_nodes);
+
+        let mut visited: HashSet<i64> = HashSet::with_capacity(ways_by_first_node.len());
+        let mut rings = Vec::new();
+        let mut open_chains = Vec::new();
+        for start_node in start_nodes {
+            if visited.contains(&start_node) {
+                continue;
+            }
+            let mut chain = Chain { first_node: start_node, last_node: start_node, coords: Vec::new(), number_of_ways: 0 };
+            let mut current = start_node;
+            loop {
+                match ways_by_first_node.get(&current) {
+                    Some(way) if !visited.contains(&current) => {
+                        visited.insert(current);
+                        let skip = if chain.coords.is_empty() { 0 } else { 1 };
+                        for node in way.nodes[skip..].iter() {
+                            if let Some(coord) = node_to_location.get(node) {
+                                chain.coords.push(*coord);
+                            } else {
+                                report.missing_nodes.push(*node);
+                            }
+                        }
+                        chain.number_of_ways += 1;
+                        current = *way.nodes.last().unwrap();
+                        chain.last_node = current;
+                        if current == start_node {
+                            break;
+                        }
+                    }
+                    // the next way is missing or already part of another chain
+                    _ => break,
+                }
+            }
+            if chain.coords.is_empty() {
+                continue;
+            }
+            if chain.last_node == chain.first_node {
+                rings.push(chain.coords);
+            } else {
+                open_chains.push(chain);
+            }
+        }
+        // the node shared by two ways is looked up once for each of them
+        report.missing_nodes.sort_unstable();
+        report.missing_nodes.dedup();
+        (rings, open_chains)
+    }
+
+    // connects the end of each open chain to the nearest chain start within the gap tolerance
+    fn close_gaps(&self, mut open_chains: Vec<Chain>, report: &mut MergeReport) -> (Vec<Vec<(f64, f64)>>, Vec<Chain>) {
+        let mut rings = Vec::new();
+        let mut i = 0;
+        while i < open_chains.len() {
+            let end = *open_chains[i].coords.last().unwrap();
+            let nearest = open_chains.iter().enumerate()
+                .map(|(j, chain)| (j, distance(end.0, end.1, chain.coords[0].0, chain.coords[0].1)))
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            let (j, gap_distance) = match nearest {
+                Some(nearest) => nearest,
+                None => break,
+            };
+            let closed = gap_distance <= self.gap_tolerance;
+            report.gaps.push(Gap {
+                from_node: open_chains[i].last_node,
+                to_node: open_chains[j].first_node,
+                from: end,
+                to: open_chains[j].coords[0],
+                distance: gap_distance,
+                closed,
+            });
+            if !closed {
+                i += 1;
+                continue;
+            }
+            if i == j {
+                // the chain ends near its own start, close the ring
+                let mut chain = open_chains.remove(i);
+                chain.coords.push(chain.coords[0]);
+                rings.push(chain.coords);
+            } else {
+                // append chain j to chain i and check the new end again
+                let next = open_chains.remove(j);
+                let index = if j < i { i - 1 } else { i };
+                let chain = &mut open_chains[index];
+                chain.coords.extend(next.coords);
+                chain.last_node = next.last_node;
+                chain.number_of_ways += next.number_of_ways;
+                i = index;
+            }
+        }
+        (rings, open_chains)
+    }
+}
+
+/*
+ Finds intersections between non adjacent segments of the ring. The segments are sorted into a grid,
+ so that only segments sharing a grid cell are tested against each other.
+*/
+pub fn find_self_intersections(ring: &Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    let number_segments = ring.len().saturating_sub(1);
+    if number_segments < 4 {
+        return Vec::new();
+    }
+    let (mut lon_min, mut lat_min, mut lon_max, mut lat_max) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for (lon, lat) in ring {
+        lon_min = lon_min.min(*lon);
+        lon_max = lon_max.max(*lon);
+        lat_min = lat_min.min(*lat);
+        lat_max = lat_max.max(*lat);
+    }
+    let cells_per_axis = ((number_segments as f64).sqrt().ceil() as usize).max(1);
+    let cell_width = ((lon_max - lon_min) / cells_per_axis as f64).max(1e-9);
+    let cell_height = ((lat_max - lat_min) / cells_per_axis as f64).max(1e-9);
+    let cell_of = |lon: f64, lat: f64| -> (usize, usize) {
+        ((((lon - lon_min) / cell_width) as usize).min(cells_per_axis - 1), (((lat - lat_min) / cell_height) as usize).min(cells_per_axis - 1))
+    };
+    let mut grid: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for segment in 0..number_segments {
+        let (x1, y1) = cell_of(ring[segment].0, ring[segment].1);
+        let (x2, y2) = cell_of(ring[segment + 1].0, ring[segment + 1].1);
+        for x in x1.min(x2)..=x1.max(x2) {
+            for y in y1.min(y2)..=y1.max(y2) {
+                grid.entry((x, y)).or_default().push(segment);
+            }
+        }
+    }
+    let mut tested: HashSet<(usize, usize)> = HashSet::new();
+    let mut intersections = Vec::new();
+    for segments in grid.values() {
+        for a in 0..segments.len() {
+            for b in (a + 1)..segments.len() {
+                let (s1, s2) = (segments[a].min(segments[b]), segments[a].max(segments[b]));
+                // adjacent segments share a node, the first and last segment share the closing node
+                if s2 == s1 + 1 || (s1 == 0 && s2 == number_segments - 1) || !tested.insert((s1, s2)) {
+                    continue;
+                }
+                if let Some(location) = segment_intersection(ring[s1], ring[s1 + 1], ring[s2], ring[s2 + 1]) {
+                    intersections.push(location);
+                }
+            }
+        }
+    }
+    intersections
+}
+
+// intersection point of the segments p1-p2 and p3-p4 in planar lon/lat coordinates
+pub(crate) fn segment_intersection(p1: (f64, f64), p2: (f64, f64), p3: (f64, f64), p4: (f64, f64)) -> Option<(f64, f64)> {
+    let d1 = (p2.0 - p1.0, p2.1 - p1.1);
+    let d2 = (p4.0 - p3.0, p4.1 - p3.1);
+    let denominator = d1.0 * d2.1 - d1.1 * d2.0;
+    if denominator.abs() < 1e-18 {
+        // parallel segments
+        return None;
+    }
+    let t = ((p3.0 - p1.0) * d2.1 - (p3.1 - p1.1) * d2.0) / denominator;
+    let u = ((p3.0 - p1.0) * d1.1 - (p3.1 - p1.1) * d1.0) / denominator;
+    if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+        return Some((p1.0 + t * d1.0, p1.1 + t * d1.1));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node_index(nodes: &[(i64, (f64, f64))]) -> NodeIndex {
+        NodeIndex::new(nodes.to_vec())
+    }
+
+    fn way(id: i64, nodes: &[i64]) -> CoastlineWay {
+        CoastlineWay { id, nodes: nodes.to_vec() }
+    }
+
+    // square of four nodes around (10, 50)
+    fn square() -> NodeIndex {
+        node_index(&[(1, (10.0, 50.0)), (2, (10.1, 50.0)), (3, (10.1, 50.1)), (4, (10.0, 50.1))])
+    }
+
+    #[test]
+    fn ways_are_merged_to_closed_rings() {
+        let merger = CoastlineMerger::new(MergeStrictness::Strict, 0.0);
+        let (rings, report) = merger.merge(vec![way(1, &[1, 2, 3]), way(2, &[3, 4, 1])], &square()).unwrap();
+        assert_eq!(rings.len(), 1);
+        assert_eq!(rings[0], vec![(10.0, 50.0), (10.1, 50.0), (10.1, 50.1), (10.0, 50.1), (10.0, 50.0)]);
+        assert_eq!(report.number_of_rings, 1);
+        assert!(!report.has_issues());
+        assert!(report.gaps.is_empty());
+    }
+
+    #[test]
+    fn open_rings_are_reported_with_their_gap() {
+        let merger = CoastlineMerger::new(MergeStrictness::Warn, 0.0);
+        let (rings, report) = merger.merge(vec![way(1, &[1, 2, 3])], &square()).unwrap();
+        assert!(rings.is_empty());
+        assert_eq!(report.open_rings.len(), 1);
+        let open_ring = &report.open_rings[0];
+        assert_eq!((open_ring.first_node, open_ring.last_node), (1, 3));
+        assert_eq!((open_ring.start, open_ring.end), ((10.0, 50.0), (10.1, 50.1)));
+        assert_eq!((open_ring.number_of_ways, open_ring.number_of_coords), (1, 3));
+        // the gap from the end of the chain to its own start is reported, but not closed
+        assert_eq!(report.gaps.len(), 1);
+        let gap = &report.gaps[0];
+        assert_eq!((gap.from_node, gap.to_node), (3, 1));
+        assert_eq!((gap.from, gap.to), ((10.1, 50.1), (10.0, 50.0)));
+        assert!(gap.distance > 10_000.0);
+        assert!(!gap.closed);
+        assert!(report.has_issues());
+    }
+
+    #[test]
+    fn gaps_within_the_tolerance_are_closed() {
+        // two chains whose ends are about 36 m apart from the start of the other chain
+        let nodes = node_index(&[(1, (10.0, 50.0)), (2, (10.1, 50.0)), (3, (10.1, 50.1)),
+            (4, (10.0995, 50.1)), (5, (10.0, 50.1)), (6, (10.0, 50.0005))]);
+        let ways = || vec![way(1, &[1, 2, 3]), way(2, &[4, 5, 6])];
+
+        let (rings, report) = CoastlineMerger::new(MergeStrictness::Strict, 100.0).merge(ways(), &nodes).unwrap();
+        assert_eq!(rings.len(), 1);
+        assert_eq!(rings[0].len(), 7);
+        assert_eq!(rings[0].first(), rings[0].last());
+        assert_eq!(report.gaps.len(), 2);
+        assert!(report.gaps.iter().all(|gap| gap.closed && gap.distance < 100.0));
+        assert!(report.open_rings.is_empty());
+
+        let (rings, report) = CoastlineMerger::new(MergeStrictness::Warn, 10.0).merge(ways(), &nodes).unwrap();
+        assert!(rings.is_empty());
+        assert_eq!(report.open_rings.len(), 2);
+        assert!(report.gaps.iter().all(|gap| !gap.closed));
+    }
+
+    #[test]
+    fn duplicate_ways_are_reported() {
+        let merger = CoastlineMerger::new(MergeStrictness::Warn, 0.0);
+        // way 1 is contained twice, way 3 starts at the same node as way 1 but differs
+        let ways = vec![way(1, &[1, 2, 3]), way(2, &[3, 4, 1]), way(1, &[1, 2, 3]), way(3, &[1, 4])];
+        let (rings, report) = merger.merge(ways, &square()).unwrap();
+        assert_eq!(rings.len(), 1);
+        assert_eq!(report.duplicate_ways.len(), 2);
+        let identical = &report.duplicate_ways[0];
+        assert_eq!((identical.way_id, identical.duplicate_of, identical.identical), (1, 1, true));
+        let different = &report.duplicate_ways[1];
+        assert_eq!((different.way_id, different.duplicate_of, different.identical), (3, 1, false));
+        assert!(report.has_issues());
+    }
+
+    #[test]
+    fn self_intersections_are_reported() {
+        // the ring crosses itself in the middle of the square
+        let merger = CoastlineMerger::new(MergeStrictness::Warn, 0.0);
+        let (rings, report) = merger.merge(vec![way(1, &[1, 3, 2, 4, 1])], &square()).unwrap();
+        assert_eq!(rings.len(), 1);
+        assert_eq!(report.self_intersections.len(), 1);
+        let intersection = &report.self_intersections[0];
+        assert_eq!(intersection.ring_index, 0);
+        assert!((intersection.location.0 - 10.05).abs() < 1e-9 && (intersection.location.1 - 50.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_nodes_are_reported() {
+        let merger = CoastlineMerger::new(MergeStrictness::Ignore, 0.0);
+        let (_, report) = merger.merge(vec![way(1, &[1, 2, 3, 5]), way(2, &[5, 4, 1])], &square()).unwrap();
+        assert_eq!(report.missing_nodes, vec![5]);
+        assert!(report.has_issues());
+    }
+
+    #[test]
+    fn strict_mode_fails_with_the_report() {
+        let ways = || vec![way(1, &[1, 2, 3]), way(2, &[1, 4])];
+        let report = CoastlineMerger::new(MergeStrictness::Strict, 0.0).merge(ways(), &square()).unwrap_err();
+        assert_eq!(report.duplicate_ways.len(), 1);
+        assert_eq!(report.open_rings.len(), 1);
+        // the lenient modes continue with the closed rings
+        for strictness in [MergeStrictness::Ignore, MergeStrictness::Warn] {
+            let (rings, report) = CoastlineMerger::new(strictness, 0.0).merge(ways(), &square()).unwrap();
+            assert!(rings.is_empty());
+            assert_eq!(report.open_rings.len(), 1);
+        }
+    }
+
+    #[test]
+    fn strictness_is_parsed_case_insensitive() {
+        assert_eq!("STRICT".parse::<MergeStrictness>(), Ok(MergeStrictness::Strict));
+        assert_eq!("warn".parse::<MergeStrictness>(), Ok(MergeStrictness::Warn));
+        assert!("lenient".parse::<MergeStrictness>().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::Path;
+use serde_json::Value;
+use crate::model::facility::Facility;
+
+const DEFAULT_FACILITY_KIND: &str = "port";
+
+/// reads facilities from a CSV or GeoJSON file, the format is chosen by the file extension
+pub fn read_facilities(path: &str) -> Vec<Facility> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            println!("Could not read facilities file {}: {:?}", path, e);
+            return Vec::new();
+        }
+    };
+    let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    let facilities = match extension.as_str() {
+        "csv" => parse_csv(&content),
+        "geojson" | "json" => parse_geojson(&content),
+        _ => {
+            println!("Unsupported facilities file {}, expected .csv or .geojson", path);
+            Vec::new()
+        }
+    };
+    println!("Read {} facilities from {}", facilities.len(), path);
+    facilities
+}
+
+/*
+ Expects one facility per line in the form: name,lat,lon[,kind]
+ A header line and lines which can not be parsed are skipped.
+*/
+fn parse_csv(content: &str) -> Vec<Facility> {
+    let mut facilities = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let columns: Vec<&str> = line.split(',').map(|c| c.trim().trim_matches('"')).collect();
+        if columns.len() < 3 {
+            println!("Skipping line {} of facilities file: expected at least 3 columns", line_number + 1);
+            continue;
+        }
+        match (columns[1].parse::<f64>(), columns[2].parse::<f64>()) {
+            (Ok(lat), Ok(lon)) => {
+                let kind = columns.get(3).filter(|k| !k.is_empty()).unwrap_or(&DEFAULT_FACILITY_KIND);
+                facilities.push(Facility { name: columns[0].to_string(), kind: kind.to_string(), lat, lon });
+            }
+            _ => {
+                // most likely the header
+                if line_number != 0 {
+                    println!("Skipping line {} of facilities file: could not parse coordinates", line_number + 1);
+                }
+            }
+        }
+    }
+    facilities
+}
+
+// Expects a FeatureCollection of Points, name and kind are taken from the properties
+fn parse_geojson(content: &str) -> Vec<Facility> {
+    let json: Value = match serde_json::from_str(content) {
+        Ok(json) => json,
+        Err(e) => {
+            println!("Could not parse facilities GeoJSON: {:?}", e);
+            return Vec::new();
+        }
+    };
+    let features = match json["features"].as_array() {
+        Some(features) => features,
+        None => {
+            println!("Facilities GeoJSON does not contain a feature collection");
+            return Vec::new();
+        }
+    };
+    features.iter().enumerate().filter_map(|(i, feature)| {
+        let geometry = &feature["geometry"];
+        if geometry["type"].as_str() != Some("Point") {
+            println!("Skipping feature {} of facilities file: geometry is not a point", i);
+            return None;
+        }
+        let lon = geometry["coordinates"][0].as_f64()?;
+        let lat = geometry["coordinates"][1].as_f64()?;
+        let properties = &feature["properties"];
+        let name = properties["name"].as_str().map(String::from).unwrap_or_else(|| format!("facility {}", i));
+        let kind = properties["kind"].as_str().unwrap_or(DEFAULT_FACILITY_KIND).to_string();
+        Some(Facility { name, kind, lat, lon })
+    }).collect()
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use serde_json::Value;
+use crate::import::polygon_source::{PolygonSource, close_ring};
+use crate::model::land_polygons::{LandPolygons, PolygonWithHoles};
+
+/// Reads all Polygon and MultiPolygon geometries of a GeoJSON file. The file can
+/// contain a FeatureCollection, a single Feature or a bare geometry.
+pub struct GeoJsonSource;
+
+impl PolygonSource for GeoJsonSource {
+    fn read_polygons(&self, path: &str) -> Result<LandPolygons, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Could not read file {}: {:?}", path, e))?;
+        let polygons = parse_geojson(&content).map_err(|e| format!("Could not parse GeoJSON {}: {}", path, e))?;
+        Ok(LandPolygons::new(polygons, Vec::new()))
+    }
+}
+
+fn parse_geojson(content: &str) -> Result<Vec<PolygonWithHoles>, String> {
+    let json: Value = serde_json::from_str(content).map_err(|e| format!("{:?}", e))?;
+    let mut polygons = Vec::new();
+    match json["type"].as_str() {
+        Some("FeatureCollection") => {
+            let features = json["features"].as_array().ok_or("FeatureCollection without features")?;
+            for feature in features {
+                add_geometry(&feature["geometry"], &mut polygons);
+            }
+        }
+        Some("Feature") => add_geometry(&json["geometry"], &mut polygons),
+        _ => add_geometry(&json, &mut polygons),
+    }
+    Ok(polygons)
+}
+
+fn add_geometry(geometry: &Value, polygons: &mut Vec<PolygonWithHoles>) {
+    match geometry["type"].as_str() {
+        Some("Polygon") => add_polygon(&geometry["coordinates"], polygons),
+        Some("MultiPolygon") => {
+            for polygon in geometry["coordinates"].as_array().unwrap_or(&Vec::new()) {
+                add_polygon(polygon, polygons);
+            }
+        }
+        Some("GeometryCollection") => {
+            for geometry in geometry["geometries"].as_array().unwrap_or(&Vec::new()) {
+                add_geometry(geometry, polygons);
+            }
+        }
+        // points and lines are no land areas
+        _ => {}
+    }
+}
+
+// the first ring of a polygon is the outer ring, all others are holes
+fn add_polygon(rings: &Value, polygons: &mut Vec<PolygonWithHoles>) {
+    let mut rings: Vec<Vec<(f64, f64)>> = rings.as_array().unwrap_or(&Vec::new()).iter().map(|ring| {
+        let mut ring: Vec<(f64, f64)> = ring.as_array().unwrap_or(&Vec::new()).iter()
+            .filter_map(|position| Some((position[0].as_f64()?, position[1].as_f64()?)))
+            .collect();
+        close_ring(&mut ring);
+        ring
+    }).collect();
+    if rings.is_empty() {
+        return;
+    }
+    let outer = rings.remove(0);
+    polygons.push(PolygonWithHoles { outer, holes: rings });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_polygons_with_holes() {
+        let polygons = parse_geojson(r#"{"type": "Polygon", "coordinates": [
+            [[0, 0], [10, 0], [10, 10], [0, 10], [0, 0]],
+            [[2, 2], [4, 2], [4, 4]]
+        ]}"#).unwrap();
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].outer, vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)]);
+        // open rings are closed
+        assert_eq!(polygons[0].holes, vec![vec![(2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (2.0, 2.0)]]);
+    }
+
+    #[test]
+    fn parses_multipolygons_of_a_feature_collection() {
+        let polygons = parse_geojson(r#"{"type": "FeatureCollection", "features": [
+            {"type": "Feature", "properties": {}, "geometry": {"type": "MultiPolygon", "coordinates": [
+                [[[0, 0], [10, 0], [10, 10], [0, 0]], [[1, 1], [2, 1], [2, 2], [1, 1]]],
+                [[[20, 20], [30, 20], [30, 30], [20, 20]]]
+            ]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Point", "coordinates": [5, 5]}},
+            {"type": "Feature", "properties": {}, "geometry": {"type": "Polygon", "coordinates": [[[-5.5, 1.5], [-4, 1.5], [-4, 3], [-5.5, 1.5]]]}}
+        ]}"#).unwrap();
+        assert_eq!(polygons.len(), 3);
+        assert_eq!(polygons[0].holes.len(), 1);
+        assert!(polygons[1].holes.is_empty());
+        assert_eq!(polygons[1].outer[1], (30.0, 20.0));
+        assert_eq!(polygons[2].outer[0], (-5.5, 1.5));
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        assert!(parse_geojson(r#"{"type": "Polygon", "coordinates": [[[0, 0], [10, 0]"#).is_err());
+        assert!(parse_geojson(r#"{"type": "FeatureCollection"}"#).is_err());
+        assert!(parse_geojson(r#"{"type": "LineString", "coordinates": [[0, 0], [10, 0]]}"#).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::time::Instant;
+use crate::algorithms::land_test::IncrementalLandTest;
+use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+use crate::config::Config;
+use crate::import::osc_reader::read_change_file;
+use crate::import::pbf_reader::{read_file_with_changes, read_or_create_polygons, clip_to_configured_region, create_polygon_test, generate_graph,
+                                polygons_path, graph_path, cn_metadata_path, changes_path, save_polygons_to_disk, save_graph_to_disk, load_graph_from_disk,
+                                create_save_cn_metadata, read_saved_changes, save_changes_to_disk};
+use crate::import::polygon_source::read_polygons;
+use crate::model::grid_graph::GridGraph;
+use crate::model::land_polygons::{LandPolygons, bounding_box};
+use crate::model::region::BoundingBox;
+
+/// Updates the polygons and the graph of the coastlines file with an OSM change file (.osc) or a new
+/// coastlines file. The changes of all change files are saved, so that each change file is applied
+/// together with the previous ones and a rebuild from the coastlines file keeps them. Only grid points in the bounding boxes of changed rings are tested against the new
+/// polygons, all other points keep their classification. The derived data (connected components and
+/// contraction hierarchy) is recomputed afterwards.
+pub fn update_graph(osm_path_name: &str, update_path_name: &str, number_nodes: usize) -> Result<(), String> {
+    let start_time = Instant::now();
+    let config = Config::global();
+    let osm_path = Path::new(osm_path_name);
+    let old_polygons = read_or_create_polygons(osm_path_name, false)?;
+    let is_change_file = Path::new(update_path_name).extension().and_then(|e| e.to_str()) == Some("osc");
+    let new_polygons = if is_change_file {
+        let new_changes = read_change_file(update_path_name).map_err(|e| format!("Could not read change file: {}", e))?;
+        if new_changes.is_empty() {
+            println!("Change file {} contains no changes", update_path_name);
+            return Ok(());
+        }
+        let mut changes = read_saved_changes(osm_path)?;
+        changes.merge(new_changes);
+        let polygons = read_file_with_changes(osm_path_name, &changes).map_err(|e| format!("Could not read polygons: {}", e))?;
+        save_changes_to_disk(&changes_path(osm_path), &changes)
+            .map_err(|e| format!("Could not save change set, the next update would drop this one: {:?}", e))?;
+        println!("Saved {} changed nodes and {} changed ways of all updates", changes.nodes.len(), changes.ways.len());
+        polygons
+    } else {
+        read_polygons(update_path_name, config.polygon_format()).map_err(|e| format!("Could not read polygons: {}", e))?
+    };
+    let new_polygons = clip_to_configured_region(new_polygons);
+
+    let affected_areas = changed_areas(&old_polygons, &new_polygons);
+    println!("Found {} changed rings", affected_areas.len());
+    if let Err(e) = save_polygons_to_disk(&polygons_path(osm_path), &new_polygons) {
+        println!("Could not save updated polygons to disk: {:?}", e);
+    }
+    if affected_areas.is_empty() {
+        println!("Polygons did not change, the graph is up to date");
+        return Ok(());
+    }
+
+    let path = graph_path(osm_path, number_nodes);
+    let polygon_test = create_polygon_test(&new_polygons);
+    let graph = match load_graph_from_disk(&path) {
+        Ok(old_graph) => patch_graph(&old_graph, affected_areas, &polygon_test, number_nodes),
+        Err(_) => {
+            println!("No graph found at {}, generating the graph from scratch", path.to_str().unwrap());
+            generate_graph(&polygon_test, number_nodes)
+        }
+    };
+    if graph.nodes.is_empty() {
+        return Err(String::from("The updated graph has no nodes"));
+    }
+    match save_graph_to_disk(&path, &graph) {
+        Ok(()) => println!("Saved updated graph to disk at {}", path.to_str().unwrap()),
+        Err(e) => println!("Could not save updated graph to {}: {:?}", path.to_str().unwrap(), e),
+    }
+
+    // the contraction hierarchy depends on the edges of the graph and has to be rebuilt
+    let cn_path = cn_metadata_path(osm_path, number_nodes);
+    if cn_path.is_file() {
+        if let Err(e) = fs::remove_file(&cn_path) {
+            println!("Could not remove outdated cn metadata {}: {:?}", cn_path.to_str().unwrap(), e);
+        }
+        create_save_cn_metadata(&cn_path, &graph);
+    }
+    println!("Updated graph in {} sec", start_time.elapsed().as_secs());
+    Ok(())
+}
+
+/// generates the graph again, only grid points in the affected areas are tested against the polygons
+fn patch_graph(old_graph: &GridGraph, affected_areas: Vec<BoundingBox>, polygon_test: &NestedPolygonTest, number_nodes: usize) -> GridGraph {
+    let land_test = IncrementalLandTest::new(old_graph, affected_areas, polygon_test);
+    let graph = generate_graph(&land_test, number_nodes);
+    println!("Reclassified {} grid points in changed areas, node count changed from {} to {}", land_test.number_tested_points(), old_graph.nodes.len(), graph.nodes.len());
+    graph
+}
+
+/// bounding boxes of all rings which are only part of one of the polygon sets
+fn changed_areas(old_polygons: &LandPolygons, new_polygons: &LandPolygons) -> Vec<BoundingBox> {
+    let old_rings = ring_fingerprints(old_polygons);
+    let new_rings = ring_fingerprints(new_polygons);
+    old_rings.iter().filter(|(fingerprint, _)| !new_rings.contains_key(fingerprint))
+        .chain(new_rings.iter().filter(|(fingerprint, _)| !old_rings.contains_key(fingerprint)))
+        .map(|(_, bbox)| *bbox)
+        .collect()
+}
+
+fn ring_fingerprints(polygons: &LandPolygons) -> HashMap<u64, BoundingBox> {
+    polygons.land.iter().chain(polygons.inland_water.iter())
+        .flat_map(|polygon| polygon.rings())
+        .map(|ring| {
+            let mut hasher = DefaultHasher::new();
+            ring.iter().for_each(|(lon, lat)| (lon.to_bits(), lat.to_bits()).hash(&mut hasher));
+            let (lon_min, lat_min, lon_max, lat_max) = bounding_box(ring);
+            (hasher.finish(), BoundingBox { lon_min, lat_min, lon_max, lat_max })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn island(lon: f64, lat: f64) -> Vec<(f64, f64)> {
+        vec![(lon, lat), (lon + 20.0, lat), (lon + 20.0, lat + 20.0), (lon, lat + 20.0), (lon, lat)]
+    }
+
+    fn positions(graph: &GridGraph, filter: impl Fn(f64, f64) -> bool) -> HashSet<(u64, u64)> {
+        graph.nodes.iter().filter(|node| filter(node.lon, node.lat)).map(|node| (node.lon.to_bits(), node.lat.to_bits())).collect()
+    }
+
+    #[test]
+    fn only_nodes_in_changed_areas_are_reclassified() {
+        Config::init_for_tests();
+        let old_polygons = LandPolygons::from_coastline_rings(vec![island(10.0, 0.0)]);
+        let new_polygons = LandPolygons::from_coastline_rings(vec![island(10.0, 0.0), island(100.0, 0.0)]);
+        let old_graph = generate_graph(&NestedPolygonTest::new(&old_polygons, false), 5000);
+
+        let affected_areas = changed_areas(&old_polygons, &new_polygons);
+        assert_eq!(affected_areas.len(), 1);
+        let changed = affected_areas[0];
+        assert!(!positions(&old_graph, |lon, lat| changed.contains(lon, lat)).is_empty());
+
+        // the polygon test also contains an island outside of the changed area, which must not be tested
+        let unchanged = BoundingBox { lon_min: -80.0, lat_min: 0.0, lon_max: -60.0, lat_max: 20.0 };
+        let polygons = LandPolygons::from_coastline_rings(vec![island(10.0, 0.0), island(100.0, 0.0), island(-80.0, 0.0)]);
+        let graph = patch_graph(&old_graph, affected_areas, &NestedPolygonTest::new(&polygons, false), 5000);
+
+        // grid points on the coastline are water
+        let inside = |lon: f64, lat: f64| lon > changed.lon_min && lon < changed.lon_max && lat > changed.lat_min && lat < changed.lat_max;
+        assert!(positions(&graph, inside).is_empty());
+        assert!(!positions(&graph, |lon, lat| unchanged.contains(lon, lat)).is_empty());
+        assert_eq!(positions(&graph, |lon, lat| !changed.contains(lon, lat)), positions(&old_graph, |lon, lat| !changed.contains(lon, lat)));
+    }
+}
@@ -1 +1,11 @@
 pub(crate) mod pbf_reader;
+pub(crate) mod facility_reader;
+pub(crate) mod coastline_merger;
+pub(crate) mod node_index;
+pub(crate) mod polygon_source;
+pub(crate) mod geojson_polygon_reader;
+pub(crate) mod shapefile_reader;
+pub(crate) mod wkt_reader;
+pub(crate) mod multipolygon;
+pub(crate) mod osc_reader;
+pub(crate) mod graph_update;
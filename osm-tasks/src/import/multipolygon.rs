@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+use crate::import::node_index::NodeIndex;
+use crate::model::land_polygons::{PolygonWithHoles, ring_contains};
+
+/// multipolygon relation with the ids of its member ways and if the member is an inner ring
+pub struct WaterRelation {
+    pub(crate) id: i64,
+    pub(crate) members: Vec<(i64, bool)>,
+}
+
+/// builds the polygons of a multipolygon relation. The member ways are joined to rings and each inner
+/// ring becomes a hole of the outer ring containing it. Returns None if no outer ring could be closed.
+pub fn assemble_multipolygon(relation: &WaterRelation, ways: &HashMap<i64, Vec<i64>>, node_to_location: &NodeIndex) -> Option<Vec<PolygonWithHoles>> {
+    let member_ways = |inner: bool| -> Vec<Vec<i64>> {
+        relation.members.iter()
+            .filter(|(_, is_inner)| *is_inner == inner)
+            .filter_map(|(way_id, _)| ways.get(way_id).filter(|nodes| nodes.len() > 1).cloned())
+            .collect()
+    };
+    let (outer_rings, number_open_outer) = join_ways_to_rings(member_ways(false));
+    let (inner_rings, number_open_inner) = join_ways_to_rings(member_ways(true));
+    if number_open_outer + number_open_inner > 0 {
+        println!("Multipolygon relation {} has {} open rings", relation.id, number_open_outer + number_open_inner);
+    }
+    let mut polygons: Vec<PolygonWithHoles> = outer_rings.iter()
+        .filter_map(|ring| resolve_ring(ring, node_to_location))
+        .map(PolygonWithHoles::new)
+        .collect();
+    if polygons.is_empty() {
+        return None;
+    }
+    for ring in inner_rings.iter().filter_map(|ring| resolve_ring(ring, node_to_location)) {
+        if let Some(polygon) = polygons.iter_mut().find(|polygon| ring_contains(&polygon.outer, ring[0])) {
+            polygon.holes.push(ring);
+        }
+    }
+    Some(polygons)
+}
+
+/// builds a polygon without holes from a closed way
+pub fn resolve_closed_way(nodes: &[i64], node_to_location: &NodeIndex) -> Option<PolygonWithHoles> {
+    if nodes.len() < 4 || nodes.first() != nodes.last() {
+        return None;
+    }
+    resolve_ring(nodes, node_to_location).map(PolygonWithHoles::new)
+}
+
+/*
+ Joins the ways at their end nodes to closed rings, the ways of a multipolygon relation do not need to
+ have the same direction. Returns the closed rings and the number of chains which could not be closed.
+*/
+fn join_ways_to_rings(mut ways: Vec<Vec<i64>>) -> (Vec<Vec<i64>>, usize) {
+    let mut rings = Vec::new();
+    let mut number_open = 0;
+    while let Some(mut ring) = ways.pop() {
+        while ring.first() != ring.last() {
+            let end = *ring.last().unwrap();
+            let next = ways.iter().position(|way| *way.first().unwrap() == end || *way.last().unwrap() == end);
+            match next {
+                Some(i) => {
+                    let mut way = ways.swap_remove(i);
+                    if *way.first().unwrap() != end {
+                        way.reverse();
+                    }
+                    ring.extend(way.into_iter().skip(1));
+                }
+                None => break,
+            }
+        }
+        if ring.len() >= 4 && ring.first() == ring.last() {
+            rings.push(ring);
+        } else {
+            number_open += 1;
+        }
+    }
+    (rings, number_open)
+}
+
+// rings with missing nodes are dropped, because they can not be closed correctly
+fn resolve_ring(nodes: &[i64], node_to_location: &NodeIndex) -> Option<Vec<(f64, f64)>> {
+    nodes.iter().map(|node| node_to_location.get(node).cloned()).collect()
+}
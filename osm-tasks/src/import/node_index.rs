@@ -0,0 +1,25 @@
+use rayon::prelude::*;
+
+/// Maps OSM node ids to their coordinates (lon, lat). The ids are stored in a sorted vector, which
+/// needs considerably less memory than a hash map and is queried with a binary search.
+pub struct NodeIndex {
+    ids: Vec<i64>,
+    locations: Vec<(f64, f64)>,
+}
+
+impl NodeIndex {
+    pub fn new(mut entries: Vec<(i64, (f64, f64))>) -> NodeIndex {
+        entries.par_sort_unstable_by_key(|(id, _)| *id);
+        entries.dedup_by_key(|(id, _)| *id);
+        let (ids, locations) = entries.into_iter().unzip();
+        NodeIndex { ids, locations }
+    }
+
+    pub fn get(&self, id: &i64) -> Option<&(f64, f64)> {
+        self.ids.binary_search(id).ok().map(|index| &self.locations[index])
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use serde::{Serialize, Deserialize};
+use quick_xml::Reader;
+use quick_xml::events::{Event, BytesStart};
+
+/// way of an OSM change file, only the tags relevant for the polygons are kept
+#[derive(Serialize, Deserialize)]
+pub struct ChangedWay {
+    pub(crate) nodes: Vec<i64>,
+    pub(crate) is_coastline: bool,
+    pub(crate) is_water: bool,
+}
+
+/// Created, modified and deleted nodes and ways of an OSM change file (.osc). Deleted elements are
+/// stored as None. Changes of water relations are not supported, so only the ids of the changed
+/// relations are kept to detect changes of the water relations of the coastlines file.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ChangeSet {
+    pub(crate) nodes: HashMap<i64, Option<(f64, f64)>>,
+    pub(crate) ways: HashMap<i64, Option<ChangedWay>>,
+    pub(crate) relations: HashSet<i64>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.ways.is_empty() && self.relations.is_empty()
+    }
+
+    /// adds the changes of a newer change set, they replace the changes of the same elements
+    pub fn merge(&mut self, newer: ChangeSet) {
+        self.nodes.extend(newer.nodes);
+        self.ways.extend(newer.ways);
+        self.relations.extend(newer.relations);
+    }
+}
+
+/// reads an OSM change file (osmChange XML)
+pub fn read_change_file(path: &str) -> Result<ChangeSet, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Could not read change file {}: {:?}", path, e))?;
+    let mut reader = Reader::from_str(&content);
+    reader.trim_text(true);
+    let mut buffer = Vec::new();
+    let mut changes = ChangeSet::default();
+    let mut is_delete = false;
+    // id and way of the way which is currently read
+    let mut current_way: Option<(i64, ChangedWay)> = None;
+    // id of the relation which is currently read
+    let mut current_relation: Option<i64> = None;
+    loop {
+        let event = reader.read_event(&mut buffer);
+        let is_empty_element = matches!(event, Ok(Event::Empty(_)));
+        match event {
+            Ok(Event::Start(ref element)) | Ok(Event::Empty(ref element)) => {
+                match element.name() {
+                    b"create" | b"modify" => is_delete = false,
+                    b"delete" => is_delete = true,
+                    b"node" => {
+                        let attributes = read_attributes(element)?;
+                        let id = parse_attribute::<i64>(&attributes, "id")?;
+                        let location = if is_delete {
+                            None
+                        } else {
+                            Some((parse_attribute::<f64>(&attributes, "lon")?, parse_attribute::<f64>(&attributes, "lat")?))
+                        };
+                        changes.nodes.insert(id, location);
+                    }
+                    b"way" => {
+                        let attributes = read_attributes(element)?;
+                        let id = parse_attribute::<i64>(&attributes, "id")?;
+                        if is_delete {
+                            changes.ways.insert(id, None);
+                        } else if !is_empty_element {
+                            current_way = Some((id, ChangedWay { nodes: Vec::new(), is_coastline: false, is_water: false }));
+                        }
+                    }
+                    b"relation" => {
+                        let id = parse_attribute::<i64>(&read_attributes(element)?, "id")?;
+                        changes.relations.insert(id);
+                        if !is_empty_element {
+                            current_relation = Some(id);
+                        }
+                    }
+                    b"nd" => {
+                        if let Some((_, way)) = current_way.as_mut() {
+                            way.nodes.push(parse_attribute::<i64>(&read_attributes(element)?, "ref")?);
+                        }
+                    }
+                    b"tag" => {
+                        if let Some((_, way)) = current_way.as_mut() {
+                            let attributes = read_attributes(element)?;
+                            match (attributes.get("k").map(|k| k.as_str()), attributes.get("v").map(|v| v.as_str())) {
+                                (Some("natural"), Some("coastline")) => way.is_coastline = true,
+                                (Some("natural"), Some("water")) => way.is_water = true,
+                                _ => {}
+                            }
+                        } else if let Some(id) = current_relation {
+                            let attributes = read_attributes(element)?;
+                            if attributes.get("k").map(|k| k.as_str()) == Some("natural") && attributes.get("v").map(|v| v.as_str()) == Some("water") {
+                                return Err(format!("Change file {} changes the water relation {}, changes of relations are not supported", path, id));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Ok(Event::End(ref element)) => {
+                match element.name() {
+                    b"way" => {
+                        if let Some((id, way)) = current_way.take() {
+                            changes.ways.insert(id, Some(way));
+                        }
+                    }
+                    b"relation" => current_relation = None,
+                    _ => {}
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Could not parse change file {}: {:?}", path, e)),
+            _ => {}
+        }
+        buffer.clear();
+    }
+    println!("Read {} changed nodes, {} changed ways and {} changed relations from {}", changes.nodes.len(), changes.ways.len(), changes.relations.len(), path);
+    Ok(changes)
+}
+
+fn read_attributes(element: &BytesStart) -> Result<HashMap<String, String>, String> {
+    element.attributes().map(|attribute| {
+        let attribute = attribute.map_err(|e| format!("invalid attribute: {:?}", e))?;
+        let key = String::from_utf8_lossy(attribute.key).to_string();
+        let value = attribute.unescaped_value().map_err(|e| format!("invalid attribute value: {:?}", e))?;
+        Ok((key, String::from_utf8_lossy(&value).to_string()))
+    }).collect()
+}
+
+fn parse_attribute<T: std::str::FromStr>(attributes: &HashMap<String, String>, name: &str) -> Result<T, String> {
+    attributes.get(name)
+        .and_then(|value| value.parse::<T>().ok())
+        .ok_or(format!("missing or invalid attribute {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_content(name: &str, content: &str) -> Result<ChangeSet, String> {
+        let path = std::env::temp_dir().join(format!("osm-tasks-test-{}-{}.osc", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        let changes = read_change_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        changes
+    }
+
+    #[test]
+    fn reads_nodes_ways_and_relations() {
+        let changes = read_content("elements", r#"<osmChange version="0.6">
+            <modify>
+                <node id="1" lat="54.5" lon="10.5"/>
+                <way id="10"><nd ref="1"/><nd ref="2"/><tag k="natural" v="coastline"/></way>
+                <relation id="100"><member type="way" ref="10" role=""/><tag k="type" v="route"/></relation>
+            </modify>
+            <delete><node id="2"/><way id="11"/></delete>
+        </osmChange>"#).unwrap();
+        assert_eq!(changes.nodes[&1], Some((10.5, 54.5)));
+        assert_eq!(changes.nodes[&2], None);
+        let way = changes.ways[&10].as_ref().unwrap();
+        assert_eq!(way.nodes, vec![1, 2]);
+        assert!(way.is_coastline && !way.is_water);
+        assert!(changes.ways[&11].is_none());
+        assert!(changes.relations.contains(&100));
+    }
+
+    #[test]
+    fn changes_of_water_relations_are_errors() {
+        let changes = read_content("relation", r#"<osmChange version="0.6"><create>
+            <relation id="100"><member type="way" ref="10" role="outer"/><tag k="natural" v="water"/></relation>
+        </create></osmChange>"#);
+        assert!(changes.is_err());
+    }
+
+    #[test]
+    fn newer_changes_replace_older_ones() {
+        let mut changes = ChangeSet::default();
+        changes.nodes.insert(1, Some((1.0, 1.0)));
+        changes.nodes.insert(2, Some((2.0, 2.0)));
+        let mut newer = ChangeSet::default();
+        newer.nodes.insert(1, None);
+        changes.merge(newer);
+        assert_eq!(changes.nodes[&1], None);
+        assert_eq!(changes.nodes[&2], Some((2.0, 2.0)));
+    }
+}
@@ -1,33 +1,133 @@
 use std::time::Instant;
-use std::collections::{HashMap, HashSet};
-use osmpbf::Element;
+use std::collections::HashMap;
+use osmpbf::{Element, RelMemberType};
 use rayon::prelude::*;
 use crate::algorithms::polygon_test::PointInPolygonTest;
+use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+use crate::algorithms::land_test::LandTest;
 use osmpbf::ElementReader;
 use std::fs::File;
 use std::io::{Write, BufWriter, BufReader};
 use core::iter;
-use std::iter::FromIterator;
 use rand::distributions::{Distribution, Uniform};
-use std::slice::Iter;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::ffi::OsStr;
 use crate::export::json_generator::JsonBuilder;
 use crate::export::kml_exporter::KmlExport;
-use crate::model::grid_graph::GridGraph;
+use crate::model::grid_graph::{GridGraph, GridType};
 use crate::model::cn_model::CNMetadata;
 use crate::algorithms::cn_graph_creator::CNGraphCreator;
-
-/// tries to load the graph for this from disk and builds the graph if prebuild graph was found.
-pub(crate) fn read_or_create_graph<S: AsRef<OsStr> + ?Sized>(osm_path_name: &S, force_create: bool, number_nodes: usize) -> GridGraph {
+use crate::config::Config;
+use crate::import::coastline_merger::{CoastlineMerger, CoastlineWay};
+use crate::import::node_index::NodeIndex;
+use crate::import::polygon_source::read_polygons;
+use crate::import::multipolygon::{WaterRelation, assemble_multipolygon, resolve_closed_way};
+use crate::model::land_polygons::LandPolygons;
+use crate::algorithms::polygon_simplification::simplify_polygons;
+use crate::algorithms::polygon_clipping::clip_polygons_to_bbox;
+use crate::model::region::Region;
+use crate::import::osc_reader::ChangeSet;
+use crate::algorithms::grid_generator::configured_generator;
+use crate::model::weight::WeightUnit;
+use crate::model::build_status::BuildPhase;
+use crate::persistence::build_tracker::BuildTracker;
+
+// margin around regions in degrees, polygons outside of it are removed
+const REGION_POLYGON_MARGIN: f64 = 1.0;
+// version of the format of the polygons cache, caches with another version are created again
+const POLYGONS_CACHE_VERSION: u32 = 2;
+
+/// tries to load the merged polygons from disk and reads the coastlines or land polygons file if no polygons were found.
+pub(crate) fn read_or_create_polygons<S: AsRef<OsStr> + ?Sized>(osm_path_name: &S, force_create: bool) -> Result<LandPolygons, String> {
     let osm_path = Path::new(osm_path_name);
-    let osm_name = osm_path.file_name().unwrap();
-    let mut graph_file_name = osm_name.to_str().unwrap().to_owned();
-    graph_file_name.push_str(".");
-    graph_file_name.push_str(&*number_nodes.to_string());
+    let path = polygons_path(osm_path);
+    if !force_create {
+        match load_polygons_from_disk(&path) {
+            Ok(polygons) => {
+                println!("Loaded polygons from disk \"{}\". Polygon count: {}, inland waters: {}", path.to_str().unwrap(), polygons.land.len(), polygons.inland_water.len());
+                return Ok(polygons);
+            }
+            Err(e) => println!("polygons not ok: {:?}", e),
+        }
+    }
+    BuildTracker::global().phase(BuildPhase::ReadingPbf);
+    let polygons = read_polygons(osm_path.to_str().unwrap(), Config::global().polygon_format())
+        .map_err(|e| format!("Could not read polygons: {}", e))?;
+    let polygons = clip_to_configured_region(polygons);
+    BuildTracker::global().phase(BuildPhase::Saving);
+    match save_polygons_to_disk(&path, &polygons) {
+        Ok(()) => println!("Saved polygons to disk at {}", path.to_str().unwrap()),
+        Err(e) => println!("Could not save polygons to {}: {:?}", path.to_str().unwrap(), e),
+    }
+    Ok(polygons)
+}
+
+/// clips the polygons to the configured region, polygons are returned unchanged if no region is configured
+pub(crate) fn clip_to_configured_region(polygons: LandPolygons) -> LandPolygons {
+    match Region::configured() {
+        Some(region) => {
+            // keep a margin around the region, so that line of sight tests at the border of the region are correct
+            let clipped = clip_polygons_to_bbox(&polygons, &region.bounding_box().expand(REGION_POLYGON_MARGIN));
+            println!("Clipped polygons to region {}: {} land polygons, {} inland waters", region.name(), clipped.land.len(), clipped.inland_water.len());
+            clipped
+        }
+        None => polygons,
+    }
+}
+
+/// creates the land test for the graph generation, the polygons are simplified if a tolerance is configured
+pub(crate) fn create_polygon_test(polygons: &LandPolygons) -> NestedPolygonTest {
+    let config = Config::global();
+    if config.simplify_tolerance() > 0.0 {
+        let (simplified, report) = simplify_polygons(polygons, config.simplify_tolerance());
+        println!("Polygons for the land test: {}", report);
+        return NestedPolygonTest::new(&simplified, config.include_inland_water());
+    }
+    NestedPolygonTest::new(polygons, config.include_inland_water())
+}
+
+/// generates a regional graph if a region is configured and a graph with the configured grid type otherwise
+pub(crate) fn generate_graph(land_test: &dyn LandTest, number_nodes: usize) -> GridGraph {
+    let mut graph = configured_generator(number_nodes).generate(land_test);
+    graph.assign_components(Config::global().min_component_size());
+    graph
+}
+
+pub(crate) fn polygons_path(osm_path: &Path) -> PathBuf {
+    let mut polygons_file_name = osm_path.file_name().unwrap().to_str().unwrap().to_owned();
+    if let Some(region) = Region::configured() {
+        polygons_file_name.push('.');
+        polygons_file_name.push_str(&region.name());
+    }
+    polygons_file_name.push_str(".polygons");
+    osm_path.with_file_name(polygons_file_name)
+}
+
+/// path of the change set of all updates which were applied to the coastlines file
+pub(crate) fn changes_path(osm_path: &Path) -> PathBuf {
+    let mut changes_file_name = osm_path.file_name().unwrap().to_str().unwrap().to_owned();
+    changes_file_name.push_str(".changes");
+    osm_path.with_file_name(changes_file_name)
+}
+
+pub(crate) fn graph_path(osm_path: &Path, number_nodes: usize) -> PathBuf {
+    let mut graph_file_name = osm_path.file_name().unwrap().to_str().unwrap().to_owned();
+    graph_file_name.push_str(&graph_name_suffix(number_nodes));
     graph_file_name.push_str(".bin_new");
-    println!("force create? {}, filename {}", force_create, graph_file_name);
-    let path = osm_path.with_file_name(graph_file_name);
+    osm_path.with_file_name(graph_file_name)
+}
+
+pub(crate) fn cn_metadata_path(osm_path: &Path, number_nodes: usize) -> PathBuf {
+    let mut cn_file_name = osm_path.file_name().unwrap().to_str().unwrap().to_owned();
+    cn_file_name.push_str(&graph_name_suffix(number_nodes));
+    cn_file_name.push_str(".cn_meta");
+    osm_path.with_file_name(cn_file_name)
+}
+
+/// tries to load the graph for this from disk and builds the graph if prebuild graph was found.
+pub(crate) fn read_or_create_graph<S: AsRef<OsStr> + ?Sized>(osm_path_name: &S, force_create: bool, number_nodes: usize, polygon_test: &dyn LandTest) -> GridGraph {
+    let path = graph_path(Path::new(osm_path_name), number_nodes);
+    println!("force create? {}, filename {}", force_create, path.to_str().unwrap());
     if !force_create {
         let disk_graph = load_graph_from_disk(&path);
         if disk_graph.is_ok() {
@@ -38,26 +138,42 @@ pub(crate) fn read_or_create_graph<S: AsRef<OsStr> + ?Sized>(osm_path_name: &S,
             println!("graph not ok");
         }
     }
-    let polygons = read_file(osm_path.to_str().unwrap());
-    let polygon_test = PointInPolygonTest::new(polygons);
-
     // assign new value to the GRAPH reference
-    let gra = GridGraph::new(&polygon_test, number_nodes);
-    save_graph_to_disk(&path, &gra);
-    println!("Saved graph to disk at {}", path.to_str().unwrap());
+    let gra = generate_graph(polygon_test, number_nodes);
+    BuildTracker::global().phase(BuildPhase::Saving);
+    match save_graph_to_disk(&path, &gra) {
+        Ok(()) => println!("Saved graph to disk at {}", path.to_str().unwrap()),
+        Err(e) => println!("Could not save graph to {}: {:?}", path.to_str().unwrap(), e),
+    }
 
-    return gra;
+    gra
 }
 
-pub(crate) fn read_or_create_cn_metadata<S: AsRef<OsStr> + ?Sized>(osm_path_name: &S, force_recreate: bool, number_nodes: usize, initial_graph: &GridGraph) -> CNMetadata {
-    let osm_path = Path::new(osm_path_name);
-    let osm_name = osm_path.file_name().unwrap();
-    let mut graph_file_name = osm_name.to_str().unwrap().to_owned();
-    graph_file_name.push_str(".");
-    graph_file_name.push_str(&*number_nodes.to_string());
-    graph_file_name.push_str(".cn_meta");
+/// part of the graph file names which identifies the graph: the number of nodes or the region (including
+/// the spacing of regional graphs) and the options which change the land test
+fn graph_name_suffix(number_nodes: usize) -> String {
+    let config = Config::global();
+    let mut suffix = match Region::configured() {
+        Some(region) => format!(".{}", region.name()),
+        None => format!(".{}", number_nodes),
+    };
+    if Region::configured().is_none() && config.grid_type() != GridType::Sphere {
+        suffix.push_str(&format!(".{}", config.grid_type()));
+    }
+    if config.include_inland_water() {
+        suffix.push_str(".water");
+    }
+    if config.simplify_tolerance() > 0.0 {
+        suffix.push_str(&format!(".s{}", config.simplify_tolerance()));
+    }
+    if config.weight_unit() != WeightUnit::Centimeter {
+        suffix.push_str(&format!(".{}", config.weight_unit()));
+    }
+    suffix
+}
 
-    let path = osm_path.with_file_name(graph_file_name);
+pub(crate) fn read_or_create_cn_metadata<S: AsRef<OsStr> + ?Sized>(osm_path_name: &S, force_recreate: bool, number_nodes: usize, initial_graph: &GridGraph) -> CNMetadata {
+    let path = cn_metadata_path(Path::new(osm_path_name), number_nodes);
 
     println!("trying to load {}", path.to_str().expect("failed"));
     if !force_recreate {
@@ -70,32 +186,71 @@ pub(crate) fn read_or_create_cn_metadata<S: AsRef<OsStr> + ?Sized>(osm_path_name
             println!("cn metadata not ok");
         }
     }
-    return create_save_cn_metadata(&path, initial_graph)
+    create_save_cn_metadata(&path, initial_graph)
 }
 
-fn create_save_cn_metadata(path: &Path, initial_graph: &GridGraph) -> CNMetadata {
+pub(crate) fn create_save_cn_metadata(path: &Path, initial_graph: &GridGraph) -> CNMetadata {
     let mut creator = CNGraphCreator::new(initial_graph);
     let data = creator.build_cn_graph();
-    save_cn_metadata_to_disk(path, &data);
-    println!("saved cn metadata at {}", path.to_str().unwrap());
-    return data;
+    BuildTracker::global().phase(BuildPhase::Saving);
+    match save_cn_metadata_to_disk(path, &data) {
+        Ok(()) => println!("saved cn metadata at {}", path.to_str().unwrap()),
+        Err(e) => println!("Could not save cn metadata to {}: {:?}", path.to_str().unwrap(), e),
+    }
+    data
+}
+
+fn save_cn_metadata_to_disk(path: &Path, meta: &CNMetadata) -> bincode::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    bincode::serialize_into(&mut f, meta)?;
+    f.flush()?;
+    Ok(())
+}
+
+pub(crate) fn save_graph_to_disk(path: &Path, graph: &GridGraph) -> bincode::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    bincode::serialize_into(&mut f, graph)?;
+    f.flush()?;
+    Ok(())
 }
 
-fn save_cn_metadata_to_disk(path: &Path, meta: &CNMetadata) {
-    let mut f = BufWriter::new(File::create(path).unwrap());
-    if let Err(e) = bincode::serialize_into(&mut f, meta) {
-        println!("Could not save cn metadata to disk: {:?}", e);
+pub(crate) fn save_polygons_to_disk(path: &Path, polygons: &LandPolygons) -> bincode::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    bincode::serialize_into(&mut f, &POLYGONS_CACHE_VERSION)?;
+    bincode::serialize_into(&mut f, polygons)?;
+    f.flush()?;
+    Ok(())
+}
+
+pub(crate) fn load_polygons_from_disk(path: &Path) -> bincode::Result<LandPolygons> {
+    let mut f = BufReader::new(File::open(path)?);
+    let version: u32 = bincode::deserialize_from(&mut f)?;
+    if version != POLYGONS_CACHE_VERSION {
+        return Err(Box::new(bincode::ErrorKind::Custom(format!("polygons cache has version {}, expected {}", version, POLYGONS_CACHE_VERSION))));
     }
+    bincode::deserialize_from(&mut f)
 }
 
-fn save_graph_to_disk(path: &Path, graph: &GridGraph) {
-    let mut f = BufWriter::new(File::create(path).unwrap());
-    if let Err(e) = bincode::serialize_into(&mut f, graph) {
-        println!("Could not save graph to disk: {:?}", e);
+pub(crate) fn save_changes_to_disk(path: &Path, changes: &ChangeSet) -> bincode::Result<()> {
+    let mut f = BufWriter::new(File::create(path)?);
+    bincode::serialize_into(&mut f, changes)?;
+    f.flush()?;
+    Ok(())
+}
+
+/// change set of the previous updates of the coastlines file, empty if the file was never updated
+pub(crate) fn read_saved_changes(osm_path: &Path) -> Result<ChangeSet, String> {
+    let path = changes_path(osm_path);
+    if !path.is_file() {
+        return Ok(ChangeSet::default());
     }
+    let mut f = BufReader::new(File::open(&path).map_err(|e| format!("Could not open change set {}: {:?}", path.to_str().unwrap(), e))?);
+    let changes: ChangeSet = bincode::deserialize_from(&mut f).map_err(|e| format!("Could not read change set {}: {:?}", path.to_str().unwrap(), e))?;
+    println!("Applying {} changed nodes and {} changed ways of previous updates", changes.nodes.len(), changes.ways.len());
+    Ok(changes)
 }
 
-fn load_graph_from_disk(path: &Path) -> bincode::Result<GridGraph> {
+pub(crate) fn load_graph_from_disk(path: &Path) -> bincode::Result<GridGraph> {
     let mut f = BufReader::new(File::open(path)?);
     bincode::deserialize_from(&mut f)
 }
@@ -105,49 +260,152 @@ fn load_cn_meta_from_disk(path: &Path) -> bincode::Result<CNMetadata> {
     bincode::deserialize_from(&mut f)
 }
 
-pub fn read_file(path: &str) -> Vec<Vec<(f64, f64)>> {
-    let start_time = Instant::now();
-    let reader = ElementReader::from_path(path).expect(&*format!("failed to read file {}", path));
+/// reads the polygons of the file with the changes of previous updates applied
+pub fn read_file(path: &str) -> Result<LandPolygons, String> {
+    read_file_with_changes(path, &read_saved_changes(Path::new(path))?)
+}
 
-    // key is the first node of the way; value is a tuple containing the last node and the whole way
-    let mut coastlines: HashMap<i64, (i64, Vec<i64>)> = HashMap::new();
-    let mut node_to_location: HashMap<i64, (f64, f64)> = HashMap::new();
+/// reads the polygons of the file with the changes of an OSM change file applied. Nodes and ways of the
+/// change set replace the elements of the file with the same id.
+pub fn read_file_with_changes(path: &str, changes: &ChangeSet) -> Result<LandPolygons, String> {
+    let start_time = Instant::now();
     println!("Reading file {}", path);
 
-    /*
-     Assumptions:
-     - each coastline way ends with a node which is contained in another coastline way
-    */
-    if let Err(e) = reader.for_each(|item| {
-        match item {
-            Element::Way(way) => {
-                if let Some(_) = way.tags().find(|(k, v)| *k == "natural" && *v == "coastline") {
-                    let first_node_id = way.refs().next().expect("way does not contain any nodes");
-                    if let Some(last) = way.refs().last() {
-                        coastlines.insert(first_node_id, (last, way.refs().collect()));
+    // first pass: collect the coastline ways, the closed water ways and the water multipolygon relations
+    let reader = ElementReader::from_path(path).map_err(|e| format!("Failed to read file {}: {}", path, e))?;
+    let elements: WaterAndCoastlineElements = reader.par_map_reduce(
+        |element| {
+            let mut elements = WaterAndCoastlineElements::default();
+            match element {
+                Element::Way(way) if changes.ways.contains_key(&way.id()) => {}
+                Element::Way(way) => {
+                    if way.tags().any(|(k, v)| k == "natural" && v == "coastline") {
+                        elements.coastlines.push(CoastlineWay { id: way.id(), nodes: way.refs().collect() });
+                    } else if way.tags().any(|(k, v)| k == "natural" && v == "water") {
+                        // open water ways are members of multipolygon relations
+                        let nodes: Vec<i64> = way.refs().collect();
+                        if nodes.len() >= 4 && nodes.first() == nodes.last() {
+                            elements.water_ways.push((way.id(), nodes));
+                        }
                     }
                 }
+                Element::Relation(relation) => {
+                    if relation.tags().any(|(k, v)| k == "natural" && v == "water") && relation.tags().any(|(k, v)| k == "type" && v == "multipolygon") {
+                        let members = relation.members()
+                            .filter(|member| member.member_type == RelMemberType::Way)
+                            .map(|member| (member.member_id, member.role().map(|role| role == "inner").unwrap_or(false)))
+                            .collect();
+                        elements.water_relations.push(WaterRelation { id: relation.id(), members });
+                    }
+                }
+                _ => {}
             }
-            Element::Node(node) => {
-                node_to_location.insert(node.id(), (node.lon(), node.lat()));
-            }
-            Element::DenseNode(node) => {
-                node_to_location.insert(node.id(), (node.lon(), node.lat()));
-            }
+            elements
+        },
+        WaterAndCoastlineElements::default,
+        |a, b| a.merge(b),
+    ).map_err(|e| format!("Could not read coastlines file {}: {}", path, e))?;
+    let WaterAndCoastlineElements { mut coastlines, mut water_ways, water_relations } = elements;
+    if let Some(relation) = water_relations.iter().find(|relation| changes.relations.contains(&relation.id)) {
+        return Err(format!("The water relation {} was changed, changes of relations are not supported", relation.id));
+    }
+    for (id, way) in changes.ways.iter() {
+        match way {
+            Some(way) if way.is_coastline => coastlines.push(CoastlineWay { id: *id, nodes: way.nodes.clone() }),
+            Some(way) if way.is_water && way.nodes.len() >= 4 && way.nodes.first() == way.nodes.last() => water_ways.push((*id, way.nodes.clone())),
             _ => {}
         }
-    }) {
-        println!("Could not read coastlines file: {:?}", e);
     }
-    println!("Reading done in {} sec", start_time.elapsed().as_secs());
+    println!("Found {} coastline ways, {} water ways and {} water relations in {} sec", coastlines.len(), water_ways.len(), water_relations.len(), start_time.elapsed().as_secs());
+
+    // second pass: collect the member ways of the relations, they are not necessarily tagged themselves
+    let mut member_way_ids: Vec<i64> = water_relations.iter().flat_map(|relation| relation.members.iter().map(|(id, _)| *id)).collect();
+    member_way_ids.par_sort_unstable();
+    member_way_ids.dedup();
+    // closed water ways which are members of a relation are part of its polygons already
+    let number_water_ways = water_ways.len();
+    water_ways.retain(|(id, _)| member_way_ids.binary_search(id).is_err());
+    if water_ways.len() < number_water_ways {
+        println!("Skipped {} water ways which are members of water relations", number_water_ways - water_ways.len());
+    }
+    let member_ways: HashMap<i64, Vec<i64>> = if member_way_ids.is_empty() {
+        HashMap::new()
+    } else {
+        let reader = ElementReader::from_path(path).map_err(|e| format!("Failed to read file {}: {}", path, e))?;
+        reader.par_map_reduce(
+            |element| {
+                match element {
+                    Element::Way(way) if !changes.ways.contains_key(&way.id()) && member_way_ids.binary_search(&way.id()).is_ok() => vec![(way.id(), way.refs().collect::<Vec<i64>>())],
+                    _ => Vec::new()
+                }
+            },
+            Vec::new,
+            |mut a, mut b| {
+                a.append(&mut b);
+                a
+            },
+        ).map_err(|e| format!("Could not read coastlines file {}: {}", path, e))?.into_iter()
+            .chain(member_way_ids.iter().filter_map(|id| changes.ways.get(id).and_then(|way| way.as_ref()).map(|way| (*id, way.nodes.clone()))))
+            .collect()
+    };
+
+    let mut node_ids: Vec<i64> = coastlines.iter().map(|way| &way.nodes)
+        .chain(water_ways.iter().map(|(_, nodes)| nodes))
+        .chain(member_ways.values())
+        .flat_map(|nodes| nodes.iter().cloned())
+        .collect();
+    node_ids.par_sort_unstable();
+    node_ids.dedup();
+    println!("Coastlines and waters reference {} nodes", node_ids.len());
+
+    // third pass: resolve the locations of the referenced nodes only
+    let reader = ElementReader::from_path(path).map_err(|e| format!("Failed to read file {}: {}", path, e))?;
+    let mut locations: Vec<(i64, (f64, f64))> = reader.par_map_reduce(
+        |element| {
+            let (id, location) = match element {
+                Element::Node(node) => (node.id(), (node.lon(), node.lat())),
+                Element::DenseNode(node) => (node.id(), (node.lon(), node.lat())),
+                _ => return Vec::new()
+            };
+            if !changes.nodes.contains_key(&id) && node_ids.binary_search(&id).is_ok() {
+                vec![(id, location)]
+            } else {
+                Vec::new()
+            }
+        },
+        Vec::new,
+        |mut a, mut b| {
+            a.append(&mut b);
+            a
+        },
+    ).map_err(|e| format!("Could not read coastlines file {}: {}", path, e))?;
+    locations.extend(changes.nodes.iter()
+        .filter(|(id, _)| node_ids.binary_search(id).is_ok())
+        .filter_map(|(id, location)| location.map(|location| (*id, location))));
+    drop(node_ids);
+    let node_to_location = NodeIndex::new(locations);
+    println!("Reading done in {} sec, resolved {} nodes", start_time.elapsed().as_secs(), node_to_location.len());
+    BuildTracker::global().phase(BuildPhase::MergingPolygons);
     let merge_start_time = Instant::now();
-    let mut polygons: Vec<Vec<(f64, f64)>> = merge_ways_to_polygons1(coastlines, node_to_location);
-
-    println!("Merged coastlines to {} polygons in {} sec", polygons.len(), merge_start_time.elapsed().as_secs());
-    check_polygons_closed(&polygons);
+    let config = Config::global();
+    let merger = CoastlineMerger::new(config.merge_strictness(), config.gap_tolerance());
+    let rings: Vec<Vec<(f64, f64)>> = match merger.merge(coastlines, &node_to_location) {
+        Ok((rings, _)) => rings,
+        Err(report) => return Err(format!("Could not merge coastlines: {}", report)),
+    };
+    let mut polygons = LandPolygons::from_coastline_rings(rings);
+    println!("Merged coastlines to {} polygons with {} holes in {} sec", polygons.land.len(), polygons.number_of_holes(), merge_start_time.elapsed().as_secs());
+
+    polygons.inland_water = water_ways.iter().filter_map(|(_, nodes)| resolve_closed_way(nodes, &node_to_location)).collect();
+    for relation in water_relations.iter() {
+        if let Some(water) = assemble_multipolygon(relation, &member_ways, &node_to_location) {
+            polygons.inland_water.extend(water);
+        }
+    }
+    println!("Built {} inland water polygons", polygons.inland_water.len());
 
     // sort polygons by size so that we check the bigger before the smaller ones
-    polygons.sort_by(|a, b| b.len().cmp(&a.len()));
+    polygons.land.sort_by(|a, b| b.outer.len().cmp(&a.outer.len()));
 
     /*
     let file = "poly";
@@ -167,13 +425,31 @@ pub fn read_file(path: &str) -> Vec<Vec<(f64, f64)>> {
     //let graph = GridGraph::new();
     //graph.nodes.into_iter().foreach(|n| { kml.add_point(n, None) });
     //kml.write_file("kml.kml".parse().unwrap());
-    return polygons;
+    Ok(polygons)
+}
+
+// elements collected in the first pass of the import
+#[derive(Default)]
+struct WaterAndCoastlineElements {
+    coastlines: Vec<CoastlineWay>,
+    // closed water ways with their id
+    water_ways: Vec<(i64, Vec<i64>)>,
+    water_relations: Vec<WaterRelation>,
+}
+
+impl WaterAndCoastlineElements {
+    fn merge(mut self, mut other: WaterAndCoastlineElements) -> WaterAndCoastlineElements {
+        self.coastlines.append(&mut other.coastlines);
+        self.water_ways.append(&mut other.water_ways);
+        self.water_relations.append(&mut other.water_relations);
+        self
+    }
 }
 
 pub fn read_file_and_export_geojson(osm_path: &str, geojson_path: &str) {
-    let polygons = read_file(osm_path);
+    let polygons = read_polygons_for_export(osm_path);
     let mut builder = JsonBuilder::new(geojson_path.parse().unwrap());
-    builder.add_polygons(polygons);
+    builder.add_polygons_with_holes(polygons.land.iter().map(|polygon| polygon.rings().into_iter().cloned().collect()).collect());
     builder.build();
 }
 
@@ -210,7 +486,7 @@ pub fn lines_to_json(lines: Vec<((f64, f64), (f64, f64))>) -> String {
       \"properties\": {{}},
       \"geometry\":  {} \
     }}\n,", geometry);
-        features = features + &*feature;
+        features += &*feature;
     }
     features.pop();
     format!("{{
@@ -221,20 +497,26 @@ pub fn lines_to_json(lines: Vec<((f64, f64), (f64, f64))>) -> String {
 }}", features)
 }
 
-#[allow(dead_code)]
-fn export_polygons_with_resolution(polygons: Vec<Vec<(f64, f64)>>, path: String, max_nodes_per_polygon: usize) {
+pub fn read_file_and_export_kml(osm_path: &str, kml_path: &str) {
+    let polygons = read_polygons_for_export(osm_path);
     let mut kml = KmlExport::init();
-    polygons.into_iter().map(|poly| {
-        if poly.len() > max_nodes_per_polygon {
-            let inverse_factor = poly.len() / max_nodes_per_polygon;
-            let first = *poly.first().unwrap();
-            return vec![first].into_iter().chain(poly.into_iter().step_by(inverse_factor)).collect();
-        }
-        poly
-    }).for_each(|poly| {
-        kml.add_polygon(poly, None);
+    polygons.land.into_iter().for_each(|polygon| {
+        kml.add_polygon_with_holes(polygon.outer, polygon.holes, None);
     });
-    kml.write_file(path);
+    kml.write_file(kml_path.to_string());
+}
+
+// reads the polygons and simplifies them with the export tolerance
+fn read_polygons_for_export(osm_path: &str) -> LandPolygons {
+    let config = Config::global();
+    let polygons = read_polygons(osm_path, config.polygon_format())
+        .unwrap_or_else(|e| panic!("Could not read polygons: {}", e));
+    if config.export_simplify_tolerance() > 0.0 {
+        let (simplified, report) = simplify_polygons(&polygons, config.export_simplify_tolerance());
+        println!("Polygons for the export: {}", report);
+        return simplified;
+    }
+    polygons
 }
 
 #[allow(dead_code)]
@@ -246,113 +528,32 @@ fn test_random_points_in_polygon(polygon_test: &PointInPolygonTest, number_of_po
         (rng_lon.sample(&mut rng), rng_lat.sample(&mut rng))
     }).collect();
     coords.into_par_iter().map(|test_point: (f64, f64)| {
-        if polygon_test.check_intersection(test_point.clone()) {
+        if polygon_test.check_intersection(test_point) {
             return test_point;
         }
-        return (f64::NAN, f64::NAN);
+        (f64::NAN, f64::NAN)
     }).filter(|(lon, _): &(f64, f64)| { !lon.is_nan() }).collect()
 }
 
-fn merge_ways_to_polygons1(coastlines: HashMap<i64, (i64, Vec<i64>)>, node_to_location: HashMap<i64, (f64, f64)>) -> Vec<Vec<(f64, f64)>> {
-    let mut polygons: Vec<Vec<(f64, f64)>> = Vec::new();
-    let mut visited: HashMap<i64, bool> = HashMap::new();
-    for key in coastlines.keys() {
-        visited.insert(*key, false);
-    }
-
-    for key in coastlines.keys() {
-        if *visited.get(key).unwrap_or(&true) {
-            continue;
-        }
-        let mut start = key;
-        let mut poly: Vec<(f64, f64)> = vec![*node_to_location.get(start).expect("Could not find coords for start node")];
-
-        loop {
-            if let Some((end, way)) = coastlines.get(start) {
-                // add way to polygon
-                for node in way[1..].iter() {
-                    if let Some((lat, lon)) = node_to_location.get(node) {
-                        poly.push((*lat, *lon));
-                    } else {
-                        print!("could not find coords for node {}", node)
-                    }
-                }
-                visited.insert(*start, true);
-                start = end;
-            } else {
-                println!("Could not find node {} in coastlines map", start);
-                break;
-            }
-            if let Some(visit) = visited.get(start) {
-                if *visit == true {
-                    polygons.push(poly);
-                    break;
-                }
-            } else {
-                println!("Could not find node {} in visited map", start);
-            }
-        }
-    }
-    return polygons;
-}
-
-#[allow(dead_code)]
-fn merge_ways_to_polygons2(coastlines: HashMap<i64, (i64, Vec<i64>)>, node_to_location: HashMap<i64, (f64, f64)>) -> Vec<Vec<(f64, f64)>> {
-    let mut unprocessed_coastlines: HashSet<&i64> = HashSet::from_iter(coastlines.keys());
-    let mut polygons: Vec<Vec<(f64, f64)>> = vec![];
-
-    while !unprocessed_coastlines.is_empty() {
-        let first_node = **unprocessed_coastlines.iter().next().expect("Coastline already processed");
-        let (mut next_node, nodes) = coastlines.get(&first_node).expect("coastline not found in map");
-        unprocessed_coastlines.remove(&first_node);
-
-        let mut polygon: Vec<(f64, f64)> = Vec::with_capacity(nodes.len());
-        append_coords_from_map_for_nodes(&node_to_location, &mut polygon, &mut nodes.iter());
-        while next_node != first_node {
-            unprocessed_coastlines.remove(&next_node);
-            reserve_space_if_below_threshold(&mut polygon, 2000, 5000);
-            if let Some((next_next_node, nodes)) = coastlines.get(&next_node) {
-                append_coords_from_map_for_nodes(&node_to_location, &mut polygon, &mut nodes[1..].iter());
-                next_node = *next_next_node;
-            } else {
-                println!("Could not find next node {}", next_node);
-                break;
-            }
-        }
-        polygon.shrink_to_fit();
-        reserve_space_if_below_threshold(&mut polygons, 1, 5000);
-        polygons.push(polygon);
-    }
-    polygons.shrink_to_fit();
-    return polygons;
-}
-
-#[inline]
-fn append_coords_from_map_for_nodes(node_to_location: &HashMap<i64, (f64, f64)>, polygon: &mut Vec<(f64, f64)>, nodes: &mut Iter<i64>) {
-    nodes.for_each(|node_id| {
-        if let Some(coord) = node_to_location.get(node_id) {
-            polygon.push(*coord);
-        } else {
-            //Should not happen
-            println!("Could not resolve coord for node: {}", node_id)
-        }
-    });
-}
-
-#[inline]
-// use this function to check periodically if the capacity of a vector is below a limit
-// to avoid expensive memory allocation at every insert operation
-fn reserve_space_if_below_threshold<T>(vector: &mut Vec<T>, minimum_size: usize, reserved_size: usize) {
-    if vector.capacity() < minimum_size {
-        vector.reserve(reserved_size);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::land_polygons::PolygonWithHoles;
+
+    #[test]
+    fn polygons_cache_checks_the_version() {
+        let path = std::env::temp_dir().join(format!("osm-tasks-test-{}.polygons", std::process::id()));
+        let square = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.0, 0.0)];
+        let polygons = LandPolygons::new(vec![PolygonWithHoles::new(square.clone())], vec![]);
+        save_polygons_to_disk(&path, &polygons).unwrap();
+        assert_eq!(load_polygons_from_disk(&path).unwrap().land[0].outer, square);
+
+        // cache of an older version without header
+        let mut f = BufWriter::new(File::create(&path).unwrap());
+        bincode::serialize_into(&mut f, &polygons).unwrap();
+        f.flush().unwrap();
+        drop(f);
+        assert!(load_polygons_from_disk(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
     }
 }
-
-fn check_polygons_closed(polygons: &Vec<Vec<(f64, f64)>>) -> bool {
-    let polygon_count = polygons.len();
-    let closed_polygons_count = polygons.iter().filter(|polygon| !polygon.is_empty() && polygon.first() == polygon.last()).count();
-    println!("{} of {} polygons are closed", closed_polygons_count, polygon_count);
-    polygon_count == closed_polygons_count
-}
-
-
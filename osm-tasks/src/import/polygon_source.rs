@@ -0,0 +1,93 @@
+use std::path::Path;
+use std::str::FromStr;
+use crate::import::pbf_reader::read_file;
+use crate::import::geojson_polygon_reader::GeoJsonSource;
+use crate::import::shapefile_reader::ShapefileSource;
+use crate::import::wkt_reader::WktSource;
+use crate::model::land_polygons::LandPolygons;
+
+/// Source of the land polygons which are used by the point in polygon test. All rings are closed
+/// lists of (lon, lat) coordinates.
+pub trait PolygonSource {
+    fn read_polygons(&self, path: &str) -> Result<LandPolygons, String>;
+}
+
+/// merges the coastline ways of an OSM PBF file to polygons and reads the inland waters
+pub struct PbfSource;
+
+impl PolygonSource for PbfSource {
+    fn read_polygons(&self, path: &str) -> Result<LandPolygons, String> {
+        read_file(path)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PolygonFormat {
+    Pbf,
+    GeoJson,
+    Shapefile,
+    Wkt,
+}
+
+impl FromStr for PolygonFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pbf" => Ok(PolygonFormat::Pbf),
+            "geojson" | "json" => Ok(PolygonFormat::GeoJson),
+            "shp" | "shapefile" => Ok(PolygonFormat::Shapefile),
+            "wkt" => Ok(PolygonFormat::Wkt),
+            _ => Err(format!("unknown polygon format {}, expected one of: pbf, geojson, shapefile, wkt", s)),
+        }
+    }
+}
+
+impl PolygonFormat {
+    /// guesses the format by the file extension
+    pub fn from_path(path: &str) -> Option<PolygonFormat> {
+        let extension = Path::new(path).extension().and_then(|e| e.to_str())?;
+        match extension.to_lowercase().as_str() {
+            "pbf" => Some(PolygonFormat::Pbf),
+            "geojson" | "json" => Some(PolygonFormat::GeoJson),
+            "shp" => Some(PolygonFormat::Shapefile),
+            "wkt" => Some(PolygonFormat::Wkt),
+            _ => None,
+        }
+    }
+
+    pub fn source(&self) -> Box<dyn PolygonSource> {
+        match self {
+            PolygonFormat::Pbf => Box::new(PbfSource),
+            PolygonFormat::GeoJson => Box::new(GeoJsonSource),
+            PolygonFormat::Shapefile => Box::new(ShapefileSource),
+            PolygonFormat::Wkt => Box::new(WktSource),
+        }
+    }
+}
+
+/// reads the polygons with the given format or the format matching the file extension if none is given.
+/// Files with an unknown extension are read as OSM PBF.
+pub fn read_polygons(path: &str, format: Option<PolygonFormat>) -> Result<LandPolygons, String> {
+    let format = format.or_else(|| PolygonFormat::from_path(path)).unwrap_or(PolygonFormat::Pbf);
+    println!("Reading polygons from {} as {:?}", path, format);
+    let mut polygons = format.source().read_polygons(path)?;
+    for polygons in [&mut polygons.land, &mut polygons.inland_water] {
+        // drop degenerated rings, a ring needs at least three distinct points and the closing point
+        polygons.retain(|polygon| polygon.outer.len() >= 4);
+        polygons.iter_mut().for_each(|polygon| polygon.holes.retain(|hole| hole.len() >= 4));
+        // sort polygons by size so that we check the bigger before the smaller ones
+        polygons.sort_by(|a, b| b.outer.len().cmp(&a.outer.len()));
+    }
+    println!("Read {} land polygons with {} holes and {} inland waters from {}", polygons.land.len(), polygons.number_of_holes(), polygons.inland_water.len(), path);
+    Ok(polygons)
+}
+
+/// closes the ring if the last coordinate is not equal to the first one
+pub(crate) fn close_ring(ring: &mut Vec<(f64, f64)>) {
+    if let (Some(first), Some(last)) = (ring.first().cloned(), ring.last().cloned()) {
+        if first != last {
+            ring.push(first);
+        }
+    }
+}
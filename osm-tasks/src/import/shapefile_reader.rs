@@ -0,0 +1,72 @@
+use shapefile::{PolygonRing, Polygon};
+use crate::import::polygon_source::{PolygonSource, close_ring};
+use crate::model::land_polygons::{LandPolygons, PolygonWithHoles, ring_contains};
+
+/// Reads the polygons of an ESRI shapefile with polygon shapes, e.g. the land polygons from
+/// osmdata.openstreetmap.de. The coordinates have to be in WGS84 (EPSG:4326).
+pub struct ShapefileSource;
+
+impl PolygonSource for ShapefileSource {
+    fn read_polygons(&self, path: &str) -> Result<LandPolygons, String> {
+        let shapes = shapefile::read_shapes_as::<_, Polygon>(path)
+            .map_err(|e| format!("Could not read shapefile {}: {:?}", path, e))?;
+        let mut polygons = Vec::new();
+        for shape in shapes.iter() {
+            let mut outers: Vec<PolygonWithHoles> = Vec::new();
+            let mut holes: Vec<Vec<(f64, f64)>> = Vec::new();
+            for ring in shape.rings() {
+                let mut coords: Vec<(f64, f64)> = ring.points().iter().map(|point| (point.x, point.y)).collect();
+                close_ring(&mut coords);
+                match ring {
+                    PolygonRing::Outer(_) => outers.push(PolygonWithHoles::new(coords)),
+                    PolygonRing::Inner(_) => holes.push(coords),
+                }
+            }
+            // a shape can have multiple outer rings, each hole belongs to the outer ring containing it
+            for hole in holes {
+                if let Some(outer) = outers.iter_mut().find(|outer| ring_contains(&outer.outer, hole[0])) {
+                    outer.holes.push(hole);
+                }
+            }
+            polygons.extend(outers);
+        }
+        Ok(LandPolygons::new(polygons, Vec::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shapefile::{Point, ShapeWriter};
+
+    fn ring(coords: &[(f64, f64)]) -> Vec<Point> {
+        coords.iter().map(|(x, y)| Point::new(*x, *y)).collect()
+    }
+
+    #[test]
+    fn holes_are_assigned_to_the_outer_ring_containing_them() {
+        // one shape with two outer rings and a hole in the second one
+        let shape = Polygon::with_rings(vec![
+            PolygonRing::Outer(ring(&[(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0), (0.0, 0.0)])),
+            PolygonRing::Outer(ring(&[(20.0, 0.0), (20.0, 10.0), (30.0, 10.0), (30.0, 0.0), (20.0, 0.0)])),
+            PolygonRing::Inner(ring(&[(22.0, 2.0), (24.0, 2.0), (24.0, 4.0), (22.0, 4.0), (22.0, 2.0)])),
+        ]);
+        let path = std::env::temp_dir().join(format!("osm-tasks-test-{}.shp", std::process::id()));
+        ShapeWriter::from_path(&path).unwrap().write_shapes(&vec![shape]).unwrap();
+        let polygons = ShapefileSource.read_polygons(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("shx")).unwrap();
+
+        let polygons = polygons.unwrap();
+        assert_eq!(polygons.land.len(), 2);
+        assert!(polygons.land[0].holes.is_empty());
+        assert_eq!(polygons.land[1].outer[0], (20.0, 0.0));
+        assert_eq!(polygons.land[1].holes.len(), 1);
+        assert_eq!(polygons.land[1].holes[0].len(), 5);
+    }
+
+    #[test]
+    fn missing_files_are_reported() {
+        assert!(ShapefileSource.read_polygons("does-not-exist.shp").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs;
+use crate::import::polygon_source::{PolygonSource, close_ring};
+use crate::model::land_polygons::{LandPolygons, PolygonWithHoles};
+
+/// Reads the POLYGON and MULTIPOLYGON geometries from a WKT file. The file may contain
+/// multiple geometries, e.g. one per line.
+pub struct WktSource;
+
+impl PolygonSource for WktSource {
+    fn read_polygons(&self, path: &str) -> Result<LandPolygons, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("Could not read file {}: {:?}", path, e))?;
+        Ok(LandPolygons::new(parse_wkt(&content)?, Vec::new()))
+    }
+}
+
+fn parse_wkt(content: &str) -> Result<Vec<PolygonWithHoles>, String> {
+    let mut polygons = Vec::new();
+    let upper = content.to_uppercase();
+    let mut rest = upper.as_str();
+    while let Some(position) = rest.find("POLYGON") {
+        // MULTIPOLYGON contains one more level of parentheses
+        let ring_depth = if rest[..position].ends_with("MULTI") { 3 } else { 2 };
+        rest = &rest[position + "POLYGON".len()..];
+        if rest.trim_start().starts_with("EMPTY") {
+            continue;
+        }
+        let end = geometry_end(rest).ok_or("unbalanced parentheses in WKT")?;
+        parse_rings(&rest[..end], ring_depth, &mut polygons)?;
+        rest = &rest[end..];
+    }
+    Ok(polygons)
+}
+
+// returns the position after the closing parenthesis of the geometry
+fn geometry_end(geometry: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in geometry.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_rings(geometry: &str, ring_depth: usize, polygons: &mut Vec<PolygonWithHoles>) -> Result<(), String> {
+    let mut depth = 0;
+    let mut ring_start = 0;
+    // the first ring of each polygon is the outer ring
+    let mut is_outer = true;
+    for (i, c) in geometry.char_indices() {
+        match c {
+            '(' => {
+                depth += 1;
+                if depth == ring_depth - 1 {
+                    is_outer = true;
+                } else if depth == ring_depth {
+                    ring_start = i + 1;
+                }
+            }
+            ')' => {
+                if depth == ring_depth {
+                    let mut ring = parse_coordinates(&geometry[ring_start..i])?;
+                    close_ring(&mut ring);
+                    if is_outer {
+                        polygons.push(PolygonWithHoles::new(ring));
+                        is_outer = false;
+                    } else if let Some(polygon) = polygons.last_mut() {
+                        polygon.holes.push(ring);
+                    }
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn parse_coordinates(ring: &str) -> Result<Vec<(f64, f64)>, String> {
+    ring.split(',').map(|position| {
+        let mut values = position.split_whitespace().map(|v| v.parse::<f64>());
+        match (values.next(), values.next()) {
+            (Some(Ok(lon)), Some(Ok(lat))) => Ok((lon, lat)),
+            _ => Err(format!("invalid WKT coordinate: {}", position.trim())),
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_polygons_with_holes() {
+        let polygons = parse_wkt("POLYGON ((0 0, 10 0, 10 10, 0 10, 0 0), (2 2, 4 2, 4 4, 2 2))").unwrap();
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].outer, vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)]);
+        assert_eq!(polygons[0].holes, vec![vec![(2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (2.0, 2.0)]]);
+    }
+
+    #[test]
+    fn parses_multipolygons_and_multiple_geometries() {
+        let content = "MULTIPOLYGON (((0 0, 10 0, 10 10, 0 0), (1 1, 2 1, 2 2)), ((20 20, 30 20, 30 30, 20 20)))\n\
+                       polygon ((-5.5 1.5, -4 1.5, -4 3))\n\
+                       POLYGON EMPTY";
+        let polygons = parse_wkt(content).unwrap();
+        assert_eq!(polygons.len(), 3);
+        assert_eq!(polygons[0].holes.len(), 1);
+        // open rings are closed
+        assert_eq!(polygons[0].holes[0], vec![(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 1.0)]);
+        assert_eq!(polygons[1].outer[0], (20.0, 20.0));
+        assert!(polygons[1].holes.is_empty());
+        assert_eq!(polygons[2].outer, vec![(-5.5, 1.5), (-4.0, 1.5), (-4.0, 3.0), (-5.5, 1.5)]);
+    }
+
+    #[test]
+    fn malformed_input_is_rejected() {
+        assert!(parse_wkt("POLYGON ((0 0, 10 0, 10 10, 0 0)").is_err());
+        assert!(parse_wkt("POLYGON ((0 0, 10 x, 10 10, 0 0))").is_err());
+        assert!(parse_wkt("POLYGON ((0 0, 10, 10 10, 0 0))").is_err());
+        assert!(parse_wkt("POINT (1 2)").unwrap().is_empty());
+    }
+}
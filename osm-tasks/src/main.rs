@@ -8,37 +8,70 @@ extern crate rocket_contrib;
 
 use std::env;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use rocket::State;
+use rocket::{Request, State};
+use rocket::http::ContentType;
+use rocket::response::content::Content;
+use rocket::response::Stream;
+use rocket::response::status::NoContent;
 use rocket_contrib::json::Json;
 use rocket_okapi::{openapi, routes_with_openapi};
 use rocket_okapi::swagger_ui::{make_swagger_ui, SwaggerUIConfig};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 use model::benchmark::CollectedBenchmarks;
+use model::facility::NearestFacility;
+use model::spatial_query::{NearbyNode, SpatialQuery};
+use model::route_job::{RouteJob, RouteResponse};
+use model::build_status::BuildStatus;
+use algorithms::graph_validator::ValidationReport;
 
 use crate::config::Config as LocalConfig;
-use crate::cors::CORS;
+use crate::cors::Cors;
+use crate::formatted_route::{FormattedRoute, RouteFormat};
+use crate::export::rtz_exporter::{LegGeometry, RtzOptions};
+use crate::algorithms::route_simplification::RouteSimplificationOptions;
+use crate::api_error::{ApiError, ApiErrorBody, ApiErrorCode, ApiResult, check_position, check_k};
 use crate::model::grid_graph::Node;
 use crate::navigator_use_case::NavigatorUseCase;
 use crate::persistence::benchmark_repo::BenchmarkRepo;
+use crate::persistence::build_tracker::BuildTracker;
 use crate::persistence::in_memory_benchmark_repo::InMemoryBenchmarkRepo;
 use crate::persistence::in_memory_navigator::InMemoryGraph;
-use crate::persistence::in_memory_routing_repo::{InMemoryRoutingRepo, RouteRequest, ShipRoute};
+use crate::persistence::in_memory_routing_repo::{InMemoryRoutingRepo, RouteRequest};
+use crate::model::ship_route::RoutingAlgorithm;
 use crate::persistence::navigator::Navigator;
 use crate::persistence::routing_repo::RoutingRepo;
+use crate::persistence::event_bus::{EventBus, EventStream, MAX_STREAMS};
 use crate::import::pbf_reader;
+use crate::import::graph_update;
+use crate::export::validation_export;
 use rocket::config::{Config, Environment};
 
 mod persistence;
 mod navigator_use_case;
 mod cors;
+mod api_error;
+mod formatted_route;
 mod config;
 mod export;
 mod algorithms;
 mod model;
 mod import;
 
+// timeouts of the synchronous route endpoint in milliseconds
+const DEFAULT_ROUTE_TIMEOUT_MS: u64 = 5000;
+const MAX_ROUTE_TIMEOUT_MS: u64 = 10000;
+// size of the chunks of the event stream, events are padded to a multiple of it
+const EVENT_CHUNK_SIZE: u64 = 512;
+// maximum number of results of the nearest facility and the node queries
+const MAX_NEAREST_FACILITIES: usize = 100;
+const MAX_NEAREST_NODES: usize = 1000;
+// maximum radius of the radius query in meters
+const MAX_RADIUS: f64 = 500_000.0;
+
 #[derive(Serialize, Deserialize, JsonSchema, Clone)]
 struct Response {
     msg: String
@@ -46,8 +79,17 @@ struct Response {
 
 #[openapi]
 #[post("/buildGraph?<num_nodes>")]
-fn build_graph(num_nodes: usize, navigator_use_case: State<NavigatorUseCase>) {
-    navigator_use_case.build_graph(num_nodes);
+fn build_graph(num_nodes: usize, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<BuildStatus> {
+    if !navigator_use_case.build_graph(num_nodes) {
+        return Err(ApiError::GraphBuildRunning);
+    }
+    Ok(Json(navigator_use_case.get_build_status()))
+}
+
+#[openapi]
+#[get("/graph/status")]
+fn graph_status(navigator_use_case: State<NavigatorUseCase>) -> Json<BuildStatus> {
+    Json(navigator_use_case.get_build_status())
 }
 
 #[openapi]
@@ -56,10 +98,17 @@ fn test(navigator_use_case: State<NavigatorUseCase>) -> Json<u32> {
     Json(navigator_use_case.get_number_nodes())
 }
 
-// returns job id
+// returns job id, the state of the job can be queried at /jobs/<id>. algorithm is dijkstra (default) or
+// thetaStar for any-angle routes
 #[openapi]
-#[get("/route?<lat_start>&<lon_start>&<lat_end>&<lon_end>")]
-fn route(lat_start: f64, lon_start: f64, lat_end: f64, lon_end: f64, navigator_use_case: State<NavigatorUseCase>) -> Option<Json<Option<u32>>> {
+#[get("/route?<lat_start>&<lon_start>&<lat_end>&<lon_end>&<algorithm>")]
+fn route(lat_start: f64, lon_start: f64, lat_end: f64, lon_end: f64, algorithm: Option<String>, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<u32> {
+    check_position("start", lat_start, lon_start)?;
+    check_position("end", lat_end, lon_end)?;
+    let algorithm = match algorithm {
+        Some(algorithm) => algorithm.parse::<RoutingAlgorithm>().map_err(ApiError::InvalidParameter)?,
+        None => RoutingAlgorithm::Dijkstra,
+    };
     let route_request = RouteRequest {
         start: Node {
             lon: lon_start,
@@ -68,39 +117,167 @@ fn route(lat_start: f64, lon_start: f64, lat_end: f64, lon_end: f64, navigator_u
         end: Node {
             lon: lon_end,
             lat: lat_end
-        }
+        },
+        algorithm,
     };
-    let id = navigator_use_case.calculate_route(route_request);
-    if id.is_some() {
-        return Some(Json(id));
-    }
-    return None;
+    Ok(Json(navigator_use_case.calculate_route(route_request)))
 }
 
-// true if job is finished, false if not
+// Calculates the route and returns it directly if it is found within timeout_ms (default 5 s, at most
+// 10 s). Otherwise only the job is returned, the route can then be polled like for GET /route.
+// If too many requests are waiting already, the job is returned without waiting.
+#[openapi]
+#[post("/route?<timeout_ms>", format = "json", data = "<route_request>")]
+fn route_sync(timeout_ms: Option<u64>, route_request: Json<RouteRequest>, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<RouteResponse> {
+    check_position("start", route_request.start.lat, route_request.start.lon)?;
+    check_position("end", route_request.end.lat, route_request.end.lon)?;
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_ROUTE_TIMEOUT_MS).min(MAX_ROUTE_TIMEOUT_MS));
+    Ok(Json(navigator_use_case.calculate_route_and_wait(route_request.into_inner(), timeout)))
+}
+
+// true if job is finished, failed or cancelled, false if not
 #[openapi]
 #[get("/jobStatus?<id>")]
-fn job_status(id: usize, navigator_use_case: State<NavigatorUseCase>) -> Json<bool> {
-    return Json(navigator_use_case.get_route(id).is_some());
+fn job_status(id: u32, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<bool> {
+    let job = navigator_use_case.get_job(id).ok_or(ApiError::JobNotFound(id))?;
+    Ok(Json(job.state.is_terminal()))
 }
 
+// Returns the route of a finished job as JSON (default), GeoJSON Feature, GPX, KML or RTZ file, the error of
+// the job otherwise. leg_geometry (greatCircle or rhumbLine) and speed (planned speed in knots) are only used for RTZ.
+// The route is simplified if simplify is set, if max_waypoints or tolerance (in meters) is set and for GPX, KML and RTZ by default.
+// every query parameter of the route is a handler argument
+#[allow(clippy::too_many_arguments)]
 #[openapi]
-#[get("/jobResult/<id>")]
-fn job_result(id: usize, navigator_use_case: State<NavigatorUseCase>) -> Option<Json<ShipRoute>> {
-    let route = navigator_use_case.get_route(id);
-    if route.is_some() {
-        return Some(Json(route.unwrap()));
+#[get("/jobResult/<id>?<format>&<leg_geometry>&<speed>&<simplify>&<max_waypoints>&<tolerance>")]
+fn job_result(id: u32, format: Option<String>, leg_geometry: Option<String>, speed: Option<f64>, simplify: Option<bool>, max_waypoints: Option<usize>,
+              tolerance: Option<f64>, navigator_use_case: State<NavigatorUseCase>) -> Result<FormattedRoute, ApiError> {
+    let format = match format {
+        Some(format) => format.parse::<RouteFormat>().map_err(ApiError::InvalidParameter)?,
+        None => RouteFormat::Json,
+    };
+    let mut rtz_options = RtzOptions::default();
+    if let Some(leg_geometry) = leg_geometry {
+        rtz_options.leg_geometry = leg_geometry.parse::<LegGeometry>().map_err(ApiError::InvalidParameter)?;
+    }
+    if let Some(speed) = speed {
+        if speed <= 0.0 || !speed.is_finite() {
+            return Err(ApiError::InvalidParameter(format!("speed {} is not a positive number", speed)));
+        }
+        rtz_options.speed_knots = Some(speed);
+    }
+    if let Some(tolerance) = tolerance {
+        if tolerance < 0.0 || !tolerance.is_finite() {
+            return Err(ApiError::InvalidParameter(format!("tolerance {} is not a non-negative number", tolerance)));
+        }
     }
-    return None;
+    if max_waypoints.is_some_and(|max_waypoints| max_waypoints < 2) {
+        return Err(ApiError::InvalidParameter(String::from("max_waypoints must be at least 2")));
+    }
+    let simplification_options = RouteSimplificationOptions { max_waypoints, tolerance };
+    let simplify = simplify.unwrap_or(format.is_simplified_by_default() || max_waypoints.is_some() || tolerance.is_some());
+    // the simplification only checks great circle legs against the land, a rhumb line between the same waypoints can cross land
+    if simplify && format == RouteFormat::Rtz && rtz_options.leg_geometry == LegGeometry::Loxodrome {
+        return Err(ApiError::InvalidParameter(String::from("leg_geometry rhumbLine requires simplify=false, simplified legs are only free of land as great circles")));
+    }
+    let job = navigator_use_case.get_job(id).ok_or(ApiError::JobNotFound(id))?;
+    let mut route = navigator_use_case.get_route(id).ok_or_else(|| ApiError::for_job_without_route(&job))?;
+    // without polygons the stored route is returned unsimplified, its result has no simplification report
+    if simplify {
+        if let Some(simplified) = navigator_use_case.simplify_route(&route, &simplification_options) {
+            route = simplified;
+        }
+    }
+    Ok(FormattedRoute::new(id, route, format, &rtz_options))
+}
+
+// returns the state of the job with the reason of failed jobs and timestamps
+#[openapi]
+#[get("/jobs/<id>")]
+fn get_job(id: u32, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<RouteJob> {
+    navigator_use_case.get_job(id).map(Json).ok_or(ApiError::JobNotFound(id))
+}
+
+// cancels a pending or running job, returns the state of the job after the cancellation
+#[openapi]
+#[delete("/jobs/<id>")]
+fn cancel_job(id: u32, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<RouteJob> {
+    navigator_use_case.cancel_job(id).map(Json).ok_or(ApiError::JobNotFound(id))
+}
+
+// returns the k facilities with the shortest distance by sea
+#[openapi]
+#[get("/nearestFacilities?<lat>&<lon>&<k>")]
+fn nearest_facilities(lat: f64, lon: f64, k: usize, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<Vec<NearestFacility>> {
+    check_position("position", lat, lon)?;
+    check_k(k, MAX_NEAREST_FACILITIES)?;
+    if navigator_use_case.get_number_nodes() == 0 {
+        return Err(ApiError::GraphNotBuilt);
+    }
+    navigator_use_case.find_nearest_facilities(Node { lat, lon }, k).map(Json).ok_or(ApiError::NoFacilities)
 }
 
+// returns the k graph nodes nearest to the position
 #[openapi]
-#[post("/startBenchmark?<nmb_queries>")]
-fn start_benchmark(nmb_queries: usize, navigator_use_case: State<NavigatorUseCase>) -> Json<Response> {
-    navigator_use_case.benchmark(nmb_queries);
-    Json(Response {
+#[get("/nearestNodes?<lat>&<lon>&<k>")]
+fn nearest_nodes(lat: f64, lon: f64, k: usize, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<Vec<NearbyNode>> {
+    check_position("position", lat, lon)?;
+    check_k(k, MAX_NEAREST_NODES)?;
+    navigator_use_case.query_nodes(SpatialQuery::KNearest { position: Node { lat, lon }, k }).map(Json).ok_or(ApiError::GraphNotBuilt)
+}
+
+// returns all graph nodes within the radius (in meters) around the position
+#[openapi]
+#[get("/nodesInRadius?<lat>&<lon>&<radius>")]
+fn nodes_in_radius(lat: f64, lon: f64, radius: f64, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<Vec<NearbyNode>> {
+    check_position("position", lat, lon)?;
+    if !(0.0..=MAX_RADIUS).contains(&radius) {
+        return Err(ApiError::InvalidParameter(format!("radius {} must be between 0 and {} m", radius, MAX_RADIUS)));
+    }
+    navigator_use_case.query_nodes(SpatialQuery::Radius { position: Node { lat, lon }, radius }).map(Json).ok_or(ApiError::GraphNotBuilt)
+}
+
+// returns all graph nodes within the bounding box, lon_min > lon_max describes a box crossing the antimeridian
+#[openapi]
+#[get("/nodesInBbox?<lon_min>&<lat_min>&<lon_max>&<lat_max>")]
+fn nodes_in_bbox(lon_min: f64, lat_min: f64, lon_max: f64, lat_max: f64, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<Vec<NearbyNode>> {
+    check_position("lower corner", lat_min, lon_min)?;
+    check_position("upper corner", lat_max, lon_max)?;
+    let nodes = navigator_use_case.query_nodes(SpatialQuery::BoundingBox { lon_min, lat_min, lon_max, lat_max }).ok_or(ApiError::GraphNotBuilt)?;
+    if nodes.len() > MAX_NEAREST_NODES {
+        return Err(ApiError::InvalidParameter(format!("the bounding box contains {} nodes, at most {} nodes are returned", nodes.len(), MAX_NEAREST_NODES)));
+    }
+    Ok(Json(nodes))
+}
+
+// checks the invariants of the graph and returns edge length, degree and component statistics
+#[openapi]
+#[get("/validateGraph")]
+fn validate_graph(navigator_use_case: State<NavigatorUseCase>) -> ApiResult<ValidationReport> {
+    navigator_use_case.validate_graph().map(Json).ok_or(ApiError::GraphNotBuilt)
+}
+
+// returns the edges and nodes which violate the invariants of the graph as GeoJSON feature collection
+#[openapi]
+#[get("/validateGraph/geojson")]
+fn validation_geojson(navigator_use_case: State<NavigatorUseCase>) -> ApiResult<Value> {
+    navigator_use_case.validation_geojson().map(Json).ok_or(ApiError::GraphNotBuilt)
+}
+
+// theta_star also runs Theta* for each query, it is slow compared to the other algorithms and therefore off by default
+#[openapi]
+#[post("/startBenchmark?<nmb_queries>&<theta_star>")]
+fn start_benchmark(nmb_queries: usize, theta_star: Option<bool>, navigator_use_case: State<NavigatorUseCase>) -> ApiResult<Response> {
+    if nmb_queries == 0 {
+        return Err(ApiError::InvalidParameter(String::from("nmb_queries must be at least 1")));
+    }
+    if navigator_use_case.get_number_nodes() == 0 {
+        return Err(ApiError::GraphNotBuilt);
+    }
+    navigator_use_case.benchmark(nmb_queries, theta_star.unwrap_or(false));
+    Ok(Json(Response {
         msg: "started benchmark".parse().unwrap()
-    })
+    }))
 }
 
 #[openapi]
@@ -114,29 +291,73 @@ fn check_benchmark(navigator_use_case: State<NavigatorUseCase>) -> Json<bool> {
 
 #[openapi]
 #[get("/benchmarkResults")]
-fn benchmark_results(navigator_use_case: State<NavigatorUseCase>) -> Option<Json<CollectedBenchmarks>> {
-    let results = navigator_use_case.get_benchmark_results();
-    if results.is_some() {
-        return Some(Json(results.unwrap()));
-    }
-    None
+fn benchmark_results(navigator_use_case: State<NavigatorUseCase>) -> ApiResult<CollectedBenchmarks> {
+    navigator_use_case.get_benchmark_results().map(Json).ok_or(ApiError::BenchmarkNotRun)
+}
+
+#[catch(400)]
+fn bad_request(request: &Request) -> Json<ApiErrorBody> {
+    Json(ApiErrorBody::new(ApiErrorCode::InvalidRequest, format!("could not parse request {}", request.uri())))
+}
+
+#[catch(404)]
+fn not_found(request: &Request) -> Json<ApiErrorBody> {
+    Json(ApiErrorBody::new(ApiErrorCode::NotFound, format!("no endpoint matches {} {}, check the path and the query parameters", request.method(), request.uri())))
+}
+
+#[catch(422)]
+fn unprocessable_entity(request: &Request) -> Json<ApiErrorBody> {
+    Json(ApiErrorBody::new(ApiErrorCode::InvalidRequest, format!("could not parse the body of request {}", request.uri())))
+}
+
+#[catch(500)]
+fn internal_error(request: &Request) -> Json<ApiErrorBody> {
+    Json(ApiErrorBody::new(ApiErrorCode::Internal, format!("internal error while handling {}", request.uri())))
+}
+
+/// Streams route job state changes and the progress of graph builds and benchmarks as server-sent events.
+/// Not part of the openapi spec, as okapi does not know streamed responses.
+#[get("/events")]
+fn events() -> Result<Content<Stream<EventStream>>, ApiError> {
+    let stream = EventBus::global().open_stream().ok_or(ApiError::TooManyStreams(MAX_STREAMS))?;
+    Ok(Content(ContentType::new("text", "event-stream"), Stream::chunked(stream, EVENT_CHUNK_SIZE)))
 }
 
 #[openapi]
 #[get("/testCH")]
-fn test_ch(navigator_use_case: State<NavigatorUseCase>) {
+fn test_ch(navigator_use_case: State<NavigatorUseCase>) -> NoContent {
     navigator_use_case.test_ch();
+    NoContent
 }
 
 fn main() {
     LocalConfig::init();
     let config = LocalConfig::global();
     println!("Using file {} and a maximum number of {} nodes.", config.coastlines_file(), config.number_of_nodes());
+    if let Some(update_file) = config.update_file().as_ref() {
+        println!("Update graph with {}", update_file);
+        let tracker = BuildTracker::global();
+        tracker.start(config.number_of_nodes());
+        match graph_update::update_graph(config.coastlines_file(), update_file, config.number_of_nodes()) {
+            Ok(()) => tracker.finish(),
+            Err(e) => tracker.fail(e),
+        }
+    }
     if let Some(geojson_path) = config.geojson_export_path().as_ref() {
         println!("Generate and export polygons as geoJSON");
         pbf_reader::read_file_and_export_geojson(config.coastlines_file(), geojson_path);
         println!("Generated geoJSON with polygons");
     }
+    if let Some(kml_path) = config.kml_export_path().as_ref() {
+        println!("Generate and export polygons as KML");
+        pbf_reader::read_file_and_export_kml(config.coastlines_file(), kml_path);
+        println!("Generated KML with polygons");
+    }
+    if config.validation_report_path().is_some() || config.validation_geojson_path().is_some() {
+        println!("Validate graph");
+        validation_export::validate_and_export(config.coastlines_file(), config.number_of_nodes(),
+                                               config.validation_report_path().as_deref(), config.validation_geojson_path().as_deref());
+    }
     rocket()
 }
 
@@ -156,9 +377,11 @@ fn rocket() {
         .finalize();
 
     rocket::custom(config.unwrap())
-        .attach(CORS)
+        .attach(Cors)
+        .register(catchers![bad_request, not_found, unprocessable_entity, internal_error])
         .manage(navigator_use_case)
-        .mount("/", routes_with_openapi![job_status, job_result, route, build_graph, test, start_benchmark, check_benchmark, benchmark_results, test_ch])
+        .mount("/", routes_with_openapi![job_status, job_result, get_job, cancel_job, route, route_sync, build_graph, graph_status, test, start_benchmark, check_benchmark, benchmark_results, test_ch, nearest_facilities, nearest_nodes, nodes_in_radius, nodes_in_bbox, validate_graph, validation_geojson])
+        .mount("/", routes![events])
         .mount(
             "/swagger-ui/",
             make_swagger_ui(&SwaggerUIConfig {
@@ -1,34 +1,38 @@
+use crate::model::weight::Weight;
+
 /// Compact GridGraph which uses adjacency list with primitive types to store the graph
 pub struct AdjacencyArray {
-    edges_and_distances_offsets: Vec<u32>,
-    edges_and_distances: Vec<u32>
+    // index of the first edge of each node, the last entry is the number of edges
+    offsets: Vec<u32>,
+    targets: Vec<u32>,
+    weights: Vec<Weight>,
 }
 
 impl AdjacencyArray {
-    pub fn new(edges_and_distances_offsets: Vec<u32>, edges_and_distances: Vec<u32>) -> AdjacencyArray {
-        AdjacencyArray { edges_and_distances_offsets, edges_and_distances }
+    pub fn new(offsets: Vec<u32>, targets: Vec<u32>, weights: Vec<Weight>) -> AdjacencyArray {
+        AdjacencyArray { offsets, targets, weights }
     }
 
-    pub fn edges_and_distances_offsets(&self) -> &Vec<u32>{
-        &self.edges_and_distances_offsets
+    pub fn offsets(&self) -> &Vec<u32> {
+        &self.offsets
     }
 
-    pub fn edges_and_distances(&self) -> &Vec<u32> {
-        &self.edges_and_distances
+    pub fn targets(&self) -> &Vec<u32> {
+        &self.targets
     }
 
-    pub(crate) fn get_neighbors_of_node_and_distances(&self, node: u32) -> &[u32] {
-        &self.edges_and_distances[(self.edges_and_distances_offsets[node as usize] as usize)..(self.edges_and_distances_offsets[node as usize + 1] as usize)]
+    pub fn weights(&self) -> &Vec<Weight> {
+        &self.weights
     }
 
-    pub(crate) fn get_nodes_count(&self) -> u32 {
-        self.edges_and_distances_offsets.len() as u32 - 1
+    /// targets and weights of the outgoing edges of the node, both slices have the same length
+    pub(crate) fn get_neighbors_of_node_and_distances(&self, node: u32) -> (&[u32], &[Weight]) {
+        let start = self.offsets[node as usize] as usize;
+        let end = self.offsets[node as usize + 1] as usize;
+        (&self.targets[start..end], &self.weights[start..end])
     }
 
-    /*
-    pub(crate) fn remove_neighbors_edges(&mut self, node: u32) {
-        for i in (self.edges_and_distances_offsets[node as usize] as usize)..(self.edges_and_distances_offsets[node as usize + 1] as usize) {
-            self.edges_and_distances[i] = u32::MAX;
-        }
-    } */
+    pub(crate) fn get_nodes_count(&self) -> u32 {
+        self.offsets.len() as u32 - 1
+    }
 }
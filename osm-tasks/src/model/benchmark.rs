@@ -7,25 +7,13 @@ pub struct BenchmarkResult {
     pub(crate) start_node: Node,
     pub(crate) end_node: Node,
     pub(crate) nmb_nodes: usize,
-    pub(crate) distance: u32,
+    // length of the route in meters
+    pub(crate) distance: f64,
     pub(crate) amount_nodes_popped: u32,
+    // in nanoseconds
     pub(crate) time: u64,
 }
 
-impl BenchmarkResult {
-    fn new() -> BenchmarkResult {
-        BenchmarkResult {
-            query_id: 0,
-            start_node: Node::new(),
-            end_node: Node::new(),
-            nmb_nodes: 0,
-            distance: 0,
-            amount_nodes_popped: 0,
-            time: 0
-        }
-    }
-}
-
 #[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct AlgoBenchmark {
     pub(crate) results: Vec<BenchmarkResult>,
@@ -39,10 +27,51 @@ impl AlgoBenchmark {
     }
 }
 
+/// length of the any-angle route compared to the dijkstra route of the same query, lengths in meters
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
+pub struct DistanceImprovement {
+    pub(crate) query_id: usize,
+    pub(crate) dijkstra_distance: f64,
+    pub(crate) theta_star_distance: f64,
+    // dijkstra_distance - theta_star_distance, positive if the any-angle route is shorter
+    pub(crate) improvement: f64,
+    // improvement in percent of dijkstra_distance
+    pub(crate) improvement_percent: f64,
+}
+
+impl DistanceImprovement {
+    pub(crate) fn new(query_id: usize, dijkstra_distance: f64, theta_star_distance: f64) -> DistanceImprovement {
+        let improvement = dijkstra_distance - theta_star_distance;
+        let improvement_percent = if dijkstra_distance > 0.0 { improvement / dijkstra_distance * 100.0 } else { 0.0 };
+        DistanceImprovement { query_id, dijkstra_distance, theta_star_distance, improvement, improvement_percent }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct DistanceComparison {
+    pub(crate) results: Vec<DistanceImprovement>,
+    // 0 if there are no results
+    pub(crate) mean_improvement_percent: f64,
+    pub(crate) max_improvement_percent: f64,
+}
+
+impl DistanceComparison {
+    pub(crate) fn new(results: Vec<DistanceImprovement>) -> DistanceComparison {
+        let percentages: Vec<f64> = results.iter().map(|result| result.improvement_percent).collect();
+        let mean_improvement_percent = if percentages.is_empty() { 0.0 } else { percentages.iter().sum::<f64>() / percentages.len() as f64 };
+        let max_improvement_percent = percentages.into_iter().fold(0.0, f64::max);
+        DistanceComparison { results, mean_improvement_percent, max_improvement_percent }
+    }
+}
+
 #[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct CollectedBenchmarks {
     pub(crate) dijkstra: AlgoBenchmark,
     pub(crate) a_star: AlgoBenchmark,
     pub(crate) bd_dijkstra: AlgoBenchmark,
     pub(crate) ch: AlgoBenchmark,
+    // any-angle routes, they are shorter than the routes of the other algorithms
+    pub(crate) theta_star: AlgoBenchmark,
+    // route lengths of theta star compared to dijkstra
+    pub(crate) theta_star_improvement: DistanceComparison,
 }
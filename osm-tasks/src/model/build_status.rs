@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum BuildState {
+    // no graph was built since the server started
+    Idle,
+    Running,
+    Finished,
+    Failed,
+}
+
+/// phases of a graph build, phases of data that is loaded from disk are skipped
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum BuildPhase {
+    // reading the coastlines or land polygons file
+    ReadingPbf,
+    MergingPolygons,
+    // land test of the grid points, the sphere grid also connects the nodes in this phase
+    ClassifyingNodes,
+    CreatingEdges,
+    ChPreprocessing,
+    Saving,
+}
+
+/// state of the current or last graph build, all timestamps are unix timestamps in milliseconds
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildStatus {
+    pub(crate) state: BuildState,
+    pub(crate) phase: Option<BuildPhase>,
+    // progress of the current phase between 0 and 100
+    pub(crate) percent: u32,
+    // estimated remaining time of the current phase, only known for phases that report their progress
+    pub(crate) eta_ms: Option<u64>,
+    pub(crate) number_nodes: Option<usize>,
+    pub(crate) started_at: Option<u64>,
+    pub(crate) phase_started_at: Option<u64>,
+    pub(crate) finished_at: Option<u64>,
+    pub(crate) error: Option<String>,
+}
+
+impl BuildStatus {
+    pub fn idle() -> BuildStatus {
+        BuildStatus {
+            state: BuildState::Idle,
+            phase: None,
+            percent: 0,
+            eta_ms: None,
+            number_nodes: None,
+            started_at: None,
+            phase_started_at: None,
+            finished_at: None,
+            error: None,
+        }
+    }
+}
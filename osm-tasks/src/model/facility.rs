@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::algorithms::water_snapping::WaterSnapping;
+use crate::model::grid_graph::Node;
+
+/// A port, bunkering station or any other facility a ship might want to reach
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Facility {
+    pub(crate) name: String,
+    // e.g. "port" or "bunkering", taken from the input file
+    pub(crate) kind: String,
+    pub(crate) lat: f64,
+    pub(crate) lon: f64,
+}
+
+impl Facility {
+    pub fn position(&self) -> Node {
+        Node { lat: self.lat, lon: self.lon }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct NearestFacility {
+    pub(crate) facility: Facility,
+    // graph node the facility has been mapped to
+    pub(crate) node: Node,
+    // distance by sea in meters
+    pub(crate) distance: f64,
+}
+
+/// Registered facilities mapped onto the nodes of the current graph
+pub struct FacilityRegistry {
+    facilities: Vec<Facility>,
+    // key is the graph node, value contains the indices of all facilities mapped to this node
+    node_to_facilities: HashMap<u32, Vec<usize>>,
+}
+
+impl FacilityRegistry {
+    /// maps each facility onto the nearest node which can be reached over water, so that a facility
+    /// is not mapped onto the other side of a peninsula
+    pub(crate) fn new(facilities: Vec<Facility>, snapping: &WaterSnapping, nodes: &[Node]) -> FacilityRegistry {
+        let mut node_to_facilities: HashMap<u32, Vec<usize>> = HashMap::new();
+        for (i, facility) in facilities.iter().enumerate() {
+            if let Some((node, _)) = snapping.snap(&facility.position(), nodes) {
+                node_to_facilities.entry(node).or_default().push(i);
+            } else {
+                println!("Could not map facility {} onto the graph", facility.name);
+            }
+        }
+        println!("Mapped {} facilities onto {} graph nodes", facilities.len(), node_to_facilities.len());
+        FacilityRegistry { facilities, node_to_facilities }
+    }
+
+    pub fn empty() -> FacilityRegistry {
+        FacilityRegistry { facilities: Vec::new(), node_to_facilities: HashMap::new() }
+    }
+
+    pub fn get_facility(&self, index: usize) -> &Facility {
+        &self.facilities[index]
+    }
+
+    pub fn node_to_facilities(&self) -> &HashMap<u32, Vec<usize>> {
+        &self.node_to_facilities
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.facilities.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::line_of_sight::LineOfSight;
+    use crate::algorithms::nearest_neighbor::NearestNeighbor;
+    use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+    use crate::model::land_polygons::LandPolygons;
+
+    #[test]
+    fn facilities_are_not_mapped_across_land() {
+        // strip of land between lon 10.0 and 10.02, the facility lies at its east coast
+        let polygons = LandPolygons::from_coastline_rings(vec![vec![(10.0, 49.0), (10.02, 49.0), (10.02, 51.0), (10.0, 51.0), (10.0, 49.0)]]);
+        let polygon_test = NestedPolygonTest::new(&polygons, false);
+        let nodes = vec![Node { lon: 9.999, lat: 50.0 }, Node { lon: 10.045, lat: 50.0 }];
+        let nearest_neighbor = NearestNeighbor::new(&nodes);
+        let facility = Facility { name: String::from("east harbour"), kind: String::from("port"), lat: 50.0, lon: 10.0205 };
+        assert_eq!(nearest_neighbor.find_nearest_neighbor(&facility.position()), Some(0));
+
+        let snapping = WaterSnapping::new(&nearest_neighbor, LineOfSight::new(&polygon_test));
+        let registry = FacilityRegistry::new(vec![facility], &snapping, &nodes);
+        assert_eq!(registry.node_to_facilities().get(&1), Some(&vec![0]));
+        assert!(registry.node_to_facilities().get(&0).is_none());
+    }
+}
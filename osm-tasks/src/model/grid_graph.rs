@@ -7,20 +7,28 @@ use std::f64;
 use serde::{Deserialize, Serialize};
 use rayon::prelude::*;
 use std::time::Instant;
+use std::fmt;
+use std::str::FromStr;
 use crate::config::Config;
 use crate::model::adjacency_array::AdjacencyArray;
-use crate::algorithms::polygon_test::PointInPolygonTest;
+use crate::algorithms::land_test::LandTest;
+use crate::algorithms::connected_components::strongly_connected_components;
+use crate::model::region::Region;
+use crate::model::weight::{Weight, meters_to_weight};
+use crate::model::build_status::BuildPhase;
+use crate::persistence::build_tracker::BuildTracker;
 
 /// Returns the upper bound of the number of nodes in this graph.
 pub fn get_maximum_number_of_nodes() -> usize {
-    Config::global().number_of_nodes() as usize
+    Config::global().number_of_nodes()
 }
 
 #[derive(Clone, Copy, Serialize, Deserialize, JsonSchema)]
 pub struct Edge {
     pub source: u32,
     pub target: u32,
-    pub(crate) distance: u32,
+    // length in the configured weight unit
+    pub(crate) distance: Weight,
 }
 
 impl PartialEq for Edge {
@@ -37,18 +45,46 @@ pub struct Node {
     pub lon: f64,
 }
 
-impl Node {
-    pub(crate) fn new() -> Node {
-        Node {
-            lat: 0.0,
-            lon: 0.0
+impl From<Node> for (f64, f64) {
+    fn from(val: Node) -> Self {
+        (val.lon, val.lat)
+    }
+}
+
+/// tessellation of the sphere used to place the nodes of the graph
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum GridType {
+    // equally distributed nodes in rows of equal latitude
+    Sphere,
+    // vertices of a subdivided icosahedron
+    Icosahedral,
+    // centers of the hexagonal cells of a subdivided icosahedron
+    Hexagonal,
+    // rows of equal latitude inside of a region
+    Regional,
+}
+
+impl FromStr for GridType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sphere" => Ok(GridType::Sphere),
+            "icosahedral" | "icosahedron" => Ok(GridType::Icosahedral),
+            "hexagonal" | "hex" => Ok(GridType::Hexagonal),
+            _ => Err(format!("unknown grid type {}, expected one of: sphere, icosahedral, hexagonal", s)),
         }
     }
 }
 
-impl Into<(f64, f64)> for Node {
-    fn into(self) -> (f64, f64) {
-        (self.lon, self.lat)
+impl fmt::Display for GridType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GridType::Sphere => write!(f, "sphere"),
+            GridType::Icosahedral => write!(f, "icosahedral"),
+            GridType::Hexagonal => write!(f, "hexagonal"),
+            GridType::Regional => write!(f, "regional"),
+        }
     }
 }
 
@@ -60,28 +96,30 @@ pub struct GridGraph {
     pub edges: Vec<Vec<Edge>>,
     // index equals node id
     pub nodes: Vec<Node>,
+    // index equals node id, nodes with different component ids can not reach each other
+    pub component_ids: Vec<u32>,
+    // water nodes of the components removed by assign_components, an update keeps them as water
+    pub removed_nodes: Vec<Node>,
+    // tessellation which was used to generate the graph
+    pub grid_type: GridType,
 }
 
 impl GridGraph {
     // Generates an adjacency array representation of the edges of this graph
     pub fn adjacency_array(&self) -> AdjacencyArray {
-        let mut edges_and_distances = vec![u32::MAX; (self.number_edges * 2) as usize];
-        let mut offsets = vec![u32::MAX; self.number_nodes as usize + 1];
-        let mut counter = 0;
-        let mut prev_offset = 0;
-        self.edges.iter().enumerate().for_each(|(i, edges)| {
+        let mut targets = Vec::with_capacity(self.number_edges as usize);
+        let mut weights = Vec::with_capacity(self.number_edges as usize);
+        let mut offsets = Vec::with_capacity(self.number_nodes as usize + 1);
+        offsets.push(0);
+        for edges in self.edges.iter() {
             // for each node add all edges to adj array
-            for j in 0..edges.len() {
-                edges_and_distances[counter * 2] = edges[j].target;
-                edges_and_distances[counter * 2 + 1] = edges[j].distance;
-                counter += 1;
+            for edge in edges.iter() {
+                targets.push(edge.target);
+                weights.push(edge.distance);
             }
-
-            offsets[i] = prev_offset;
-            prev_offset += self.edges[i].len() as u32 * 2
-        });
-        offsets[self.number_nodes as usize] = prev_offset;
-        return AdjacencyArray::new(offsets, edges_and_distances);
+            offsets.push(targets.len() as u32);
+        }
+        AdjacencyArray::new(offsets, targets, weights)
     }
 
     pub fn remove_node(&mut self, v: u32) {
@@ -94,9 +132,9 @@ impl GridGraph {
         self.number_edges += 1;
     }
 
-    // distance in km, should be sufficient
-    pub fn get_distance(&self, node1: u32, node2: u32) -> u64 {
-        calculate_length_between_points_on_sphere(&self.nodes[node1 as usize], &self.nodes[node2 as usize]) as u64
+    // great circle distance in the configured weight unit, rounded down so that it can be used as a lower bound
+    pub fn get_distance(&self, node1: u32, node2: u32) -> Weight {
+        (calculate_length_between_points_on_sphere(&self.nodes[node1 as usize], &self.nodes[node2 as usize]) * Config::global().weight_unit().units_per_meter()) as Weight
     }
 
     pub fn default() -> GridGraph {
@@ -105,9 +143,66 @@ impl GridGraph {
             number_nodes: 0,
             edges: Vec::new(),
             nodes: Vec::new(),
+            component_ids: Vec::new(),
+            removed_nodes: Vec::new(),
+            grid_type: GridType::Sphere,
+        }
+    }
+
+    /// true if a route between both nodes exists, always true if the components have not been assigned
+    pub fn is_reachable(&self, node1: u32, node2: u32) -> bool {
+        if self.component_ids.len() != self.nodes.len() {
+            return true;
         }
+        self.component_ids[node1 as usize] == self.component_ids[node2 as usize]
     }
-    pub fn new(polygon_test: &PointInPolygonTest, number_nodes: usize) -> GridGraph {
+
+    /// Calculates the strongly connected components of the graph and stores the component id of each node.
+    /// Components with less than min_component_size nodes (e.g. lakes or isolated grid points) are removed from the graph.
+    pub fn assign_components(&mut self, min_component_size: usize) {
+        let component_ids = strongly_connected_components(&self.adjacency_array());
+        let number_components = component_ids.iter().max().map(|max| *max as usize + 1).unwrap_or(0);
+        let mut component_sizes = vec![0; number_components];
+        component_ids.iter().for_each(|id| component_sizes[*id as usize] += 1);
+        println!("Found {} connected components, largest component has {} nodes", number_components, component_sizes.iter().max().unwrap_or(&0));
+
+        // mapping from the old node ids to the new ones
+        let mut new_ids: Vec<Option<u32>> = vec![None; self.nodes.len()];
+        let mut number_kept_nodes = 0;
+        for i in 0..self.nodes.len() {
+            if component_sizes[component_ids[i] as usize] >= min_component_size {
+                new_ids[i] = Some(number_kept_nodes);
+                number_kept_nodes += 1;
+            }
+        }
+        if number_kept_nodes as usize == self.nodes.len() {
+            self.component_ids = component_ids;
+            return;
+        }
+
+        let mut nodes = Vec::with_capacity(number_kept_nodes as usize);
+        let mut edges = Vec::with_capacity(number_kept_nodes as usize);
+        let mut kept_component_ids = Vec::with_capacity(number_kept_nodes as usize);
+        for i in 0..self.nodes.len() {
+            match new_ids[i] {
+                Some(new_id) => {
+                    nodes.push(self.nodes[i]);
+                    kept_component_ids.push(component_ids[i]);
+                    edges.push(self.edges[i].iter().filter_map(|edge| {
+                        new_ids[edge.target as usize].map(|target| Edge { source: new_id, target, distance: edge.distance })
+                    }).collect::<Vec<Edge>>());
+                }
+                None => self.removed_nodes.push(self.nodes[i]),
+            }
+        }
+        println!("Removed {} nodes of components with less than {} nodes", self.nodes.len() - number_kept_nodes as usize, min_component_size);
+        self.number_edges = edges.iter().map(|e| e.len() as i64).sum();
+        self.number_nodes = nodes.len() as i64;
+        self.nodes = nodes;
+        self.edges = edges;
+        self.component_ids = kept_component_ids;
+    }
+    pub fn new(polygon_test: &dyn LandTest, number_nodes: usize) -> GridGraph {
         // mapping from virtual nodes indices (0..NUMBER_NODES) (includes nodes inside of polygons) to the actual nodes of the grid (includes only nodes of the graph)
         let start_time = Instant::now();
         let maximum_number_of_nodes = number_nodes;
@@ -117,9 +212,10 @@ impl GridGraph {
         let mut nodes = vec![Node { lat: 0.0, lon: 0.0 }; maximum_number_of_nodes];
         let mut edges: Vec<Vec<Edge>> = vec![Vec::with_capacity(8); maximum_number_of_nodes];
 
+        BuildTracker::global().phase(BuildPhase::ClassifyingNodes);
         // algorithm taken from here https://www.cmu.edu/biolphys/deserno/pdf/sphere_equi.pdf
         // number of nodes is only very close not equal to NUMBER_NODES
-        let pi = PI as f64;
+        let pi = PI;
         let radius_earth: f64 = 1.0; // in km
         let a: f64 = 4.0 * pi * (radius_earth.powf(2.0) / maximum_number_of_nodes as f64);
         let d: f64 = a.sqrt();
@@ -132,7 +228,9 @@ impl GridGraph {
         // calculated in rad!!
         for m in (0..m_theta).rev() {
             if ((number_virtual_nodes as f64 / maximum_number_of_nodes as f64) * 100.0).ceil() as i32 > ((number_virtual_nodes_before_last_round as f64 / maximum_number_of_nodes as f64) * 100.0).ceil() as i32 {
-                println!("Generating graph: {}%", ((number_virtual_nodes as f64 / maximum_number_of_nodes as f64) * 100.0).ceil() as i32);
+                let percent = ((number_virtual_nodes as f64 / maximum_number_of_nodes as f64) * 100.0).ceil() as i32;
+                println!("Generating graph: {}%", percent);
+                BuildTracker::global().progress(percent as u32);
             }
             let polar = pi * ((m as f64) + 0.5) / (m_theta as f64);
             m_phi = (2.0 * pi * (polar).sin() / d_phi).round() as i32;
@@ -146,14 +244,14 @@ impl GridGraph {
                 // convert rad to degrees and lon = polar - 90; lat = azimuthal-180
                 let lon = azimuthal * (180.0 / pi) - 180.0;
 
-                if polygon_test.check_intersection(*&(lon, lat)) {
+                if polygon_test.check_intersection((lon, lat)) {
                     (n, None)
                 } else {
                     let source_node = Node { lat, lon };
                     (n, Some(source_node))
                 }
             }).collect();
-            let mut last_node_mid_top_node_orientation = NodeOrientation::MID;
+            let mut last_node_mid_top_node_orientation = NodeOrientation::Mid;
             nodes_to_place.into_iter().for_each(|(n, source_node_option)| {
                 let n_float = n as f64;
                 if let Some(source_node) = source_node_option {
@@ -185,25 +283,23 @@ impl GridGraph {
                                             if dst_left > dst_right {
                                                 add_edge(&mut edges, &nodes, number_graph_nodes, &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_before_last_round, &number_azimuth_steps_last_round, virtual_index_top_right_node + 1)]);
                                                 // check if the orientation has flipped from left to right
-                                                if last_node_mid_top_node_orientation == NodeOrientation::LEFT {
+                                                if last_node_mid_top_node_orientation == NodeOrientation::Left {
                                                     // insert extra edges crossed over the gap,so that no gap is produced
                                                     add_extra_edge(&mut edges, &nodes, number_graph_nodes, &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_before_last_round, &number_azimuth_steps_last_round, virtual_index_top_left_node - 1)]);
                                                     if let Some(left_neighbor_index) = &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_at_start_of_this_round, &(m_phi as usize), number_virtual_nodes + (m_phi - 1) as usize)] {
                                                         add_extra_edge(&mut edges, &nodes, *left_neighbor_index as usize, &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_before_last_round, &number_azimuth_steps_last_round, virtual_index_top_right_node)]);
                                                     }
                                                 }
-                                                last_node_mid_top_node_orientation = NodeOrientation::RIGHT;
+                                                last_node_mid_top_node_orientation = NodeOrientation::Right;
                                             } else {
                                                 add_edge(&mut edges, &nodes, number_graph_nodes, &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_before_last_round, &number_azimuth_steps_last_round, virtual_index_top_left_node - 1)]);
-                                                last_node_mid_top_node_orientation = NodeOrientation::LEFT;
+                                                last_node_mid_top_node_orientation = NodeOrientation::Left;
                                             }
-                                        } else {
-                                            if last_node_mid_top_node_orientation == NodeOrientation::LEFT {
-                                                // insert extra edges crossed over the gap,so that no gap is produced
-                                                add_extra_edge(&mut edges, &nodes, number_graph_nodes, &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_before_last_round, &number_azimuth_steps_last_round, virtual_index_top_left_node - 1)]);
-                                                if let Some(left_neighbor_index) = &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_at_start_of_this_round, &(m_phi as usize), number_virtual_nodes + (m_phi - 1) as usize)] {
-                                                    add_extra_edge(&mut edges, &nodes, *left_neighbor_index as usize, &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_before_last_round, &number_azimuth_steps_last_round, virtual_index_top_right_node)]);
-                                                }
+                                        } else if last_node_mid_top_node_orientation == NodeOrientation::Left {
+                                            // insert extra edges crossed over the gap,so that no gap is produced
+                                            add_extra_edge(&mut edges, &nodes, number_graph_nodes, &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_before_last_round, &number_azimuth_steps_last_round, virtual_index_top_left_node - 1)]);
+                                            if let Some(left_neighbor_index) = &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_at_start_of_this_round, &(m_phi as usize), number_virtual_nodes + (m_phi - 1) as usize)] {
+                                                add_extra_edge(&mut edges, &nodes, *left_neighbor_index as usize, &virtual_nodes_to_index[calc_index_modulo(&number_virtual_nodes_before_last_round, &number_azimuth_steps_last_round, virtual_index_top_right_node)]);
                                             }
                                         }
                                     }
@@ -253,47 +349,130 @@ impl GridGraph {
             number_nodes: number_graph_nodes as i64,
             edges,
             nodes,
+            component_ids: vec![],
+            removed_nodes: vec![],
+            grid_type: GridType::Sphere,
+        }
+    }
+
+    /// Generates a graph inside of the region with rows of equal latitude. The rows and the nodes of
+    /// each row are spacing_km apart, each node is connected to its neighbors in the row and to the
+    /// nearest nodes of the row below.
+    pub fn new_regional(polygon_test: &dyn LandTest, region: &Region, spacing_km: f64) -> GridGraph {
+        let start_time = Instant::now();
+        let bbox = region.bounding_box();
+        let lat_step = (spacing_km * 1000.0 / EARTH_RADIUS).to_degrees();
+        let number_rows = ((bbox.lat_max - bbox.lat_min) / lat_step).floor() as usize + 1;
+        // (lat, lon step) of each row, the longitude step grows towards the poles to keep the spacing
+        let rows: Vec<(f64, f64)> = (0..number_rows).map(|row| {
+            let lat = bbox.lat_min + row as f64 * lat_step;
+            let lon_step = (lat_step / lat.to_radians().cos().max(1e-6)).min(360.0);
+            (lat, lon_step)
+        }).collect();
+        let number_columns = |lon_step: f64| ((bbox.lon_max - bbox.lon_min) / lon_step).floor() as usize + 1;
+        println!("Generating regional graph for {} with {} rows and a spacing of {} km", region.name(), number_rows, spacing_km);
+
+        BuildTracker::global().phase(BuildPhase::ClassifyingNodes);
+        // Do point in polygon test in parallel and collect results
+        let classified_rows: Vec<Vec<Option<Node>>> = rows.par_iter().map(|(lat, lon_step)| {
+            (0..number_columns(*lon_step)).map(|column| {
+                let lon = bbox.lon_min + column as f64 * lon_step;
+                if region.contains(lon, *lat) && !polygon_test.check_intersection((lon, *lat)) {
+                    Some(Node { lat: *lat, lon })
+                } else {
+                    None
+                }
+            }).collect()
+        }).collect();
+
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut row_ids: Vec<Vec<Option<u32>>> = Vec::with_capacity(number_rows);
+        for row in classified_rows.iter() {
+            row_ids.push(row.iter().map(|node_option| node_option.map(|node| {
+                nodes.push(node);
+                nodes.len() as u32 - 1
+            })).collect());
+        }
+        BuildTracker::global().phase(BuildPhase::CreatingEdges);
+        let mut edges: Vec<Vec<Edge>> = vec![Vec::with_capacity(8); nodes.len()];
+        for row in 0..number_rows {
+            let lon_step = rows[row].1;
+            for (column, id_option) in row_ids[row].iter().enumerate() {
+                let id = match id_option {
+                    Some(id) => *id as usize,
+                    None => continue,
+                };
+                // edge to the right neighbor in the row
+                if column + 1 < row_ids[row].len() {
+                    add_edge(&mut edges, &nodes, id, &row_ids[row][column + 1]);
+                }
+                if row == 0 {
+                    continue;
+                }
+                // edges to all nodes of the row below which are at most one column step away
+                let lon = bbox.lon_min + column as f64 * lon_step;
+                let lon_step_below = rows[row - 1].1;
+                let tolerance = lon_step.max(lon_step_below) * (1.0 + 1e-9);
+                let first_column = ((lon - tolerance - bbox.lon_min) / lon_step_below).ceil().max(0.0) as usize;
+                let last_column = (((lon + tolerance - bbox.lon_min) / lon_step_below).floor() as usize).min(row_ids[row - 1].len() - 1);
+                for column_below in first_column..=last_column {
+                    add_edge(&mut edges, &nodes, id, &row_ids[row - 1][column_below]);
+                }
+            }
+        }
+        let number_edges: usize = edges.iter().map(|e| e.len()).sum();
+        println!("number placed nodes {}", nodes.len());
+        println!("number edges {}", number_edges);
+        println!("Generated regional graph in {} seconds", start_time.elapsed().as_secs());
+        GridGraph {
+            number_edges: number_edges as i64,
+            number_nodes: nodes.len() as i64,
+            edges,
+            nodes,
+            component_ids: vec![],
+            removed_nodes: vec![],
+            grid_type: GridType::Regional,
         }
     }
 }
 
-fn add_edge(edges: &mut Vec<Vec<Edge>>, nodes: &Vec<Node>, node1_idx: usize, node2_idx_option: &Option<u32>) -> Option<f64> {
+fn add_edge(edges: &mut [Vec<Edge>], nodes: &[Node], node1_idx: usize, node2_idx_option: &Option<u32>) -> Option<f64> {
     if let Some(node2_idx) = node2_idx_option {
         // target node is part of the graph
-        let distance = calculate_length_between_points_on_sphere(&nodes[node1_idx as usize], &nodes[*node2_idx as usize]);
-        edges[node1_idx].push(Edge { source: node1_idx as u32, target: *node2_idx, distance: distance as u32 });
-        edges[*node2_idx as usize].push(Edge { source: *node2_idx, target: node1_idx as u32, distance: distance as u32 });
+        let distance = calculate_length_between_points_on_sphere(&nodes[node1_idx], &nodes[*node2_idx as usize]);
+        edges[node1_idx].push(Edge { source: node1_idx as u32, target: *node2_idx, distance: meters_to_weight(distance) });
+        edges[*node2_idx as usize].push(Edge { source: *node2_idx, target: node1_idx as u32, distance: meters_to_weight(distance) });
         return Some(distance);
     }
-    return None;
+    None
 }
 
 // like add_edge but checks if the edge is already present before inserting the edge
-fn add_extra_edge(edges: &mut Vec<Vec<Edge>>, nodes: &Vec<Node>, node1_idx: usize, node2_idx_option: &Option<u32>) -> Option<f64> {
+fn add_extra_edge(edges: &mut [Vec<Edge>], nodes: &[Node], node1_idx: usize, node2_idx_option: &Option<u32>) -> Option<f64> {
     if let Some(node2_idx) = node2_idx_option {
         // target node is part of the graph
-        let distance = calculate_length_between_points_on_sphere(&nodes[node1_idx as usize], &nodes[*node2_idx as usize]);
+        let distance = calculate_length_between_points_on_sphere(&nodes[node1_idx], &nodes[*node2_idx as usize]);
         // check for duplicates
         if !edges[node1_idx].iter().any(|e| { e.target == *node2_idx }) {
-            edges[node1_idx].push(Edge { source: node1_idx as u32, target: *node2_idx, distance: distance as u32 });
+            edges[node1_idx].push(Edge { source: node1_idx as u32, target: *node2_idx, distance: meters_to_weight(distance) });
         }
         if !edges[*node2_idx as usize].iter().any(|e| { e.target == node1_idx as u32 }) {
-            edges[*node2_idx as usize].push(Edge { source: *node2_idx, target: node1_idx as u32, distance: distance as u32 });
+            edges[*node2_idx as usize].push(Edge { source: *node2_idx, target: node1_idx as u32, distance: meters_to_weight(distance) });
         }
         return Some(distance);
     }
-    return None;
+    None
 }
 
 fn calc_index_modulo(round_start_index: &usize, nodes_in_rounds: &usize, index_usize: usize) -> usize {
     let mut index = index_usize as isize;
-    index = index - *round_start_index as isize;
-    index = index + *nodes_in_rounds as isize;
+    index -= *round_start_index as isize;
+    index += *nodes_in_rounds as isize;
     let new_index = (index % *nodes_in_rounds as isize) + *round_start_index as isize;
     new_index as usize
 }
 
-const EARTH_RADIUS: f64 = 6_378_137_f64; // earth radius in meters
+pub const EARTH_RADIUS: f64 = 6_378_137_f64; // earth radius in meters
 
 fn calculate_length_between_points_on_sphere(node1: &Node, node2: &Node) -> f64 {
     distance(node1.lon, node1.lat, node2.lon, node2.lat)
@@ -309,12 +488,12 @@ pub fn distance(lon1_deg: f64, lat1_deg: f64, lon2_deg: f64, lat2_deg: f64) -> f
     let dlon_sin = ((lon2 - lon1) / 2.0).sin();
     let a = dlat_sin.powf(2.0) + lat1.cos() * lat2.cos() * dlon_sin.powf(2.0);
     let c = 2.0 * (a.sqrt()).asin();
-    return EARTH_RADIUS * c;
+    EARTH_RADIUS * c
 }
 
 #[derive(Clone, PartialEq, Eq, Copy)]
 enum NodeOrientation {
-    LEFT,
-    RIGHT,
-    MID,
+    Left,
+    Right,
+    Mid,
 }
@@ -1,10 +1,11 @@
 use std::fmt;
 use std::cmp::Ordering;
+use crate::model::weight::Weight;
 
 #[derive(Debug)]
 pub struct HeapItem {
     pub(crate) node_id: u32,
-    pub(crate) distance: u32,
+    pub(crate) distance: Weight,
     pub(crate) previous_node: u32,
 }
 
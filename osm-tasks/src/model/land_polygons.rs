@@ -0,0 +1,117 @@
+use serde::{Serialize, Deserialize};
+
+/// polygon with an outer ring and optional holes, all rings are closed lists of (lon, lat) coordinates
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PolygonWithHoles {
+    pub outer: Vec<(f64, f64)>,
+    pub holes: Vec<Vec<(f64, f64)>>,
+}
+
+impl PolygonWithHoles {
+    pub fn new(outer: Vec<(f64, f64)>) -> PolygonWithHoles {
+        PolygonWithHoles { outer, holes: Vec::new() }
+    }
+
+    /// outer ring followed by the holes
+    pub fn rings(&self) -> Vec<&Vec<(f64, f64)>> {
+        let mut rings = Vec::with_capacity(self.holes.len() + 1);
+        rings.push(&self.outer);
+        rings.extend(self.holes.iter());
+        rings
+    }
+
+    pub fn number_of_vertices(&self) -> usize {
+        self.outer.len() + self.holes.iter().map(|hole| hole.len()).sum::<usize>()
+    }
+}
+
+/*
+ Land areas and inland waters (lakes, inland seas). Holes of land polygons are water enclosed by
+ land, holes of inland waters are islands.
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LandPolygons {
+    pub land: Vec<PolygonWithHoles>,
+    pub inland_water: Vec<PolygonWithHoles>,
+}
+
+impl LandPolygons {
+    pub fn new(land: Vec<PolygonWithHoles>, inland_water: Vec<PolygonWithHoles>) -> LandPolygons {
+        LandPolygons { land, inland_water }
+    }
+
+    /// builds the land polygons from merged coastline rings. The coastline is mapped with the land on the
+    /// left side, so counterclockwise rings are land and clockwise rings are water enclosed by land, which
+    /// become holes of the smallest land ring containing them.
+    pub fn from_coastline_rings(rings: Vec<Vec<(f64, f64)>>) -> LandPolygons {
+        let (land_rings, water_rings): (Vec<_>, Vec<_>) = 
+rings.into_iter().partition(|ring| signed_area(ring) >= 0.0);
+        let mut land: Vec<PolygonWithHoles> = land_rings.into_iter().map(PolygonWithHoles::new).collect();
+        let outer_bboxes: Vec<(f64, f64, f64, f64)> = land.iter().map(|polygon| bounding_box(&polygon.outer)).collect();
+        let mut number_unassigned_holes = 0;
+        for hole in water_rings {
+            let hole_bbox = bounding_box(&hole);
+            let container = (0..land.len())
+                .filter(|i| bbox_contains(&outer_bboxes[*i], &hole_bbox) && ring_contains(&land[*i].outer, hole[0]))
+                .min_by(|a, b| bbox_area(&outer_bboxes[*a]).partial_cmp(&bbox_area(&outer_bboxes[*b])).unwrap());
+            match container {
+                Some(i) => land[i].holes.push(hole),
+                None => number_unassigned_holes += 1,
+            }
+        }
+        if number_unassigned_holes > 0 {
+            println!("Dropped {} clockwise coastline rings which are not inside of a land polygon", number_unassigned_holes);
+        }
+        LandPolygons { land, inland_water: Vec::new() }
+    }
+
+    /// outer rings of the land polygons, e.g. for exports which do not need the holes
+    pub fn land_outer_rings(&self) -> Vec<&Vec<(f64, f64)>> {
+        self.land.iter().map(|polygon| &polygon.outer).collect()
+    }
+
+    pub fn number_of_holes(&self) -> usize {
+        self.land.iter().chain(self.inland_water.iter()).map(|polygon| polygon.holes.len()).sum()
+    }
+}
+
+/// area of the ring in square degrees, positive if the ring is counterclockwise
+pub fn signed_area(ring: &[(f64, f64)]) -> f64 {
+    let mut area = 0.0;
+    for i in 0..ring.len() {
+        let (x1, y1) = ring[i];
+        let (x2, y2) = ring[(i + 1) % ring.len()];
+        area += x1 * y2 - x2 * y1;
+    }
+    area / 2.0
+}
+
+/// even-odd ray casting test of a (lon, lat) point against a single ring
+pub fn ring_contains(ring: &[(f64, f64)], (lon, lat): (f64, f64)) -> bool {
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        let (lon_i, lat_i) = ring[i];
+        let (lon_j, lat_j) = ring[j];
+        if (lat_i > lat) != (lat_j > lat) && lon < (lon_j - lon_i) * (lat - lat_i) / (lat_j - lat_i) + lon_i {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// (lon_min, lat_min, lon_max, lat_max) of the ring
+pub fn bounding_box(ring: &[(f64, f64)]) -> (f64, f64, f64, f64) {
+    ring.iter().fold((f64::MAX, f64::MAX, f64::MIN, f64::MIN), |(lon_min, lat_min, lon_max, lat_max), (lon, lat)| {
+        (lon_min.min(*lon), lat_min.min(*lat), lon_max.max(*lon), lat_max.max(*lat))
+    })
+}
+
+fn bbox_contains(outer: &(f64, f64, f64, f64), inner: &(f64, f64, f64, f64)) -> bool {
+    outer.0 <= inner.0 && outer.1 <= inner.1 && outer.2 >= inner.2 && outer.3 >= inner.3
+}
+
+fn bbox_area(bbox: &(f64, f64, f64, f64)) -> f64 {
+    (bbox.2 - bbox.0) * (bbox.3 - bbox.1)
+}
@@ -4,3 +4,13 @@ pub(crate) mod heap_item;
 pub(crate) mod cn_model;
 pub(crate) mod priority_heap_item;
 pub mod benchmark;
+pub(crate) mod facility;
+pub(crate) mod spatial_query;
+pub(crate) mod route_error;
+pub(crate) mod land_polygons;
+pub(crate) mod region;
+pub(crate) mod weight;
+pub(crate) mod route_job;
+pub(crate) mod ship_route;
+pub(crate) mod server_event;
+pub(crate) mod build_status;
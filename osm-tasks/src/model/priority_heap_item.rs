@@ -1,12 +1,13 @@
 use std::fmt;
 use std::cmp::Ordering;
+use crate::model::weight::Weight;
 
 // heap item used for min heaps
 #[derive(Debug)]
 pub struct PriorityHeapItem {
     pub(crate) node_id: u32,
-    pub(crate) distance: u32,
-    pub(crate) priority: u64,
+    pub(crate) distance: Weight,
+    pub(crate) priority: Weight,
     pub(crate) previous_node: u32,
 }
 
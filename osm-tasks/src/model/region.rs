@@ -0,0 +1,143 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use once_cell::sync::OnceCell;
+use crate::config::Config;
+use crate::import::polygon_source::read_polygons;
+use crate::model::land_polygons::{ring_contains, bounding_box};
+
+static REGION: OnceCell<Option<Region>> = OnceCell::new();
+
+/// (lon_min, lat_min, lon_max, lat_max) in degrees. Bounding boxes crossing the antimeridian (lon_min > lon_max)
+/// are not supported for regions, such a region has to be split into one region on each side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub lon_min: f64,
+    pub lat_min: f64,
+    pub lon_max: f64,
+    pub lat_max: f64,
+}
+
+impl BoundingBox {
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        lon >= self.lon_min && lon <= self.lon_max && lat >= self.lat_min && lat <= self.lat_max
+    }
+
+    /// bounding box enlarged by margin degrees on each side, limited to valid coordinates
+    pub fn expand(&self, margin: f64) -> BoundingBox {
+        BoundingBox {
+            lon_min: (self.lon_min - margin).max(-180.0),
+            lat_min: (self.lat_min - margin).max(-90.0),
+            lon_max: (self.lon_max + margin).min(180.0),
+            lat_max: (self.lat_max + margin).min(90.0),
+        }
+    }
+}
+
+/// parses lon_min,lat_min,lon_max,lat_max
+impl FromStr for BoundingBox {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f64> = s.split(',').map(|v| v.trim().parse::<f64>()).collect::<Result<Vec<f64>, _>>()
+            .map_err(|e| format!("Could not parse bounding box {}: {:?}", s, e))?;
+        if values.len() != 4 {
+            return Err(format!("Bounding box {} has to be of the form lon_min,lat_min,lon_max,lat_max", s));
+        }
+        let bbox = BoundingBox { lon_min: values[0], lat_min: values[1], lon_max: values[2], lat_max: values[3] };
+        if bbox.lon_min > bbox.lon_max {
+            return Err(format!("Bounding box {} crosses the antimeridian, which is not supported for regions", s));
+        }
+        if bbox.lon_min == bbox.lon_max || bbox.lat_min >= bbox.lat_max {
+            return Err(format!("Bounding box {} is empty", s));
+        }
+        if bbox.lon_min < -180.0 || bbox.lon_max > 180.0 || bbox.lat_min < -90.0 || bbox.lat_max > 90.0 {
+            return Err(format!("Bounding box {} is outside of the valid coordinates", s));
+        }
+        Ok(bbox)
+    }
+}
+
+/// area for which a regional graph is generated
+#[derive(Clone, Debug)]
+pub enum Region {
+    BoundingBox(BoundingBox),
+    // clip polygon (lon, lat) with its bounding box
+    Polygon(Vec<(f64, f64)>, BoundingBox),
+}
+
+impl Region {
+    /// region configured by --region-bbox or --region-polygon, the clip polygon is read once
+    pub fn configured() -> Option<&'static Region> {
+        REGION.get_or_init(|| {
+            let config = Config::global();
+            if let Some(polygon_file) = config.region_polygon() {
+                let polygons = read_polygons(polygon_file, None)
+                    .unwrap_or_else(|e| panic!("Could not read region polygon: {}", e));
+                let outer = polygons.land.into_iter().next().expect("Region polygon file does not contain a polygon").outer;
+                if outer.windows(2).any(|edge| (edge[0].0 - edge[1].0).abs() > 180.0) {
+                    panic!("Region polygon crosses the antimeridian, which is not supported for regions");
+                }
+                let (lon_min, lat_min, lon_max, lat_max) = bounding_box(&outer);
+                return Some(Region::Polygon(outer, BoundingBox { lon_min, lat_min, lon_max, lat_max }));
+            }
+            config.region_bbox().map(Region::BoundingBox)
+        }).as_ref()
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        match self {
+            Region::BoundingBox(bbox) => *bbox,
+            Region::Polygon(_, bbox) => *bbox,
+        }
+    }
+
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        match self {
+            Region::BoundingBox(bbox) => bbox.contains(lon, lat),
+            Region::Polygon(ring, bbox) => bbox.contains(lon, lat) && ring_contains(ring, (lon, lat)),
+        }
+    }
+
+    /// name used for the graph and polygon files of this region. It contains the grid spacing and for clip
+    /// polygons a hash of the vertices, so that different polygons with the same bounding box get different files.
+    pub fn name(&self) -> String {
+        let bbox = self.bounding_box();
+        let prefix = match self {
+            Region::BoundingBox(_) => String::from("bbox"),
+            Region::Polygon(ring, _) => {
+                let mut hasher = DefaultHasher::new();
+                ring.iter().for_each(|(lon, lat)| (lon.to_bits(), lat.to_bits()).hash(&mut hasher));
+                format!("polygon_{:016x}", hasher.finish())
+            }
+        };
+        format!("{}_{}_{}_{}_{}_{}km", prefix, bbox.lon_min, bbox.lat_min, bbox.lon_max, bbox.lat_max, Config::global().grid_spacing())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounding_boxes_are_parsed() {
+        assert_eq!("9,53,31,66".parse::<BoundingBox>(), Ok(BoundingBox { lon_min: 9.0, lat_min: 53.0, lon_max: 31.0, lat_max: 66.0 }));
+        assert!("170,50,-170,60".parse::<BoundingBox>().unwrap_err().contains("antimeridian"));
+        assert!("9,53,9,66".parse::<BoundingBox>().unwrap_err().contains("empty"));
+        assert!("9,53,31".parse::<BoundingBox>().is_err());
+        assert!("9,53,31,91".parse::<BoundingBox>().is_err());
+    }
+
+    #[test]
+    fn polygon_names_depend_on_the_vertices() {
+        Config::init_for_tests();
+        let bbox = BoundingBox { lon_min: 0.0, lat_min: 0.0, lon_max: 10.0, lat_max: 10.0 };
+        let bowtie = Region::Polygon(vec![(0.0, 0.0), (10.0, 0.0), (0.0, 10.0), (10.0, 10.0), (0.0, 0.0)], bbox);
+        let square = Region::Polygon(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0), (0.0, 0.0)], bbox);
+        assert_ne!(bowtie.name(), square.name());
+        assert_eq!(square.name(), square.clone().name());
+        assert!(square.name().starts_with("polygon_"));
+        assert!(square.name().ends_with(&format!("_{}km", Config::global().grid_spacing())));
+        assert_eq!(Region::BoundingBox(bbox).name(), format!("bbox_0_0_10_10_{}km", Config::global().grid_spacing()));
+    }
+}
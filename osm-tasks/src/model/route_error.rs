@@ -0,0 +1,30 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+/// reasons why a route could not be calculated
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RouteError {
+    NoGraph,
+    SnappingFailed,
+    // start and destination are part of different connected components
+    Unreachable,
+    NoRouteFound,
+    // the job has been cancelled while the route was calculated
+    Cancelled,
+    // the route calculation panicked
+    Internal,
+}
+
+impl fmt::Display for RouteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RouteError::NoGraph => write!(f, "graph has not been built"),
+            RouteError::SnappingFailed => write!(f, "start or destination could not be snapped to a node reachable over water"),
+            RouteError::Unreachable => write!(f, "destination is not reachable from start"),
+            RouteError::NoRouteFound => write!(f, "no route found"),
+            RouteError::Cancelled => write!(f, "route calculation has been cancelled"),
+            RouteError::Internal => write!(f, "route calculation failed because of an internal error"),
+        }
+    }
+}
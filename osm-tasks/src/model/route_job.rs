@@ -0,0 +1,75 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::model::route_error::RouteError;
+use crate::model::ship_route::ShipRoute;
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    // waiting for the navigator, e.g. while another route is calculated
+    Pending,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    /// true if the state of the job will not change anymore
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            JobState::Pending | JobState::Running => false,
+            JobState::Finished | JobState::Failed | JobState::Cancelled => true,
+        }
+    }
+}
+
+/// state of a route calculation, all timestamps are unix timestamps in milliseconds
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteJob {
+    pub(crate) id: u32,
+    pub(crate) state: JobState,
+    // reason why the route could not be calculated, only set for failed jobs
+    pub(crate) error: Option<RouteError>,
+    pub(crate) error_message: Option<String>,
+    pub(crate) created_at: u64,
+    pub(crate) started_at: Option<u64>,
+    pub(crate) finished_at: Option<u64>,
+}
+
+impl RouteJob {
+    pub fn new(id: u32) -> RouteJob {
+        RouteJob { id, state: JobState::Pending, error: None, error_message: None, created_at: now_millis(), started_at: None, finished_at: None }
+    }
+
+    pub fn start(&mut self) {
+        self.state = JobState::Running;
+        self.started_at = Some(now_millis());
+    }
+
+    pub fn finish(&mut self, result: Result<(), RouteError>) {
+        match result {
+            Ok(_) => self.state = JobState::Finished,
+            Err(RouteError::Cancelled) => self.state = JobState::Cancelled,
+            Err(error) => {
+                self.state = JobState::Failed;
+                self.error = Some(error);
+                self.error_message = Some(error.to_string());
+            }
+        }
+        self.finished_at = Some(now_millis());
+    }
+}
+
+/// response of the synchronous route endpoint, the route is only set if the job finished in time
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteResponse {
+    pub(crate) job: RouteJob,
+    pub(crate) route: Option<ShipRoute>,
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as u64).unwrap_or(0)
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use crate::model::route_job::RouteJob;
+use crate::model::build_status::BuildStatus;
+
+/// long running tasks that report their progress
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Task {
+    GraphBuild,
+    CnMetadata,
+    Benchmark,
+}
+
+/// events that are pushed to clients of the event stream
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ServerEvent {
+    /// sent whenever the state of a route job changes
+    #[serde(rename_all = "camelCase")]
+    Job { job: RouteJob },
+    /// sent when a task starts, makes progress or finishes, percent is between 0 and 100
+    #[serde(rename_all = "camelCase")]
+    Progress { task: Task, percent: u32, finished: bool },
+    /// sent when a graph build starts, enters a new phase, finishes or fails
+    #[serde(rename_all = "camelCase")]
+    Build { status: BuildStatus },
+}
+
+impl ServerEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServerEvent::Job { .. } => "job",
+            ServerEvent::Progress { .. } => "progress",
+            ServerEvent::Build { .. } => "build",
+        }
+    }
+
+    /// formats the event as server-sent event with the name of the event and the event as JSON data
+    pub fn to_sse(&self) -> String {
+        let data = serde_json::to_string(self).expect("could not serialize event");
+        format!("event: {}\ndata: {}\n\n", self.name(), data)
+    }
+}
@@ -0,0 +1,103 @@
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::algorithms::route_simplification::RouteSimplificationReport;
+use crate::model::grid_graph::Node;
+use crate::model::weight::{Weight, weight_to_meters, METERS_PER_NAUTICAL_MILE};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ShipRoute {
+    // length of the route in meters
+    distance: f64,
+    // length of the route in nautical miles
+    distance_nm: f64,
+    nodes: Vec<Node>,
+    start_snap: SnapInfo,
+    end_snap: SnapInfo,
+    algorithm: RoutingAlgorithm,
+    // only set for simplified routes
+    simplification: Option<RouteSimplificationReport>,
+}
+
+impl ShipRoute {
+    pub fn new(nodes: Vec<Node>, weight: Weight, start_snap: SnapInfo, end_snap: SnapInfo, algorithm: RoutingAlgorithm) -> ShipRoute {
+        ShipRoute::with_distance(nodes, weight_to_meters(weight), start_snap, end_snap, algorithm)
+    }
+
+    /// route with the distance in meters instead of a weight of the graph
+    pub fn with_distance(nodes: Vec<Node>, distance: f64, start_snap: SnapInfo, end_snap: SnapInfo, algorithm: RoutingAlgorithm) -> ShipRoute {
+        ShipRoute { nodes, distance, distance_nm: distance / METERS_PER_NAUTICAL_MILE, start_snap, end_snap, algorithm, simplification: None }
+    }
+
+    /// returns the route with the waypoints of the simplification, the distance is the length of the simplified legs
+    pub fn simplified(&self, waypoints: Vec<Node>, report: RouteSimplificationReport) -> ShipRoute {
+        ShipRoute {
+            nodes: waypoints,
+            distance: report.distance_after,
+            distance_nm: report.distance_after / METERS_PER_NAUTICAL_MILE,
+            simplification: Some(report),
+            ..self.clone()
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+    pub fn distance_nm(&self) -> f64 {
+        self.distance_nm
+    }
+    pub fn start_snap(&self) -> SnapInfo {
+        self.start_snap
+    }
+    pub fn end_snap(&self) -> SnapInfo {
+        self.end_snap
+    }
+    pub fn algorithm(&self) -> RoutingAlgorithm {
+        self.algorithm
+    }
+    pub fn simplification(&self) -> Option<RouteSimplificationReport> {
+        self.simplification
+    }
+}
+
+/// search algorithm which calculated the route
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RoutingAlgorithm {
+    // shortest path along the grid edges
+    #[default]
+    Dijkstra,
+    // any-angle search, the legs of the route are not restricted to the grid edges
+    ThetaStar,
+}
+
+impl FromStr for RoutingAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dijkstra" => Ok(RoutingAlgorithm::Dijkstra),
+            "thetastar" | "anyangle" => Ok(RoutingAlgorithm::ThetaStar),
+            _ => Err(format!("Unknown routing algorithm {}, expected dijkstra or thetaStar", s)),
+        }
+    }
+}
+
+/// describes how a requested coordinate has been mapped onto the graph
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapInfo {
+    requested: Node,
+    snapped: Node,
+    // distance between the requested and the snapped coordinate in meters
+    distance: u32,
+}
+
+impl SnapInfo {
+    pub fn new(requested: Node, snapped: Node, distance: f64) -> SnapInfo {
+        SnapInfo { requested, snapped, distance: distance as u32 }
+    }
+}
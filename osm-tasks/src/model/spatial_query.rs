@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use crate::model::grid_graph::Node;
+
+/// spatial queries supported by the nearest neighbor index
+pub enum SpatialQuery {
+    KNearest { position: Node, k: usize },
+    // radius in meters
+    Radius { position: Node, radius: f64 },
+    // if lon_min is greater than lon_max, the box crosses the antimeridian
+    BoundingBox { lon_min: f64, lat_min: f64, lon_max: f64, lat_max: f64 },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct NearbyNode {
+    pub(crate) id: u32,
+    pub(crate) node: Node,
+    // distance to the queried position in meters, None for bounding box queries
+    pub(crate) distance: Option<f64>,
+}
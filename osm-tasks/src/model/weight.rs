@@ -0,0 +1,107 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::config::Config;
+
+/// Edge weights and route distances in the configured fixed point unit (e.g. centimeters). Weights of
+/// the edges are rounded instead of truncated, so the error does not accumulate along a route.
+pub type Weight = u64;
+
+/// distance of nodes which have not been reached yet
+pub const INFINITE_WEIGHT: Weight = u64::MAX;
+
+pub const METERS_PER_NAUTICAL_MILE: f64 = 1852.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeightUnit {
+    Meter,
+    Decimeter,
+    Centimeter,
+    Millimeter,
+}
+
+impl WeightUnit {
+    pub fn units_per_meter(&self) -> f64 {
+        match self {
+            WeightUnit::Meter => 1.0,
+            WeightUnit::Decimeter => 10.0,
+            WeightUnit::Centimeter => 100.0,
+            WeightUnit::Millimeter => 1000.0,
+        }
+    }
+}
+
+impl FromStr for WeightUnit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "m" => Ok(WeightUnit::Meter),
+            "dm" => Ok(WeightUnit::Decimeter),
+            "cm" => Ok(WeightUnit::Centimeter),
+            "mm" => Ok(WeightUnit::Millimeter),
+            _ => Err(format!("unknown weight unit {}, expected one of: m, dm, cm, mm", s)),
+        }
+    }
+}
+
+impl fmt::Display for WeightUnit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightUnit::Meter => write!(f, "m"),
+            WeightUnit::Decimeter => write!(f, "dm"),
+            WeightUnit::Centimeter => write!(f, "cm"),
+            WeightUnit::Millimeter => write!(f, "mm"),
+        }
+    }
+}
+
+/// converts a length in meters to a weight in the configured unit
+pub fn meters_to_weight(meters: f64) -> Weight {
+    (meters * Config::global().weight_unit().units_per_meter()).round() as Weight
+}
+
+/// converts a weight in the configured unit to meters
+pub fn weight_to_meters(weight: Weight) -> f64 {
+    weight as f64 / Config::global().weight_unit().units_per_meter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn meters_are_converted_to_the_configured_unit() {
+        // the default unit is centimeters
+        Config::init_for_tests();
+        assert_eq!(meters_to_weight(1.0), 100);
+        // weights are rounded to the nearest unit
+        assert_eq!(meters_to_weight(1.234), 123);
+        assert_eq!(meters_to_weight(1.236), 124);
+        assert_eq!(weight_to_meters(123), 1.23);
+        assert_eq!(weight_to_meters(meters_to_weight(METERS_PER_NAUTICAL_MILE)) / METERS_PER_NAUTICAL_MILE, 1.0);
+        // half of the circumference of the earth
+        assert_eq!(weight_to_meters(meters_to_weight(20_015_086.8)), 20_015_086.8);
+    }
+
+    #[test]
+    fn units_are_parsed_and_scaled() {
+        let units: Vec<WeightUnit> = ["m", "dm", "CM", "mm"].iter().map(|unit| unit.parse().unwrap()).collect();
+        assert_eq!(units, vec![WeightUnit::Meter, WeightUnit::Decimeter, WeightUnit::Centimeter, WeightUnit::Millimeter]);
+        assert_eq!(units.iter().map(|unit| unit.units_per_meter()).collect::<Vec<f64>>(), vec![1.0, 10.0, 100.0, 1000.0]);
+        assert_eq!(units.iter().map(|unit| unit.to_string()).collect::<Vec<String>>(), vec!["m", "dm", "cm", "mm"]);
+        assert!("km".parse::<WeightUnit>().is_err());
+    }
+
+    #[test]
+    fn weights_saturate_at_the_infinite_weight() {
+        Config::init_for_tests();
+        // distances of unreached nodes stay infinite when an edge is added
+        assert_eq!(INFINITE_WEIGHT.saturating_add(meters_to_weight(1000.0)), INFINITE_WEIGHT);
+        assert_eq!((INFINITE_WEIGHT - 10).saturating_add(meters_to_weight(1.0)), INFINITE_WEIGHT);
+        // the sum of two distances of a bidirectional search does not wrap around
+        let half = INFINITE_WEIGHT / 2 + 1;
+        assert!(half.saturating_add(half) > meters_to_weight(40_000_000.0));
+        // lengths which do not fit into a weight are converted to the infinite weight
+        assert_eq!(meters_to_weight(f64::MAX), INFINITE_WEIGHT);
+    }
+}
@@ -1,75 +1,164 @@
 use crate::persistence::navigator::Navigator;
-use std::sync::{Mutex, Arc};
-use crate::persistence::in_memory_routing_repo::{RouteRequest, ShipRoute};
+use std::sync::{Mutex, Arc, Condvar, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use crate::persistence::in_memory_routing_repo::RouteRequest;
+use crate::model::ship_route::ShipRoute;
 use std::thread;
 use crate::persistence::routing_repo::RoutingRepo;
 use crate::persistence::benchmark_repo::BenchmarkRepo;
 use crate::model::benchmark::CollectedBenchmarks;
+use crate::model::facility::NearestFacility;
+use crate::model::grid_graph::Node;
+use crate::model::spatial_query::{NearbyNode, SpatialQuery};
+use crate::model::route_job::{RouteJob, RouteResponse};
+use crate::model::route_error::RouteError;
+use crate::algorithms::graph_validator::ValidationReport;
+use serde_json::Value;
+use crate::algorithms::route_simplification::RouteSimplificationOptions;
+use crate::persistence::event_bus::EventBus;
+use crate::model::server_event::Task;
+use crate::persistence::build_tracker::BuildTracker;
+use crate::persistence::graph_snapshot::GraphSnapshot;
+use crate::model::build_status::BuildStatus;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
 
 pub struct NavigatorUseCase {
     pub navigator: Arc<Mutex<Box<dyn Navigator>>>,
     pub route_repo: Arc<Mutex<Box<dyn RoutingRepo>>>,
-    pub benchmark_repo: Arc<Mutex<Box<dyn BenchmarkRepo>>>
+    pub benchmark_repo: Arc<Mutex<Box<dyn BenchmarkRepo>>>,
+    // notified with the lock of the route repo whenever a job is finished, failed or cancelled
+    job_finished: Arc<Condvar>,
+    // number of requests which wait for their route, each of them blocks a worker of the server
+    waiting_requests: AtomicUsize,
+    // graph of the last successful build, tasks which only read the graph do not lock the navigator
+    snapshot: Arc<RwLock<Option<Arc<GraphSnapshot>>>>,
 }
 
+// maximum number of requests waiting for their route at the same time
+const MAX_WAITING_REQUESTS: usize = 4;
+
 impl NavigatorUseCase {
     pub(crate) fn new(navigator: Arc<Mutex<Box<dyn Navigator>>>, route_repo: Arc<Mutex<Box<dyn RoutingRepo>>>, benchmark_repo: Arc<Mutex<Box<dyn BenchmarkRepo>>>) -> Self {
+        // the graph might have been built on startup already
+        let snapshot = navigator.lock().unwrap().snapshot().map(Arc::new);
         NavigatorUseCase {
             navigator,
             route_repo,
-            benchmark_repo
+            benchmark_repo,
+            job_finished: Arc::new(Condvar::new()),
+            waiting_requests: AtomicUsize::new(0),
+            snapshot: Arc::new(RwLock::new(snapshot)),
         }
     }
 
-    pub(crate) fn build_graph(&self, number_nodes: usize) {
+    /// Builds the graph in the background, returns false if another build is running. Errors and panics
+    /// of the build are reported in the build status, the navigator keeps its previous graph.
+    pub(crate) fn build_graph(&self, number_nodes: usize) -> bool {
+        let tracker = BuildTracker::global();
+        if !tracker.start(number_nodes) {
+            return false;
+        }
         let clone = self.navigator.clone();
+        let snapshot = self.snapshot.clone();
         thread::spawn(move || {
-            clone.lock().expect("could not lock graph").build_graph(number_nodes);
+            EventBus::global().publish_progress(Task::GraphBuild, 0);
+            let result = {
+                let mut nav = clone.lock().expect("could not lock graph");
+                // the guard is not dropped while unwinding, so a panic does not poison the navigator
+                let result = panic::catch_unwind(AssertUnwindSafe(|| nav.build_graph(number_nodes)))
+                    .unwrap_or_else(|e| Err(panic_message(e)));
+                if result.is_ok() {
+                    // replaced while the navigator is locked, so the snapshot is never older than the graph of a finished build
+                    *snapshot.write().unwrap() = nav.snapshot().map(Arc::new);
+                }
+                result
+            };
+            match result {
+                Ok(_) => {
+                    tracker.finish();
+                    EventBus::global().publish_finished(Task::GraphBuild);
+                }
+                Err(e) => tracker.fail(e),
+            }
         });
+        true
     }
 
-    pub(crate) fn calculate_route(&self, route: RouteRequest) -> Option<u32> {
-        if self.get_number_nodes() == 0 {
-            return None;
-        }
+    pub(crate) fn get_build_status(&self) -> BuildStatus {
+        BuildTracker::global().status()
+    }
+
+    /// Creates a job for the route request and calculates the route in the background. Returns the job id.
+    pub(crate) fn calculate_route(&self, route: RouteRequest) -> u32 {
         let clone = self.navigator.clone();
         let repo_clone = self.route_repo.clone();
-        let job_id;
-        {
-            job_id = Some(self.route_repo.lock().unwrap().get_job_id());
-        }
+        let job_finished = self.job_finished.clone();
+        let (job_id, cancelled) = self.route_repo.lock().unwrap().create_job();
+        publish_job(&self.route_repo, job_id);
         thread::spawn(move || {
             let result;
             { // extra scope to unlock navigator after route is calculated
                 let mut nav = clone.lock().unwrap();
-                result = nav.calculate_route(route);
+                // the job might have been cancelled while waiting for the navigator
+                if !repo_clone.lock().unwrap().start_job(job_id) {
+                    return;
+                }
+                publish_job(&repo_clone, job_id);
+                // like a failed build, a panic fails the job without poisoning the navigator
+                result = panic::catch_unwind(AssertUnwindSafe(|| nav.calculate_route(route, &cancelled)))
+                    .unwrap_or_else(|e| {
+                        println!("Route calculation of job {} {}", job_id, panic_message(e));
+                        Err(RouteError::Internal)
+                    });
             }
-            if result.is_some() {
-                // save route
-                repo_clone.lock().unwrap().add_route(result.unwrap());
+            if let Err(e) = &result {
+                println!("Route calculation of job {} failed: {}", job_id, e);
             }
+            repo_clone.lock().unwrap().finish_job(job_id, result);
+            job_finished.notify_all();
+            publish_job(&repo_clone, job_id);
         });
         job_id
     }
 
-    pub(crate) fn benchmark(&self, nmb_queries: usize) {
+    /// Calculates the route with the same job as calculate_route, but waits up to the timeout for the
+    /// job. If the job is not finished in time, the response only contains the state of the job. The
+    /// job is returned without waiting if MAX_WAITING_REQUESTS requests are waiting already.
+    pub(crate) fn calculate_route_and_wait(&self, route: RouteRequest, timeout: Duration) -> RouteResponse {
+        let job_id = self.calculate_route(route);
+        let waiting = WaitingRequest::new(&self.waiting_requests);
+        let timeout = if waiting.is_some() { timeout } else { Duration::from_millis(0) };
+        let deadline = Instant::now() + timeout;
+        let mut repo = self.route_repo.lock().unwrap();
+        loop {
+            let job = repo.get_job(job_id).expect("job has just been created");
+            let now = Instant::now();
+            if job.state.is_terminal() || now >= deadline {
+                return RouteResponse { route: repo.get_route(job_id), job };
+            }
+            repo = self.job_finished.wait_timeout(repo, deadline - now).unwrap().0;
+        }
+    }
+
+    pub(crate) fn benchmark(&self, nmb_queries: usize, include_theta_star: bool) {
         let benchmark_repo = self.benchmark_repo.clone();
         let navigator_clone = self.navigator.clone();
         thread::spawn(move || {
             let result;
             {
-                result = navigator_clone.lock().expect("could not lock graph").run_benchmarks(nmb_queries)
+                EventBus::global().publish_progress(Task::Benchmark, 0);
+                result = navigator_clone.lock().expect("could not lock graph").run_benchmarks(nmb_queries, include_theta_star)
             }
             benchmark_repo.lock().unwrap().set_results(result);
+            EventBus::global().publish_finished(Task::Benchmark);
         });
     }
 
+    /// results of the last finished benchmark, None if no benchmark has finished yet
     pub(crate) fn get_benchmark_results(&self) -> Option<CollectedBenchmarks> {
-        let lock = self.benchmark_repo.lock();
-        if lock.is_ok() {
-            return Some(lock.unwrap().get_results());
-        }
-        None
+        self.benchmark_repo.lock().ok().filter(|repo| repo.is_finished()).map(|repo| repo.get_results())
     }
 
     pub(crate) fn is_benchmark_finished(&self) -> bool {
@@ -84,20 +173,96 @@ impl NavigatorUseCase {
         self.navigator.lock().unwrap().get_number_nodes()
     }
 
-    pub(crate) fn get_route(&self, id: usize) -> Option<ShipRoute> {
+    pub(crate) fn get_route(&self, id: u32) -> Option<ShipRoute> {
+        self.route_repo.lock().unwrap().get_route(id)
+    }
+
+    pub(crate) fn get_job(&self, id: u32) -> Option<RouteJob> {
+        self.route_repo.lock().unwrap().get_job(id)
+    }
+
+    /// cancels the job, a running search is stopped after at most a few thousand nodes
+    pub(crate) fn cancel_job(&self, id: u32) -> Option<RouteJob> {
+        let job = self.route_repo.lock().unwrap().cancel_job(id);
+        self.job_finished.notify_all();
+        if let Some(job) = &job {
+            EventBus::global().publish_job(job.clone());
+        }
+        job
+    }
+
+    pub(crate) fn find_nearest_facilities(&self, position: Node, k: usize) -> Option<Vec<NearestFacility>> {
         if self.get_number_nodes() == 0 {
             return None;
         }
-        let n = self.route_repo.lock().unwrap();
-        n.get_route(id)
+        self.navigator.lock().unwrap().find_nearest_facilities(position, k)
+    }
+
+    pub(crate) fn query_nodes(&self, query: SpatialQuery) -> Option<Vec<NearbyNode>> {
+        if self.get_number_nodes() == 0 {
+            return None;
+        }
+        self.navigator.lock().unwrap().query_nodes(query)
+    }
+
+    /// validates the snapshot of the graph, the navigator is not locked, so a running build does not block the validation
+    pub(crate) fn validate_graph(&self) -> Option<ValidationReport> {
+        Some(self.snapshot()?.validate())
+    }
+
+    pub(crate) fn validation_geojson(&self) -> Option<Value> {
+        Some(self.snapshot()?.validation_geojson())
+    }
+
+    /// simplifies the route on the snapshot of the graph, so that other requests are not blocked meanwhile
+    pub(crate) fn simplify_route(&self, route: &ShipRoute, options: &RouteSimplificationOptions) -> Option<ShipRoute> {
+        self.snapshot()?.simplify_route(route, options)
+    }
+
+    fn snapshot(&self) -> Option<Arc<GraphSnapshot>> {
+        self.snapshot.read().unwrap().clone()
     }
 
     pub(crate) fn test_ch(&self) {
         //self.navigator.lock().unwrap().test_ch();
     }
+}
+
+// counts a waiting request as long as it exists, None if the maximum number of requests is waiting
+struct WaitingRequest<'a> {
+    counter: &'a AtomicUsize,
+}
 
-    #[allow(dead_code)]
-    pub(crate) fn get_job_id(&self) -> u32 {
-        self.route_repo.lock().unwrap().get_job_id()
+impl<'a> WaitingRequest<'a> {
+    fn new(counter: &'a AtomicUsize) -> Option<WaitingRequest<'a>> {
+        if counter.fetch_add(1, Ordering::SeqCst) >= MAX_WAITING_REQUESTS {
+            counter.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(WaitingRequest { counter })
+    }
+}
+
+impl Drop for WaitingRequest<'_> {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// publishes the current state of the job, the repo is unlocked before the event is sent
+fn publish_job(route_repo: &Arc<Mutex<Box<dyn RoutingRepo>>>, job_id: u32) {
+    let job = route_repo.lock().unwrap().get_job(job_id);
+    if let Some(job) = job {
+        EventBus::global().publish_job(job);
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return format!("panicked: {}", message);
+    }
+    if let Some(message) = payload.downcast_ref::<String>() {
+        return format!("panicked: {}", message);
     }
+    String::from("panicked")
 }
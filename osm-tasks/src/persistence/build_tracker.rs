@@ -0,0 +1,100 @@
+use std::cmp::min;
+use std::sync::Mutex;
+use once_cell::sync::OnceCell;
+use crate::model::build_status::{BuildPhase, BuildState, BuildStatus};
+use crate::model::route_job::now_millis;
+use crate::model::server_event::{ServerEvent, Task};
+use crate::persistence::event_bus::EventBus;
+
+static INSTANCE: OnceCell<BuildTracker> = OnceCell::new();
+
+/// Keeps the status of the current or last graph build. The tracker is global like the event bus, so that
+/// the import, the graph generation and the contraction can report their phase and progress.
+pub struct BuildTracker {
+    status: Mutex<BuildStatus>,
+}
+
+impl BuildTracker {
+    pub fn global() -> &'static BuildTracker {
+        INSTANCE.get_or_init(|| BuildTracker { status: Mutex::new(BuildStatus::idle()) })
+    }
+
+    /// returns the status with the estimated remaining time of the current phase
+    pub fn status(&self) -> BuildStatus {
+        let mut status = self.status.lock().unwrap().clone();
+        if status.state == BuildState::Running && status.percent > 0 && status.percent < 100 {
+            if let Some(phase_started_at) = status.phase_started_at {
+                let elapsed = now_millis().saturating_sub(phase_started_at);
+                status.eta_ms = Some(elapsed * (100 - status.percent) as u64 / status.percent as u64);
+            }
+        }
+        status
+    }
+
+    /// returns false if another build is running
+    pub fn start(&self, number_nodes: usize) -> bool {
+        {
+            let mut status = self.status.lock().unwrap();
+            if status.state == BuildState::Running {
+                return false;
+            }
+            *status = BuildStatus::idle();
+            status.state = BuildState::Running;
+            status.number_nodes = Some(number_nodes);
+            status.started_at = Some(now_millis());
+        }
+        self.publish_status();
+        true
+    }
+
+    pub fn phase(&self, phase: BuildPhase) {
+        {
+            let mut status = self.status.lock().unwrap();
+            status.phase = Some(phase);
+            status.percent = 0;
+            status.phase_started_at = Some(now_millis());
+        }
+        self.publish_status();
+    }
+
+    /// sets the progress of the current phase, percent is capped at 100
+    pub fn progress(&self, percent: u32) {
+        let phase = {
+            let mut status = self.status.lock().unwrap();
+            status.percent = min(percent, 100);
+            status.phase
+        };
+        let task = match phase {
+            Some(BuildPhase::ChPreprocessing) => Task::CnMetadata,
+            _ => Task::GraphBuild,
+        };
+        EventBus::global().publish_progress(task, percent);
+    }
+
+    pub fn finish(&self) {
+        {
+            let mut status = self.status.lock().unwrap();
+            status.state = BuildState::Finished;
+            status.phase = None;
+            status.percent = 100;
+            status.finished_at = Some(now_millis());
+        }
+        self.publish_status();
+    }
+
+    /// marks the build as failed, the phase of the status is the phase in which the build failed
+    pub fn fail(&self, error: String) {
+        println!("Graph build failed: {}", error);
+        {
+            let mut status = self.status.lock().unwrap();
+            status.state = BuildState::Failed;
+            status.error = Some(error);
+            status.finished_at = Some(now_millis());
+        }
+        self.publish_status();
+    }
+
+    fn publish_status(&self) {
+        EventBus::global().publish(ServerEvent::Build { status: self.status() });
+    }
+}
@@ -0,0 +1,130 @@
+use std::cmp::min;
+use std::io::{self, Read};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+use once_cell::sync::OnceCell;
+use crate::model::server_event::{ServerEvent, Task};
+use crate::model::route_job::RouteJob;
+
+static INSTANCE: OnceCell<EventBus> = OnceCell::new();
+
+// a comment is sent if there was no event for this long, so that dead connections are detected
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+// a stream without events is closed after this time to free its worker, EventSource clients reconnect on their own
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+// every open stream occupies a worker thread of Rocket, so the number of streams is limited
+pub const MAX_STREAMS: usize = 8;
+
+/// Distributes server events to all subscribers. The bus is global, so that the graph generation and
+/// the contraction can report their progress without knowing about the server.
+pub struct EventBus {
+    subscribers: Mutex<Vec<Sender<ServerEvent>>>,
+    open_streams: AtomicUsize,
+}
+
+impl EventBus {
+    pub fn global() -> &'static EventBus {
+        INSTANCE.get_or_init(|| EventBus { subscribers: Mutex::new(vec![]), open_streams: AtomicUsize::new(0) })
+    }
+
+    pub fn subscribe(&self) -> Receiver<ServerEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// opens a stream of the events published from now on, None if MAX_STREAMS streams are open already
+    pub fn open_stream(&'static self) -> Option<EventStream> {
+        if self.open_streams.fetch_add(1, Ordering::SeqCst) >= MAX_STREAMS {
+            self.open_streams.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(EventStream { receiver: self.subscribe(), pending: vec![], position: 0, last_event: Instant::now(), open_streams: &self.open_streams })
+    }
+
+    /// sends the event to all subscribers, subscribers that are gone are removed
+    pub fn publish(&self, event: ServerEvent) {
+        self.subscribers.lock().unwrap().retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    pub fn publish_job(&self, job: RouteJob) {
+        self.publish(ServerEvent::Job { job });
+    }
+
+    pub fn publish_progress(&self, task: Task, percent: u32) {
+        self.publish(ServerEvent::Progress { task, percent: min(percent, 100), finished: false });
+    }
+
+    pub fn publish_finished(&self, task: Task) {
+        self.publish(ServerEvent::Progress { task, percent: 100, finished: true });
+    }
+}
+
+/// Reads the events of a subscription as text/event-stream. Rocket writes a streamed body in chunks
+/// and only sends a chunk once it is full, so every event is padded with a comment up to the end of
+/// the buffer that is passed to read. The stream ends after IDLE_TIMEOUT without events.
+pub struct EventStream {
+    receiver: Receiver<ServerEvent>,
+    pending: Vec<u8>,
+    position: usize,
+    last_event: Instant,
+    open_streams: &'static AtomicUsize,
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.open_streams.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.position >= self.pending.len() {
+            self.pending = match self.receiver.recv_timeout(KEEP_ALIVE_INTERVAL) {
+                Ok(event) => {
+                    self.last_event = Instant::now();
+                    event.to_sse().into_bytes()
+                }
+                Err(RecvTimeoutError::Timeout) if self.last_event.elapsed() >= IDLE_TIMEOUT => return Ok(0),
+                Err(RecvTimeoutError::Timeout) => b": keep-alive\n\n".to_vec(),
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.position = 0;
+        }
+        let length = min(buf.len(), self.pending.len() - self.position);
+        buf[..length].copy_from_slice(&self.pending[self.position..self.position + length]);
+        self.position += length;
+        if length == buf.len() {
+            return Ok(length);
+        }
+        // pad the rest of the buffer with a comment line, a single byte becomes an empty line
+        let padding = &mut buf[length..];
+        let last = padding.len() - 1;
+        for byte in padding.iter_mut() {
+            *byte = b' ';
+        }
+        padding[0] = b':';
+        padding[last] = b'\n';
+        Ok(buf.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn number_of_open_streams_is_limited() {
+        let bus: &'static EventBus = Box::leak(Box::new(EventBus { subscribers: Mutex::new(vec![]), open_streams: AtomicUsize::new(0) }));
+        let mut streams: Vec<EventStream> = (0..MAX_STREAMS).map(|_| bus.open_stream().unwrap()).collect();
+        assert!(bus.open_stream().is_none());
+        // closing a stream frees its slot
+        streams.pop();
+        assert!(bus.open_stream().is_some());
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use std::time::Instant;
+use serde_json::Value;
+use crate::algorithms::graph_validator::{ValidationReport, validate_graph, expected_spacing};
+use crate::algorithms::land_test::LandTest;
+use crate::algorithms::line_of_sight::LineOfSight;
+use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+use crate::config::Config;
+use crate::algorithms::route_simplification::{simplify_route, RouteSimplificationOptions};
+use crate::model::grid_graph::GridGraph;
+use crate::model::ship_route::ShipRoute;
+
+/// Shared reference to the graph and the polygons of the navigator. Long running tasks which only read
+/// the graph work on a snapshot, so that the navigator is not locked while they run. A graph build
+/// replaces the graph of the navigator, the snapshot keeps the old one.
+#[derive(Clone)]
+pub struct GraphSnapshot {
+    pub graph: Arc<GridGraph>,
+    pub polygon_test: Option<Arc<NestedPolygonTest>>,
+    // number of grid points the graph was generated from
+    pub number_nodes: usize,
+}
+
+impl GraphSnapshot {
+    pub fn validate(&self) -> ValidationReport {
+        let start_time = Instant::now();
+        let land_test = self.polygon_test.as_ref().map(|polygon_test| polygon_test.as_ref() as &dyn LandTest);
+        let report = validate_graph(&self.graph, land_test, expected_spacing(self.graph.grid_type, self.number_nodes, Config::global().grid_spacing()));
+        println!("Validated graph in {} ms: {}", start_time.elapsed().as_millis(), report);
+        report
+    }
+
+    /// GeoJSON of the edges and nodes which violate the invariants of the graph
+    pub fn validation_geojson(&self) -> Value {
+        self.validate().offending_elements_geojson(&self.graph)
+    }
+
+    /// removes waypoints of the route without crossing land, None if no polygons are loaded
+    pub fn simplify_route(&self, route: &ShipRoute, options: &RouteSimplificationOptions) -> Option<ShipRoute> {
+        let line_of_sight = LineOfSight::new(self.polygon_test.as_ref()?);
+        let start_time = Instant::now();
+        let (waypoints, report) = simplify_route(route.nodes(), &line_of_sight, options);
+        println!("Simplified route in {} ms: {}", start_time.elapsed().as_millis(), report);
+        Some(route.simplified(waypoints, report))
+    }
+}
@@ -1,4 +1,4 @@
-use crate::model::benchmark::{CollectedBenchmarks, AlgoBenchmark};
+use crate::model::benchmark::{CollectedBenchmarks, AlgoBenchmark, DistanceComparison};
 use crate::persistence::benchmark_repo::BenchmarkRepo;
 
 pub(crate) struct InMemoryBenchmarkRepo {
@@ -13,7 +13,9 @@ impl BenchmarkRepo for InMemoryBenchmarkRepo {
                 dijkstra: AlgoBenchmark::new(),
                 a_star: AlgoBenchmark::new(),
                 bd_dijkstra: AlgoBenchmark::new(),
-                ch: AlgoBenchmark::new()
+                ch: AlgoBenchmark::new(),
+                theta_star: AlgoBenchmark::new(),
+                theta_star_improvement: DistanceComparison::new(vec![]),
             },
             finished: false
         }
@@ -1,26 +1,53 @@
 use crate::persistence::navigator::Navigator;
-use crate::persistence::in_memory_routing_repo::{ShipRoute, RouteRequest};
+use crate::persistence::in_memory_routing_repo::RouteRequest;
+use crate::model::ship_route::{ShipRoute, SnapInfo, RoutingAlgorithm};
 use crate::config::Config;
 use rand::seq::{SliceRandom};
 use std::time::Instant;
-use crate::model::benchmark::{AlgoBenchmark, BenchmarkResult, CollectedBenchmarks};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::model::benchmark::{AlgoBenchmark, BenchmarkResult, CollectedBenchmarks, DistanceComparison, DistanceImprovement};
 use std::collections::HashMap;
 use crate::algorithms::dijkstra::Dijkstra;
 use crate::algorithms::nearest_neighbor::NearestNeighbor;
 use crate::model::grid_graph::{GridGraph, Node};
+use crate::model::adjacency_array::AdjacencyArray;
 use crate::algorithms::a_star::AStar;
 use std::convert::TryFrom;
 use termion::color;
 use crate::algorithms::bd_dijkstra::BdDijkstra;
-use crate::import::pbf_reader::{read_or_create_graph, read_or_create_cn_metadata};
+use crate::import::pbf_reader::{read_or_create_graph, read_or_create_cn_metadata, read_or_create_polygons, create_polygon_test};
 use crate::model::cn_model::CNMetadata;
 use crate::algorithms::cn_search::CNBdDijkstra;
+use crate::model::facility::{FacilityRegistry, NearestFacility};
+use crate::import::facility_reader::read_facilities;
+use crate::algorithms::facility_search::FacilitySearch;
+use crate::model::spatial_query::{NearbyNode, SpatialQuery};
+use crate::algorithms::nested_polygon_test::NestedPolygonTest;
+use crate::algorithms::line_of_sight::LineOfSight;
+use crate::algorithms::water_snapping::WaterSnapping;
+use crate::model::route_error::RouteError;
+use crate::model::weight::weight_to_meters;
+use crate::persistence::graph_snapshot::GraphSnapshot;
+use crate::persistence::event_bus::EventBus;
+use crate::persistence::build_tracker::BuildTracker;
+use crate::algorithms::theta_star::{ThetaStar, LineOfSightCache};
+use crate::model::server_event::Task;
 
 pub(crate) struct InMemoryGraph {
-    graph: GridGraph,
+    graph: Arc<GridGraph>,
     cn_metadata: CNMetadata,
+    // edges of the graph, shared by the dijkstra instances and the facility search
+    adjacency_array: Option<Arc<AdjacencyArray>>,
     dijkstra: Option<Dijkstra>,
     nearest_neighbor: Option<NearestNeighbor>,
+    facility_registry: FacilityRegistry,
+    // land polygons, used to snap coordinates to nodes reachable over water
+    polygon_test: Option<Arc<NestedPolygonTest>>,
+    // line of sight tests of Theta*, only valid for the current graph
+    line_of_sight_cache: LineOfSightCache,
+    // number of grid points the graph was generated from
+    number_nodes: usize,
 }
 
 impl Navigator for InMemoryGraph {
@@ -32,59 +59,100 @@ impl Navigator for InMemoryGraph {
             get_shortcut: HashMap::new(),
         };
 
+        let mut navigator = InMemoryGraph {
+            graph: Arc::new(GridGraph::default()),
+            adjacency_array: None,
+            dijkstra: None,
+            cn_metadata,
+            nearest_neighbor: None,
+            facility_registry: FacilityRegistry::empty(),
+            polygon_test: None,
+            line_of_sight_cache: LineOfSightCache::default(),
+            number_nodes: config.number_of_nodes(),
+        };
         if config.build_graph_on_startup() {
-            let graph = read_or_create_graph(config.coastlines_file(), config.force_rebuild_graph(), config.number_of_nodes());
-            let dijkstra = Some(Dijkstra::new(graph.adjacency_array(), graph.nodes.len() as u32 - 1));
-            let nearest_neighbor = Some(NearestNeighbor::new(&graph.nodes));
-            let cn_metadata = read_or_create_cn_metadata(config.coastlines_file(), config.force_rebuild_graph(), config.number_of_nodes(), &graph);
-            InMemoryGraph {
-                graph,
-                cn_metadata,
-                dijkstra,
-                nearest_neighbor,
-            }
-        } else {
-            InMemoryGraph {
-                graph: GridGraph::default(),
-                dijkstra: None,
-                cn_metadata,
-                nearest_neighbor: None,
+            let tracker = BuildTracker::global();
+            tracker.start(config.number_of_nodes());
+            match navigator.build_graph(config.number_of_nodes()) {
+                Ok(_) => tracker.finish(),
+                Err(e) => tracker.fail(e),
             }
-
         }
+        navigator
     }
 
-    fn build_graph(&mut self, number_nodes: usize) {
+    fn build_graph(&mut self, number_nodes: usize) -> Result<(), String> {
         let config = Config::global();
-        self.graph = read_or_create_graph(config.coastlines_file(), config.force_rebuild_graph(), number_nodes);
-        self.dijkstra = Some(Dijkstra::new(self.graph.adjacency_array(), (number_nodes - 1) as u32));
-        self.nearest_neighbor = Some(NearestNeighbor::new(&self.graph.nodes));
+        let polygon_test = load_polygon_test()?;
+        let graph = read_or_create_graph(config.coastlines_file(), config.force_rebuild_graph(), number_nodes, &polygon_test);
+        if graph.nodes.is_empty() {
+            return Err(String::from("The graph has no nodes"));
+        }
+        let cn_metadata = read_or_create_cn_metadata(config.coastlines_file(), config.force_rebuild_graph(), number_nodes, &graph);
 
-        self.cn_metadata = read_or_create_cn_metadata(config.coastlines_file(), config.force_rebuild_graph(), number_nodes, &self.graph);
+        let adjacency_array = Arc::new(graph.adjacency_array());
+        let dijkstra = Dijkstra::new(adjacency_array.clone(), graph.nodes.len() as u32 - 1);
+        let nearest_neighbor = NearestNeighbor::new(&graph.nodes);
+        let snapping = WaterSnapping::new(&nearest_neighbor, LineOfSight::new(&polygon_test));
+        let facility_registry = load_facility_registry(&snapping, &graph.nodes);
+
+        // the old graph is only replaced after everything of the new graph was built successfully
+        self.adjacency_array = Some(adjacency_array);
+        self.dijkstra = Some(dijkstra);
+        self.nearest_neighbor = Some(nearest_neighbor);
+        self.facility_registry = facility_registry;
+        self.graph = Arc::new(graph);
+        self.cn_metadata = cn_metadata;
+        self.polygon_test = Some(Arc::new(polygon_test));
+        self.line_of_sight_cache.clear();
+        self.number_nodes = number_nodes;
+        Ok(())
     }
 
-    fn calculate_route(&mut self, route_request: RouteRequest) -> Option<ShipRoute> {
-        if let Some(dijkstra) = self.dijkstra.as_mut() {
-            let start_node = self.nearest_neighbor.as_ref().unwrap().find_nearest_neighbor(&route_request.start());
-            let end_node = self.nearest_neighbor.as_ref().unwrap().find_nearest_neighbor(&route_request.end());
-            let start_time = Instant::now();
-            dijkstra.change_source_node(start_node);
-            if let Some(route_and_distance) = dijkstra.find_route(end_node) {
-                let route: Vec<u32> = route_and_distance.0;
-                let distance = route_and_distance.1;
-                let nodes_route: Vec<Node> = route.into_iter().map(|i| { self.graph.nodes[i as usize] }).collect();
-                println!("Calculated route from {} to {} with distance {} in {} ns, or {} ms", start_node, end_node, distance, start_time.elapsed().as_nanos(), start_time.elapsed().as_millis());
-                return Some(ShipRoute::new(nodes_route, distance));
-            } else {
-                println!("Could not calculate route. Dijkstra is not initialized");
+    fn calculate_route(&mut self, route_request: RouteRequest, cancelled: &AtomicBool) -> Result<ShipRoute, RouteError> {
+        let dijkstra = self.dijkstra.as_mut().ok_or(RouteError::NoGraph)?;
+        let nearest_neighbor = self.nearest_neighbor.as_ref().ok_or(RouteError::NoGraph)?;
+        let polygon_test = self.polygon_test.as_ref().ok_or(RouteError::NoGraph)?;
+        let snapping = WaterSnapping::new(nearest_neighbor, LineOfSight::new(polygon_test));
+        let ((start_node, start_snap_distance), (end_node, end_snap_distance)) = match (snapping.snap(&route_request.start(), &self.graph.nodes), snapping.snap(&route_request.end(), &self.graph.nodes)) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                println!("Could not calculate route. Start or destination could not be snapped to the graph");
+                return Err(RouteError::SnappingFailed);
             }
+        };
+        if !self.graph.is_reachable(start_node, end_node) {
+            println!("Could not calculate route. Node {} and {} are part of different components", start_node, end_node);
+            return Err(RouteError::Unreachable);
         }
-        None
+        let start_time = Instant::now();
+        let algorithm = route_request.algorithm();
+        let route_and_distance = match algorithm {
+            RoutingAlgorithm::Dijkstra => {
+                dijkstra.change_source_node(start_node);
+                dijkstra.find_route_cancellable(end_node, cancelled)
+            }
+            RoutingAlgorithm::ThetaStar => ThetaStar::new(&self.graph, LineOfSight::new(polygon_test), &mut self.line_of_sight_cache, start_node).find_route_cancellable(end_node, cancelled),
+        };
+        if let Some(route_and_distance) = route_and_distance {
+            let route: Vec<u32> = route_and_distance.0;
+            let distance = route_and_distance.1;
+            let nodes_route: Vec<Node> = route.into_iter().map(|i| { self.graph.nodes[i as usize] }).collect();
+            println!("Calculated route with {:?} from {} to {} with distance {} m in {} ns, or {} ms", algorithm, start_node, end_node, weight_to_meters(distance), start_time.elapsed().as_nanos(), start_time.elapsed().as_millis());
+            let start_snap = SnapInfo::new(route_request.start(), self.graph.nodes[start_node as usize], start_snap_distance);
+            let end_snap = SnapInfo::new(route_request.end(), self.graph.nodes[end_node as usize], end_snap_distance);
+            return Ok(ShipRoute::new(nodes_route, distance, start_snap, end_snap, algorithm));
+        }
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(RouteError::Cancelled);
+        }
+        println!("Could not calculate route from {} to {}", start_node, end_node);
+        Err(RouteError::NoRouteFound)
     }
 
     fn benchmark_dijkstra(&mut self, start_node: u32, end_node: u32, query_id: usize) -> Option<BenchmarkResult> {
         // completely re initialize dijkstra to make sure nothing is cached or anything similar
-        let mut dijkstra = Dijkstra::new(self.graph.adjacency_array(), start_node);
+        let mut dijkstra = Dijkstra::new(self.adjacency_array.clone()?, start_node);
         let start_time = Instant::now();
         dijkstra.change_source_node(start_node);
         if let Some(route_and_distance) = dijkstra.find_route(end_node) {
@@ -97,7 +165,7 @@ impl Navigator for InMemoryGraph {
                 start_node: self.graph.nodes[start_node as usize],
                 end_node: self.graph.nodes[end_node as usize],
                 nmb_nodes: nodes_route.len(),
-                distance,
+                distance: weight_to_meters(distance),
                 time: u64::try_from(time).expect("time too big"),
                 query_id,
                 amount_nodes_popped: route_and_distance.2,
@@ -121,7 +189,7 @@ impl Navigator for InMemoryGraph {
                 start_node: self.graph.nodes[start_node as usize],
                 end_node: self.graph.nodes[end_node as usize],
                 nmb_nodes: nodes_route.len(),
-                distance,
+                distance: weight_to_meters(distance),
                 time: u64::try_from(time).expect("time too big"),
                 query_id,
                 amount_nodes_popped: route_and_distance.2,
@@ -145,7 +213,7 @@ impl Navigator for InMemoryGraph {
                 start_node: self.graph.nodes[start_node as usize],
                 end_node: self.graph.nodes[end_node as usize],
                 nmb_nodes: nodes_route.len(),
-                distance,
+                distance: weight_to_meters(distance),
                 time: u64::try_from(time).expect("time too big"),
                 query_id,
                 amount_nodes_popped: route_and_distance.2,
@@ -173,7 +241,7 @@ impl Navigator for InMemoryGraph {
                 start_node: self.graph.nodes[start_node as usize],
                 end_node: self.graph.nodes[end_node as usize],
                 nmb_nodes: nodes_route.len(),
-                distance,
+                distance: weight_to_meters(distance),
                 time: u64::try_from(time).expect("time too big"),
                 query_id,
                 amount_nodes_popped: route_and_distance.2,
@@ -182,30 +250,67 @@ impl Navigator for InMemoryGraph {
         None
     }
 
-    fn run_benchmarks(&mut self, nmb_queries: usize) -> CollectedBenchmarks {
+    fn benchmark_theta_star(&mut self, start_node: u32, end_node: u32, query_id: usize) -> Option<BenchmarkResult> {
+        let polygon_test = self.polygon_test.as_ref()?;
+        // an empty cache, so that the run time does not depend on the previous queries
+        let mut line_of_sight_cache = LineOfSightCache::default();
+        let mut theta_star = ThetaStar::new(&self.graph, LineOfSight::new(polygon_test), &mut line_of_sight_cache, start_node);
+        let start_time = Instant::now();
+        if let Some(route_and_distance) = theta_star.find_route(end_node) {
+            let route: Vec<u32> = route_and_distance.0;
+            let distance = route_and_distance.1;
+            let time: u128 = start_time.elapsed().as_nanos();
+            println!("Theta Star calculated route from {} to {} with distance {} and number_nodes {} in {} ns, or {} ms",
+                     start_node, end_node, distance, route.len(), start_time.elapsed().as_nanos(), start_time.elapsed().as_millis());
+            return Some(BenchmarkResult {
+                start_node: self.graph.nodes[start_node as usize],
+                end_node: self.graph.nodes[end_node as usize],
+                nmb_nodes: route.len(),
+                distance: weight_to_meters(distance),
+                time: u64::try_from(time).expect("time too big"),
+                query_id,
+                amount_nodes_popped: route_and_distance.2,
+            });
+        }
+        None
+    }
+
+    fn run_benchmarks(&mut self, nmb_queries: usize, include_theta_star: bool) -> CollectedBenchmarks {
         println!("starting benchmarks");
         let mut dijkstra_results_list: Vec<BenchmarkResult> = vec![];
         let mut dijkstra_time_per_distance: Vec<f32> = vec![];
         let mut a_star_results_list: Vec<BenchmarkResult> = vec![];
         let mut bd_dijkstra_results_list: Vec<BenchmarkResult> = vec![];
         let mut ch_results_list: Vec<BenchmarkResult> = vec![];
+        let mut theta_star_results_list: Vec<BenchmarkResult> = vec![];
+        let mut theta_star_improvements: Vec<DistanceImprovement> = vec![];
 
         let random_nodes: Vec<Node> = self.graph.nodes.choose_multiple(&mut rand::thread_rng(), nmb_queries + 1).cloned().collect();
 
-        for i in 0..random_nodes.len() - 1 {
-            let start_node = self.nearest_neighbor.as_ref().unwrap().find_nearest_neighbor(&random_nodes[i]);
-            let end_node = self.nearest_neighbor.as_ref().unwrap().find_nearest_neighbor(&random_nodes[i + 1]);
+        // a graph with less than two nodes has no queries
+        for i in 0..random_nodes.len().saturating_sub(1) {
+            EventBus::global().publish_progress(Task::Benchmark, (i * 100 / nmb_queries.max(1)) as u32);
+            let nearest_neighbor = self.nearest_neighbor.as_ref().unwrap();
+            let (start_node, end_node) = match (nearest_neighbor.find_nearest_neighbor(&random_nodes[i]), nearest_neighbor.find_nearest_neighbor(&random_nodes[i + 1])) {
+                (Some(start_node), Some(end_node)) => (start_node, end_node),
+                _ => continue,
+            };
+            if !self.graph.is_reachable(start_node, end_node) {
+                // skip pairs of different components, dijkstra would search the whole component of the start node
+                continue;
+            }
 
             // BASELINE DIJKSTRA, every result has to be equivalent
             let possible_dijkstra_result = self.benchmark_dijkstra(start_node, end_node, i);
 
             if possible_dijkstra_result.is_none() {
-                // some routes can not be calculated because there exist some nodes without neighbors
                 continue;
             }
 
             let dijkstra_result = possible_dijkstra_result.expect("dijkstra result not available");
-            dijkstra_time_per_distance.push((dijkstra_result.distance as u64 / dijkstra_result.time) as f32);
+            if dijkstra_result.time > 0 {
+                dijkstra_time_per_distance.push((dijkstra_result.distance / dijkstra_result.time as f64) as f32);
+            }
             dijkstra_results_list.push(dijkstra_result);
             println!("Got dijkstra result with time {}", dijkstra_result.time);
 
@@ -217,8 +322,8 @@ impl Navigator for InMemoryGraph {
                     let time_diff: i64 = dijkstra_result.time as i64 - a_star_res.time as i64;
                     println!("Got a_star result with time {} diff to dijkstra {}", a_star_res.time, time_diff);
                 } else {
-                    println!("{}BAD RESULT A STAR nmb nodes dijkstra {} nmb nodes a star {} length diff {}{}",
-                             color::Fg(color::Red), a_star_res.nmb_nodes, dijkstra_result.nmb_nodes, dijkstra_result.distance as i32 - a_star_res.distance as i32, color::Fg(color::Reset))
+                    println!("{}BAD RESULT A STAR nmb nodes dijkstra {} nmb nodes a star {} length diff {:.1} m{}",
+                             color::Fg(color::Red), a_star_res.nmb_nodes, dijkstra_result.nmb_nodes, dijkstra_result.distance - a_star_res.distance, color::Fg(color::Reset))
                 }
             }
 
@@ -229,8 +334,8 @@ impl Navigator for InMemoryGraph {
                     let time_diff: i64 = dijkstra_result.time as i64 - bd_dijkstra_res.time as i64;
                     println!("Got bd dijkstra result with time {} diff to dijkstra {}", bd_dijkstra_res.time, time_diff);
                 } else {
-                    println!("{}BAD RESULT BD DIJKSTRA nmb nodes dijkstra {} nmb nodes bd dijkstra {} length diff {}{}",
-                             color::Fg(color::Red), dijkstra_result.nmb_nodes, bd_dijkstra_res.nmb_nodes, dijkstra_result.distance as i32 - bd_dijkstra_res.distance as i32, color::Fg(color::Reset))
+                    println!("{}BAD RESULT BD DIJKSTRA nmb nodes dijkstra {} nmb nodes bd dijkstra {} length diff {:.1} m{}",
+                             color::Fg(color::Red), dijkstra_result.nmb_nodes, bd_dijkstra_res.nmb_nodes, dijkstra_result.distance - bd_dijkstra_res.distance, color::Fg(color::Reset))
                 }
             }
 
@@ -241,9 +346,25 @@ impl Navigator for InMemoryGraph {
                     let time_diff: i64 = dijkstra_result.time as i64 - ch_res.time as i64;
                     println!("Got ch result with time {} diff to dijkstra {}", ch_res.time, time_diff);
                 } else {
-                    println!("{}BAD RESULT CH nmb nodes dijkstra {} nmb nodes ch {} length diff {} time {}{}",
-                             color::Fg(color::Red), dijkstra_result.nmb_nodes, ch_res.nmb_nodes, dijkstra_result.distance as i32 - ch_res.distance as i32, ch_res.time, color::Fg(color::Reset))
+                    println!("{}BAD RESULT CH nmb nodes dijkstra {} nmb nodes ch {} length diff {:.1} m time {}{}",
+                             color::Fg(color::Red), dijkstra_result.nmb_nodes, ch_res.nmb_nodes, dijkstra_result.distance - ch_res.distance, ch_res.time, color::Fg(color::Reset))
+                }
+            }
+
+            // any-angle routes are not equivalent, only their length is compared to dijkstra
+            if !include_theta_star {
+                continue;
+            }
+            let theta_star_result = self.benchmark_theta_star(start_node, end_node, i);
+            if let Some(theta_star_res) = theta_star_result {
+                let improvement = DistanceImprovement::new(i, dijkstra_result.distance, theta_star_res.distance);
+                if improvement.improvement >= 0.0 {
+                    println!("Got theta star result with time {} and {:.1} m ({:.2} %) shorter than dijkstra", theta_star_res.time, improvement.improvement, improvement.improvement_percent);
+                } else {
+                    println!("{}LONGER RESULT THETA STAR length diff {:.1} m{}", color::Fg(color::Red), improvement.improvement, color::Fg(color::Reset));
                 }
+                theta_star_results_list.push(theta_star_res);
+                theta_star_improvements.push(improvement);
             }
         }
         let results = CollectedBenchmarks {
@@ -259,8 +380,65 @@ impl Navigator for InMemoryGraph {
             ch: AlgoBenchmark {
                 results: ch_results_list,
             },
+            theta_star: AlgoBenchmark {
+                results: theta_star_results_list,
+            },
+            theta_star_improvement: DistanceComparison::new(theta_star_improvements),
+        };
+        if include_theta_star {
+            println!("Theta star routes are {:.2} % shorter than dijkstra routes on average, at most {:.2} %",
+                     results.theta_star_improvement.mean_improvement_percent, results.theta_star_improvement.max_improvement_percent);
+        }
+
+        results
+    }
+
+    fn find_nearest_facilities(&self, position: Node, k: usize) -> Option<Vec<NearestFacility>> {
+        if self.facility_registry.is_empty() {
+            println!("Could not search facilities. No facilities are registered");
+            return None;
+        }
+        let snapping = WaterSnapping::new(self.nearest_neighbor.as_ref()?, LineOfSight::new(self.polygon_test.as_ref()?));
+        let (start_node, _) = snapping.snap(&position, &self.graph.nodes)?;
+        let start_time = Instant::now();
+        let mut facility_search = FacilitySearch::new(self.adjacency_array.as_ref()?, start_node);
+        let nearest = facility_search.find_nearest(self.facility_registry.node_to_facilities(), k);
+        println!("Found {} nearest facilities for node {} in {} ms", nearest.len(), start_node, start_time.elapsed().as_millis());
+        Some(nearest.into_iter().map(|(facility_index, node, distance)| {
+            NearestFacility {
+                facility: self.facility_registry.get_facility(facility_index).clone(),
+                node: self.graph.nodes[node as usize],
+                distance: weight_to_meters(distance),
+            }
+        }).collect())
+    }
+
+    fn query_nodes(&self, query: SpatialQuery) -> Option<Vec<NearbyNode>> {
+        let nearest_neighbor = self.nearest_neighbor.as_ref()?;
+        let ids_and_distances: Vec<(u32, Option<f64>)> = match query {
+            SpatialQuery::KNearest { position, k } => nearest_neighbor.find_k_nearest_neighbors(&position, k).into_iter().map(|(id, dist)| (id, Some(dist))).collect(),
+            SpatialQuery::Radius { position, radius } => nearest_neighbor.find_neighbors_in_radius(&position, radius).into_iter().map(|(id, dist)| (id, Some(dist))).collect(),
+            SpatialQuery::BoundingBox { lon_min, lat_min, lon_max, lat_max } => nearest_neighbor.find_neighbors_in_bbox(lon_min, lat_min, lon_max, lat_max).into_iter().map(|id| (id, None)).collect(),
         };
+        Some(ids_and_distances.into_iter().map(|(id, distance)| NearbyNode { id, node: self.graph.nodes[id as usize], distance }).collect())
+    }
+
+    fn snapshot(&self) -> Option<GraphSnapshot> {
+        if self.graph.nodes.is_empty() {
+            return None;
+        }
+        Some(GraphSnapshot { graph: self.graph.clone(), polygon_test: self.polygon_test.clone(), number_nodes: self.number_nodes })
+    }
+}
+
+fn load_polygon_test() -> Result<NestedPolygonTest, String> {
+    let config = Config::global();
+    Ok(create_polygon_test(&read_or_create_polygons(config.coastlines_file(), config.force_rebuild_graph())?))
+}
 
-        return results;
+fn load_facility_registry(snapping: &WaterSnapping, nodes: &[Node]) -> FacilityRegistry {
+    if let Some(facilities_file) = Config::global().facilities_file() {
+        return FacilityRegistry::new(read_facilities(facilities_file), snapping, nodes);
     }
+    FacilityRegistry::empty()
 }
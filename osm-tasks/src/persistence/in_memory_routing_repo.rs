@@ -1,49 +1,105 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use serde::{Deserialize, Serialize};
 use crate::persistence::routing_repo::RoutingRepo;
 use crate::model::grid_graph::Node;
+use crate::model::ship_route::{ShipRoute, RoutingAlgorithm};
+use crate::model::route_error::RouteError;
+use crate::model::route_job::{RouteJob, JobState, now_millis};
+
+// finished, failed and cancelled jobs are removed after this time or if there are more than
+// MAX_TERMINAL_JOBS of them, starting with the job which was finished first
+const TERMINAL_JOB_TTL_MS: u64 = 60 * 60 * 1000;
+const MAX_TERMINAL_JOBS: usize = 1000;
+
+struct JobEntry {
+    job: RouteJob,
+    route: Option<ShipRoute>,
+    cancelled: Arc<AtomicBool>,
+}
 
 pub(crate) struct InMemoryRoutingRepo {
-    routes: Vec<ShipRoute>,
+    jobs: HashMap<u32, JobEntry>,
+    // ids are never reused, also not for removed jobs
+    next_id: u32,
+}
+
+impl InMemoryRoutingRepo {
+    // removes the terminal jobs which are expired or exceed the maximum number of terminal jobs
+    fn evict_jobs(&mut self, now: u64) {
+        self.jobs.retain(|_, entry| !entry.job.state.is_terminal() || entry.job.finished_at.unwrap_or(now) + TERMINAL_JOB_TTL_MS > now);
+        let mut terminal_jobs: Vec<(u64, u32)> = self.jobs.values()
+            .filter(|entry| entry.job.state.is_terminal())
+            .map(|entry| (entry.job.finished_at.unwrap_or(now), entry.job.id))
+            .collect();
+        if terminal_jobs.len() > MAX_TERMINAL_JOBS {
+            terminal_jobs.sort_unstable();
+            for (_, id) in terminal_jobs.iter().take(terminal_jobs.len() - MAX_TERMINAL_JOBS) {
+                self.jobs.remove(id);
+            }
+        }
+    }
 }
 
 impl RoutingRepo for InMemoryRoutingRepo {
     fn new() -> InMemoryRoutingRepo {
         InMemoryRoutingRepo {
-            routes: Vec::new()
+            jobs: HashMap::new(),
+            next_id: 0,
         }
     }
 
-    fn add_route(&mut self, route: ShipRoute) -> usize {
-        self.routes.push(route);
-        self.routes.len()
+    fn create_job(&mut self) -> (u32, Arc<AtomicBool>) {
+        self.evict_jobs(now_millis());
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.jobs.insert(id, JobEntry { job: RouteJob::new(id), route: None, cancelled: cancelled.clone() });
+        (id, cancelled)
     }
 
-    fn get_route(&self, id: usize) -> Option<ShipRoute> {
-        if self.routes.get(id).is_some() {
-            return Some(self.routes[id].clone());
+    fn start_job(&mut self, id: u32) -> bool {
+        match self.jobs.get_mut(&id) {
+            Some(entry) if entry.job.state == JobState::Pending => {
+                entry.job.start();
+                true
+            }
+            _ => false,
         }
-        return None;
     }
 
-    fn has_route(&self, id: usize) -> bool {
-        self.routes.len() <= id
+    fn finish_job(&mut self, id: u32, result: Result<ShipRoute, RouteError>) {
+        if let Some(entry) = self.jobs.get_mut(&id) {
+            if entry.job.state.is_terminal() {
+                // cancelled while the route was calculated
+                return;
+            }
+            match result {
+                Ok(route) => {
+                    entry.route = Some(route);
+                    entry.job.finish(Ok(()));
+                }
+                Err(error) => entry.job.finish(Err(error)),
+            }
+        }
     }
 
-    fn get_job_id(&self) -> u32 {
-        self.routes.len() as u32
+    fn cancel_job(&mut self, id: u32) -> Option<RouteJob> {
+        let entry = self.jobs.get_mut(&id)?;
+        if !entry.job.state.is_terminal() {
+            entry.cancelled.store(true, Ordering::Relaxed);
+            entry.job.finish(Err(RouteError::Cancelled));
+        }
+        Some(entry.job.clone())
     }
-}
 
-#[derive(Serialize, Deserialize, JsonSchema, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct ShipRoute {
-    distance: u32,
-    nodes: Vec<Node>,
-}
+    fn get_job(&self, id: u32) -> Option<RouteJob> {
+        self.jobs.get(&id).map(|entry| entry.job.clone())
+    }
 
-impl ShipRoute {
-    pub fn new(nodes: Vec<Node>, distance: u32) -> ShipRoute {
-        ShipRoute { nodes, distance }
+    fn get_route(&self, id: u32) -> Option<ShipRoute> {
+        self.jobs.get(&id).and_then(|entry| entry.route.clone())
     }
 }
 
@@ -61,6 +117,9 @@ pub(crate) struct Status {
 pub struct RouteRequest {
     pub(crate) start: Node,
     pub(crate) end: Node,
+    // dijkstra if not set
+    #[serde(default)]
+    pub(crate) algorithm: RoutingAlgorithm,
 }
 
 impl RouteRequest {
@@ -70,4 +129,44 @@ impl RouteRequest {
     pub fn end(&self) -> Node {
         self.end
     }
+    pub fn algorithm(&self) -> RoutingAlgorithm {
+        self.algorithm
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expired_terminal_jobs_are_removed() {
+        let mut repo = InMemoryRoutingRepo::new();
+        let (finished, _) = repo.create_job();
+        let (running, _) = repo.create_job();
+        repo.start_job(running);
+        repo.cancel_job(finished);
+        let finished_at = repo.get_job(finished).unwrap().finished_at.unwrap();
+        repo.evict_jobs(finished_at + TERMINAL_JOB_TTL_MS - 1);
+        assert!(repo.get_job(finished).is_some());
+        repo.evict_jobs(finished_at + TERMINAL_JOB_TTL_MS);
+        assert!(repo.get_job(finished).is_none());
+        assert_eq!(repo.get_job(running).unwrap().state, JobState::Running);
+        // ids of removed jobs are not reused
+        assert_eq!(repo.create_job().0, 2);
+    }
+
+    #[test]
+    fn oldest_terminal_jobs_are_removed_first() {
+        let mut repo = InMemoryRoutingRepo::new();
+        let (pending, _) = repo.create_job();
+        for _ in 0..MAX_TERMINAL_JOBS + 5 {
+            let (id, _) = repo.create_job();
+            repo.cancel_job(id);
+        }
+        repo.evict_jobs(now_millis());
+        assert_eq!(repo.jobs.len(), MAX_TERMINAL_JOBS + 1);
+        assert!(repo.get_job(1).is_none());
+        assert!(repo.get_job(pending).is_some());
+        assert!(repo.get_job(MAX_TERMINAL_JOBS as u32 + 5).is_some());
+    }
 }
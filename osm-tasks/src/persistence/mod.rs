@@ -4,3 +4,6 @@ pub(crate) mod in_memory_navigator;
 pub(crate) mod navigator;
 pub(crate) mod benchmark_repo;
 pub(crate) mod in_memory_benchmark_repo;
+pub(crate) mod event_bus;
+pub(crate) mod build_tracker;
+pub(crate) mod graph_snapshot;
@@ -1,16 +1,31 @@
-use crate::persistence::in_memory_routing_repo::{ShipRoute, RouteRequest};
+use std::sync::atomic::AtomicBool;
+use crate::persistence::in_memory_routing_repo::RouteRequest;
+use crate::model::ship_route::ShipRoute;
 use crate::model::benchmark::{BenchmarkResult, CollectedBenchmarks};
+use crate::model::facility::NearestFacility;
+use crate::model::grid_graph::Node;
+use crate::model::route_error::RouteError;
+use crate::model::spatial_query::{NearbyNode, SpatialQuery};
+use crate::persistence::graph_snapshot::GraphSnapshot;
 
 pub trait Navigator: Send + Sync {
     fn new() -> Self
     where
         Self: Sized;
-    fn build_graph(&mut self, number_nodes: usize);
-    fn calculate_route(&mut self, route_request: RouteRequest) -> Option<ShipRoute>;
+    // the navigator keeps its previous graph if the build fails
+    fn build_graph(&mut self, number_nodes: usize) -> Result<(), String>;
+    // the search is stopped with RouteError::Cancelled as soon as the cancelled flag is set
+    fn calculate_route(&mut self, route_request: RouteRequest, cancelled: &AtomicBool) -> Result<ShipRoute, RouteError>;
     fn benchmark_dijkstra(&mut self, start_node: u32, end_node: u32, query_id: usize) -> Option<BenchmarkResult>;
     fn benchmark_a_star(&mut self, start_node: u32, end_node: u32, query_id: usize) -> Option<BenchmarkResult>;
     fn benchmark_bd_dijkstra(&mut self, start_node: u32, end_node: u32, query_id: usize) -> Option<BenchmarkResult>;
     fn get_number_nodes(&self) -> u32;
     fn benchmark_ch(&mut self, start_node: u32, end_node: u32, query_id: usize) -> Option<BenchmarkResult>;
-    fn run_benchmarks(&mut self, nmb_queries: usize) -> CollectedBenchmarks;
+    fn benchmark_theta_star(&mut self, start_node: u32, end_node: u32, query_id: usize) -> Option<BenchmarkResult>;
+    // Theta* is only benchmarked if include_theta_star is set
+    fn run_benchmarks(&mut self, nmb_queries: usize, include_theta_star: bool) -> CollectedBenchmarks;
+    fn find_nearest_facilities(&self, position: Node, k: usize) -> Option<Vec<NearestFacility>>;
+    fn query_nodes(&self, query: SpatialQuery) -> Option<Vec<NearbyNode>>;
+    // shared reference to the current graph, None if no graph is built
+    fn snapshot(&self) -> Option<GraphSnapshot>;
 }
@@ -1,11 +1,20 @@
-use crate::persistence::in_memory_routing_repo::ShipRoute;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use crate::model::ship_route::ShipRoute;
+use crate::model::route_error::RouteError;
+use crate::model::route_job::RouteJob;
 
 pub trait RoutingRepo: Send + Sync {
     fn new() -> Self
     where
         Self: Sized;
-    fn add_route(&mut self, route: ShipRoute) -> usize;
-    fn get_route(&self, id: usize) -> Option<ShipRoute>;
-    fn has_route(&self, id: usize) -> bool;
-    fn get_job_id(&self) -> u32;
+    /// creates a pending job with a new unique id, the flag is set when the job is cancelled
+    fn create_job(&mut self) -> (u32, Arc<AtomicBool>);
+    /// marks the job as running, returns false if the job has been cancelled before it was started
+    fn start_job(&mut self, id: u32) -> bool;
+    fn finish_job(&mut self, id: u32, result: Result<ShipRoute, RouteError>);
+    /// cancels a pending or running job and returns its state, None if the job does not exist
+    fn cancel_job(&mut self, id: u32) -> Option<RouteJob>;
+    fn get_job(&self, id: u32) -> Option<RouteJob>;
+    fn get_route(&self, id: u32) -> Option<ShipRoute>;
 }